@@ -0,0 +1,226 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use crate::ast::*;
+use crate::visit::{walk_expression, Visitor};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn warning(message: String) -> Self {
+        Diagnostic { severity: Severity::Warning, message }
+    }
+
+    pub fn error(message: String) -> Self {
+        Diagnostic { severity: Severity::Error, message }
+    }
+}
+
+/// What the analyzer knows about a name in scope
+#[derive(Debug, Clone, Copy)]
+struct Binding {
+    value_type: Option<&'static str>,
+    is_const: bool,
+}
+
+/// Static checks over a parsed program that don't require executing it
+pub struct Analyzer {
+    scopes: Vec<HashMap<String, Binding>>,
+    allowed_shadows: HashSet<String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Default for Analyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Analyzer {
+    pub fn new() -> Self {
+        Analyzer {
+            scopes: Vec::new(),
+            allowed_shadows: HashSet::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Silence the shadowing warning for bindings with this name
+    pub fn allow_shadowing(mut self, name: &str) -> Self {
+        self.allowed_shadows.insert(name.to_string());
+        self
+    }
+
+    pub fn analyze(mut self, program: &Program) -> Vec<Diagnostic> {
+        self.push_scope();
+        for variable in &program.variables {
+            self.declare(variable);
+        }
+
+        for workflow in &program.workflows {
+            #[cfg(feature = "cron")]
+            self.check_schedule(workflow);
+            self.push_scope();
+            for variable in &workflow.variables {
+                self.declare(variable);
+            }
+            self.analyze_steps(&workflow.steps);
+            if let Some(handler) = &workflow.on_error {
+                self.analyze_block(&handler.steps);
+            }
+            self.pop_scope();
+        }
+
+        self.pop_scope();
+        self.diagnostics
+    }
+
+    fn analyze_steps(&mut self, steps: &[Step]) {
+        self.check_forward_references(steps);
+        for step in steps {
+            match &step.content {
+                StepContent::Command(_) | StepContent::CallWorkflow(_) | StepContent::Return(_) | StepContent::Assignment(_) => {}
+                StepContent::Declaration(variable) => self.declare(variable),
+                StepContent::Conditional(conditional) => {
+                    self.analyze_block(&conditional.if_steps);
+                    if let Some(else_steps) = &conditional.else_steps {
+                        self.analyze_block(else_steps);
+                    }
+                }
+                StepContent::WhileLoop(while_loop) => {
+                    self.analyze_block(&while_loop.body);
+                }
+                StepContent::ForEach(for_each) => {
+                    self.push_scope();
+                    self.declare_name(&for_each.var_name, Binding { value_type: None, is_const: false });
+                    self.analyze_steps(&for_each.body);
+                    self.pop_scope();
+                }
+                StepContent::TryCatch(try_catch) => {
+                    self.analyze_block(&try_catch.try_steps);
+                    self.push_scope();
+                    self.declare_name(&try_catch.catch_variable, Binding { value_type: Some("string"), is_const: false });
+                    self.analyze_steps(&try_catch.catch_steps);
+                    self.pop_scope();
+                }
+            }
+        }
+    }
+
+    /// A step that reads a step coming after it in the same block finds no result
+    /// there yet. References across blocks are left to `Program::validate`.
+    fn check_forward_references(&mut self, steps: &[Step]) {
+        for (index, step) in steps.iter().enumerate() {
+            let mut references = StepReferences::default();
+            references.visit_step(step);
+            for id in references.0 {
+                if steps[index + 1..].iter().any(|later| later.id == id) {
+                    self.diagnostics.push(Diagnostic::warning(format!(
+                        "Line {}: step {} is read before it runs, as it comes later in the same block",
+                        step.line, id
+                    )));
+                }
+            }
+        }
+    }
+
+    /// `@schedule` must hold a cron expression, with or without a seconds field
+    #[cfg(feature = "cron")]
+    fn check_schedule(&mut self, workflow: &Workflow) {
+        if let Some(schedule) = &workflow.schedule {
+            if let Err(error) = croner::Cron::new(schedule).with_seconds_optional().parse() {
+                self.diagnostics.push(Diagnostic::error(format!(
+                    "Workflow '{}' has an invalid schedule '{}': {}",
+                    workflow.name, schedule, error
+                )));
+            }
+        }
+    }
+
+    fn analyze_block(&mut self, steps: &[Step]) {
+        self.push_scope();
+        self.analyze_steps(steps);
+        self.pop_scope();
+    }
+
+    fn declare(&mut self, variable: &VariableDeclaration) {
+        let binding = Binding {
+            value_type: literal_type(&variable.value),
+            is_const: variable.keyword == "const",
+        };
+        self.declare_name(&variable.name, binding);
+    }
+
+    fn declare_name(&mut self, name: &str, binding: Binding) {
+        // Redeclaring in the same scope is not shadowing, only outer scopes count
+        let outer = self.scopes[..self.scopes.len() - 1]
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name));
+
+        if let Some(outer) = outer {
+            if !self.allowed_shadows.contains(name) {
+                let message = match (outer.value_type, binding.value_type) {
+                    // Constants are usually meant to hold one value everywhere, so call this out on its own
+                    _ if outer.is_const => format!("Constant '{}' is shadowed in a nested scope", name),
+                    (Some(outer_type), Some(inner_type)) if outer_type != inner_type => format!(
+                        "Variable '{}' shadows an outer {} binding with a {} value",
+                        name, outer_type, inner_type
+                    ),
+                    _ => format!("Variable '{}' shadows an outer binding", name),
+                };
+                self.diagnostics.push(Diagnostic::warning(message));
+            }
+        }
+
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), binding);
+        }
+    }
+
+    fn push_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn pop_scope(&mut self) {
+        self.scopes.pop();
+    }
+}
+
+/// Ids of the steps a step reads, in its nested steps too
+#[derive(Default)]
+pub(crate) struct StepReferences(pub(crate) BTreeSet<u32>);
+
+impl Visitor for StepReferences {
+    fn visit_expression(&mut self, expression: &Expression) {
+        if let ExpressionKind::StepReference { step_id, .. } = &expression.kind {
+            self.0.insert(*step_id);
+        }
+        walk_expression(self, expression);
+    }
+}
+
+/// Type of a literal initializer, when it can be known without executing
+fn literal_type(expression: &Expression) -> Option<&'static str> {
+    match &expression.kind {
+        ExpressionKind::StringLiteral(_)
+        | ExpressionKind::Interpolation(_)
+        | ExpressionKind::EnvironmentVariable { .. } => Some("string"),
+        ExpressionKind::NumberLiteral(_) => Some("number"),
+        _ => None,
+    }
+}
+
+/// Run all static checks over a program with the default settings
+pub fn analyze_program(program: &Program) -> Vec<Diagnostic> {
+    Analyzer::new().analyze(program)
+}
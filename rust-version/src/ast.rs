@@ -1,9 +1,55 @@
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use crate::value::format_number;
+
+/// Where a node was parsed from: 1-based lines and columns, the end column being
+/// just past the node's last character. All zero when unknown, such as for nodes
+/// built in code.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Span {
+    pub start_line: usize,
+    pub start_col: usize,
+    pub end_line: usize,
+    pub end_col: usize,
+}
+
+impl Span {
+    pub fn new(start_line: usize, start_col: usize, end_line: usize, end_col: usize) -> Self {
+        Span { start_line, start_col, end_line, end_col }
+    }
+    
+    pub fn is_known(&self) -> bool {
+        self.start_line != 0
+    }
+    
+    /// From the start of `self` to the end of `end`; unknown if either is
+    pub fn to(self, end: Span) -> Span {
+        if self.is_known() && end.is_known() {
+            Span::new(self.start_line, self.start_col, end.end_line, end.end_col)
+        } else {
+            Span::default()
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Program {
     pub workflows: Vec<Workflow>,
     pub variables: Vec<VariableDeclaration>,
+    #[serde(default)]
+    pub functions: Vec<FunctionDeclaration>,
+}
+
+/// `fn name(a, b) = expression`: the body sees its parameters, program-level
+/// variables and other functions, but no step results
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionDeclaration {
+    pub name: String,
+    pub parameters: Vec<String>,
+    pub body: Expression,
+    #[serde(default)]
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -11,24 +57,67 @@ pub struct Workflow {
     pub name: String,
     pub variables: Vec<VariableDeclaration>,
     pub steps: Vec<Step>,
+    #[serde(default)]
+    pub on_error: Option<ErrorHandler>,
+    /// Cron expression from `@schedule(...)`, for the host's scheduler; the executor ignores it
+    #[serde(default)]
+    pub schedule: Option<String>,
+    /// Workflows named by `includes`; the parser has already merged their variables and steps in
+    #[serde(default)]
+    pub includes: Vec<String>,
+}
+
+/// Steps run when a step of the workflow fails, with the error message bound to `variable`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorHandler {
+    pub variable: String,
+    pub steps: Vec<Step>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Step {
+    /// Step number; bare statements such as assignments use 0
     pub id: u32,
     pub content: StepContent,
+    /// Source line the step starts on, 0 when unknown such as for generated steps
+    #[serde(default)]
+    pub line: usize,
+    #[serde(default)]
+    pub span: Span,
+    /// The `///` doc comment written above the step
+    #[serde(default)]
+    pub description: Option<String>,
+    /// `when (condition)` after the step: it only runs when the condition holds
+    #[serde(default)]
+    pub guard: Option<Expression>,
 }
 
+/// Stored by position like `ExpressionKind`: add new variants at the end
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum StepContent {
     Command(Command),
     Conditional(ConditionalStatement),
+    WhileLoop(WhileLoop),
+    ForEach(ForEach),
+    TryCatch(TryCatch),
+    CallWorkflow(CallWorkflow),
+    Return(ReturnStatement),
+    Assignment(Assignment),
+    /// `let`/`var`/`const` inside a block such as an if branch
+    Declaration(VariableDeclaration),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Command {
     pub name: String,
+    /// Positional arguments, which always come before named ones
     pub arguments: Vec<Expression>,
+    /// `name: value` arguments, in source order
+    #[serde(default)]
+    pub named_arguments: IndexMap<String, Expression>,
+    /// From the command name to its closing parenthesis
+    #[serde(default)]
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,23 +127,81 @@ pub struct ConditionalStatement {
     pub else_steps: Option<Vec<Step>>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WhileLoop {
+    pub condition: Expression,
+    pub body: Vec<Step>,
+}
+
+/// `for item in [..] { .. }`; `var_name` is only bound inside the body
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForEach {
+    pub var_name: String,
+    pub iterable: Expression,
+    pub body: Vec<Step>,
+}
+
+/// `try { .. } catch { .. }`; the catch steps run with the error message bound to
+/// `catch_variable` when a try step errors or reports `success: false`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TryCatch {
+    pub try_steps: Vec<Step>,
+    pub catch_variable: String,
+    pub catch_steps: Vec<Step>,
+}
+
+/// `call "Name"` runs another workflow of the program with only program-level
+/// bindings visible to it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallWorkflow {
+    pub name: String,
+}
+
+/// `return` or `return value`: ends the workflow, handing the value to its caller
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReturnStatement {
+    pub value: Option<Expression>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Assignment {
+    pub name: String,
+    pub value: Expression,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VariableDeclaration {
     pub keyword: String, // let, var, const
     pub name: String,
     pub value: Expression,
+    #[serde(default)]
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Expression {
+    pub kind: ExpressionKind,
+    #[serde(default)]
+    pub span: Span,
 }
 
+/// Compiled programs store variants by position: add new ones at the end, or bump
+/// `BINARY_FORMAT_VERSION` when inserting one shifts the rest
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum Expression {
+pub enum ExpressionKind {
     StringLiteral(String),
     NumberLiteral(f64),
     Identifier(String),
+    ArrayLiteral(Vec<Expression>),
     BinaryExpression {
         left: Box<Expression>,
         operator: String,
         right: Box<Expression>,
     },
+    UnaryExpression {
+        operator: String,
+        operand: Box<Expression>,
+    },
     PropertyAccess {
         object: Box<Expression>,
         property: String,
@@ -63,40 +210,213 @@ pub enum Expression {
         step_id: u32,
         property: Option<String>,
     },
+    /// `prev`, the most recently executed step of the current workflow
+    PreviousStep {
+        property: Option<String>,
+    },
+    /// `workflow("Name")`, an earlier workflow of the program; `.result` is what it returned
+    WorkflowReference {
+        name: String,
+    },
+    /// `"${base}/users/${id}"`: literal text alternating with embedded expressions
+    Interpolation(Vec<InterpolationPart>),
+    /// `env("NAME")` or `env("NAME", "default")`, read from the process environment when evaluated
+    EnvironmentVariable {
+        name: String,
+        default: Option<String>,
+    },
+    /// `callee(arguments)`; fewer arguments than the function takes give back a
+    /// function with them bound
+    Call {
+        callee: Box<Expression>,
+        arguments: Vec<Expression>,
+    },
+}
+
+/// A piece of an interpolated string
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum InterpolationPart {
+    Literal(String),
+    Expression(Expression),
+}
+
+impl InterpolationPart {
+    pub fn expression(&self) -> Option<&Expression> {
+        match self {
+            InterpolationPart::Expression(expression) => Some(expression),
+            InterpolationPart::Literal(_) => None,
+        }
+    }
+    
+    pub fn expression_mut(&mut self) -> Option<&mut Expression> {
+        match self {
+            InterpolationPart::Expression(expression) => Some(expression),
+            InterpolationPart::Literal(_) => None,
+        }
+    }
+}
+
+impl From<ExpressionKind> for Expression {
+    fn from(kind: ExpressionKind) -> Self {
+        Expression { kind, span: Span::default() }
+    }
 }
 
 impl Expression {
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+    
     pub fn string(value: &str) -> Self {
-        Expression::StringLiteral(value.to_string())
+        ExpressionKind::StringLiteral(value.to_string()).into()
     }
     
     pub fn number(value: f64) -> Self {
-        Expression::NumberLiteral(value)
+        ExpressionKind::NumberLiteral(value).into()
     }
     
     pub fn identifier(name: &str) -> Self {
-        Expression::Identifier(name.to_string())
+        ExpressionKind::Identifier(name.to_string()).into()
     }
     
+    pub fn array(elements: Vec<Expression>) -> Self {
+        ExpressionKind::ArrayLiteral(elements).into()
+    }
+    
+    /// Spans from the start of `left` to the end of `right`
     pub fn binary(left: Expression, operator: &str, right: Expression) -> Self {
-        Expression::BinaryExpression {
+        let span = left.span.to(right.span);
+        Expression::from(ExpressionKind::BinaryExpression {
             left: Box::new(left),
             operator: operator.to_string(),
             right: Box::new(right),
+        })
+        .with_span(span)
+    }
+    
+    pub fn unary(operator: &str, operand: Expression) -> Self {
+        ExpressionKind::UnaryExpression {
+            operator: operator.to_string(),
+            operand: Box::new(operand),
         }
+        .into()
     }
     
     pub fn property_access(object: Expression, property: &str) -> Self {
-        Expression::PropertyAccess {
+        ExpressionKind::PropertyAccess {
             object: Box::new(object),
             property: property.to_string(),
         }
+        .into()
+    }
+    
+    pub fn call(callee: Expression, arguments: Vec<Expression>) -> Self {
+        ExpressionKind::Call {
+            callee: Box::new(callee),
+            arguments,
+        }
+        .into()
     }
     
     pub fn step_reference(step_id: u32, property: Option<&str>) -> Self {
-        Expression::StepReference {
+        ExpressionKind::StepReference {
             step_id,
             property: property.map(|p| p.to_string()),
         }
+        .into()
     }
-} 
\ No newline at end of file
+    
+    pub fn previous_step(property: Option<&str>) -> Self {
+        ExpressionKind::PreviousStep { property: property.map(|p| p.to_string()) }.into()
+    }
+}
+
+/// Binding strength of binary operators, used to decide where parentheses are needed
+fn precedence(operator: &str) -> u8 {
+    match operator {
+        "||" => 1,
+        "&&" => 2,
+        "+" | "-" => 4,
+        "*" | "/" => 5,
+        _ => 3,
+    }
+}
+
+/// `text` as a string literal that reads back as the same text. Strings have no
+/// escapes, so the quotes are chosen to fit: `"..."`, then `'...'` for text holding
+/// double quotes, then verbatim `"""..."""` for text holding both or a `${`.
+pub(crate) fn quote(text: &str) -> String {
+    if text.contains("${") || (text.contains('"') && text.contains('\'')) {
+        format!("\"\"\"{}\"\"\"", text.replace("\"\"\"", "\\\"\"\""))
+    } else if text.contains('"') {
+        format!("'{}'", text)
+    } else {
+        format!("\"{}\"", text)
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ExpressionKind::StringLiteral(value) => write!(f, "{}", quote(value)),
+            ExpressionKind::NumberLiteral(value) => write!(f, "{}", format_number(*value)),
+            ExpressionKind::Identifier(name) => write!(f, "{}", name),
+            ExpressionKind::ArrayLiteral(elements) => {
+                let elements: Vec<String> = elements.iter().map(|element| element.to_string()).collect();
+                write!(f, "[{}]", elements.join(", "))
+            }
+            ExpressionKind::BinaryExpression { left, operator, right } => {
+                let level = precedence(operator);
+                match &left.kind {
+                    ExpressionKind::BinaryExpression { operator: inner, .. } if precedence(inner) < level => {
+                        write!(f, "({})", left)?
+                    }
+                    _ => write!(f, "{}", left)?,
+                }
+                write!(f, " {} ", operator)?;
+                match &right.kind {
+                    ExpressionKind::BinaryExpression { operator: inner, .. } if precedence(inner) <= level => {
+                        write!(f, "({})", right)
+                    }
+                    _ => write!(f, "{}", right),
+                }
+            }
+            ExpressionKind::UnaryExpression { operator, operand } => match &operand.kind {
+                ExpressionKind::BinaryExpression { .. } => write!(f, "{}({})", operator, operand),
+                _ => write!(f, "{}{}", operator, operand),
+            },
+            ExpressionKind::PropertyAccess { object, property } => write!(f, "{}.{}", object, property),
+            ExpressionKind::StepReference { step_id, property } => match property {
+                Some(property) => write!(f, "step {}.{}", step_id, property),
+                None => write!(f, "step {}", step_id),
+            },
+            ExpressionKind::PreviousStep { property } => match property {
+                Some(property) => write!(f, "prev.{}", property),
+                None => write!(f, "prev"),
+            },
+            ExpressionKind::WorkflowReference { name } => write!(f, "workflow({})", quote(name)),
+            ExpressionKind::EnvironmentVariable { name, default } => match default {
+                Some(default) => write!(f, "env({}, {})", quote(name), quote(default)),
+                None => write!(f, "env({})", quote(name)),
+            },
+            ExpressionKind::Interpolation(parts) => {
+                // Interpolating strings can't be triple-quoted, so only the quote character varies
+                let has_double_quote = parts.iter().any(|part| matches!(part, InterpolationPart::Literal(text) if text.contains('"')));
+                let quote = if has_double_quote { '\'' } else { '"' };
+                write!(f, "{}", quote)?;
+                for part in parts {
+                    match part {
+                        InterpolationPart::Literal(text) => write!(f, "{}", text)?,
+                        InterpolationPart::Expression(expression) => write!(f, "${{{}}}", expression)?,
+                    }
+                }
+                write!(f, "{}", quote)
+            }
+            ExpressionKind::Call { callee, arguments } => {
+                let arguments: Vec<String> = arguments.iter().map(|argument| argument.to_string()).collect();
+                write!(f, "{}({})", callee, arguments.join(", "))
+            }
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use anyhow::{anyhow, Result};
+use crate::ast::Program;
+use crate::executor::{ExecutionReport, Executor};
+
+/// Runs programs from async code. Steps still run one after another, in the
+/// order the sync `Executor` runs them, but on tokio's blocking pool, and `fetch`
+/// and `generate` await reqwest's async client there, so the runtime's worker
+/// threads stay free while a program waits on the network.
+pub struct AsyncExecutor {
+    /// Moved onto the blocking pool for each run; `None` once a run never handed it back
+    executor: Option<Executor>,
+}
+
+impl Default for AsyncExecutor {
+    fn default() -> Self {
+        Self::from(Executor::new())
+    }
+}
+
+impl AsyncExecutor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The executor underneath, e.g. to read its snapshot between runs;
+    /// `None` once a run panicked or was cancelled
+    pub fn executor(&self) -> Option<&Executor> {
+        self.executor.as_ref()
+    }
+
+    pub fn into_inner(self) -> Option<Executor> {
+        self.executor
+    }
+
+    /// Execute `program` like `Executor::execute`. Must be called from within a tokio runtime.
+    /// A run that panics, or whose future is dropped before it finishes, takes the executor
+    /// with it: the configured handlers, limits and policies are gone rather than replaced
+    /// by defaults, and this and every later call returns an error.
+    pub async fn execute(&mut self, program: &Program) -> Result<ExecutionReport> {
+        let runtime = tokio::runtime::Handle::current();
+        let mut executor = self.executor.take().ok_or_else(|| {
+            anyhow!("AsyncExecutor is unusable: an earlier run panicked or was cancelled before returning its executor")
+        })?;
+        let program = program.clone();
+        let (executor, report) = tokio::task::spawn_blocking(move || {
+            executor.set_runtime(Some(runtime));
+            let report = executor.execute(&program);
+            executor.set_runtime(None);
+            (executor, report)
+        })
+        .await
+        .map_err(|e| anyhow!("Execution task failed, the executor is lost: {}", e))?;
+        self.executor = Some(executor);
+        report
+    }
+}
+
+/// Configure with the usual `Executor` builder methods, then hand it over
+impl From<Executor> for AsyncExecutor {
+    fn from(executor: Executor) -> Self {
+        AsyncExecutor { executor: Some(executor) }
+    }
+}
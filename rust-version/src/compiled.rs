@@ -0,0 +1,101 @@
+use anyhow::Result;
+#[cfg(feature = "bincode")]
+use anyhow::anyhow;
+use indexmap::IndexMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use crate::ast::*;
+use crate::executor::{ExecutionReport, Executor};
+use crate::value::Value;
+
+/// Leading bytes of `CompiledProgram::to_bytes` output
+#[cfg(feature = "bincode")]
+const BINARY_MAGIC: &[u8; 4] = b"TMFL";
+
+/// Bumped whenever the AST changes shape, so older bytes are rejected instead of misread
+#[cfg(feature = "bincode")]
+const BINARY_FORMAT_VERSION: u32 = 4;
+
+/// A parsed program prepared for running many times. Program-level `const`s whose
+/// initializer folds to a value without running any step are evaluated on the first
+/// run and reused by every later one.
+#[derive(Debug)]
+pub struct CompiledProgram {
+    program: Program,
+    constants: OnceLock<IndexMap<String, Value>>,
+    constant_evaluations: AtomicUsize,
+}
+
+impl CompiledProgram {
+    pub fn new(program: Program) -> Self {
+        CompiledProgram {
+            program,
+            constants: OnceLock::new(),
+            constant_evaluations: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    /// Folded program-level constants by name, computed on first use
+    pub fn constants(&self) -> &IndexMap<String, Value> {
+        self.constants.get_or_init(|| {
+            let mut constants = IndexMap::new();
+            for variable in &self.program.variables {
+                if variable.keyword != "const" {
+                    continue;
+                }
+                self.constant_evaluations.fetch_add(1, Ordering::Relaxed);
+                // Anything that needs a step, a `let` or a failing operation is left to run time
+                if let Some(value) = Executor::fold_constant(&variable.value, &constants) {
+                    constants.insert(variable.name.clone(), value);
+                }
+            }
+            constants
+        })
+    }
+
+    /// How many constant initializers have been evaluated for the cache
+    pub fn constant_evaluations(&self) -> usize {
+        self.constant_evaluations.load(Ordering::Relaxed)
+    }
+
+    /// The program in a compact binary form that `from_bytes` loads without parsing.
+    /// Only programs that pass `Program::validate` are written.
+    #[cfg(feature = "bincode")]
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        self.program.validate()?;
+        let mut bytes = BINARY_MAGIC.to_vec();
+        bytes.extend_from_slice(&BINARY_FORMAT_VERSION.to_le_bytes());
+        bincode::serialize_into(&mut bytes, &self.program)
+            .map_err(|e| anyhow!("Failed to serialize program: {}", e))?;
+        Ok(bytes)
+    }
+
+    /// Load a program written by `to_bytes`, rejecting data from other format versions
+    #[cfg(feature = "bincode")]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let body = bytes
+            .strip_prefix(BINARY_MAGIC.as_slice())
+            .ok_or_else(|| anyhow!("Not a compiled program"))?;
+        let (version, body) = body
+            .split_first_chunk::<4>()
+            .ok_or_else(|| anyhow!("Compiled program is truncated"))?;
+        let version = u32::from_le_bytes(*version);
+        if version != BINARY_FORMAT_VERSION {
+            return Err(anyhow!(
+                "Compiled program has format version {}, expected {}", version, BINARY_FORMAT_VERSION
+            ));
+        }
+        let program = bincode::deserialize(body)
+            .map_err(|e| anyhow!("Failed to load compiled program: {}", e))?;
+        Ok(CompiledProgram::new(program))
+    }
+
+    /// Run the program on `executor`, reusing the folded constants
+    pub fn execute(&self, executor: &mut Executor) -> Result<ExecutionReport> {
+        executor.execute_with_constants(&self.program, self.constants())
+    }
+}
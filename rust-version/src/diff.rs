@@ -0,0 +1,150 @@
+use serde::Serialize;
+use std::fmt;
+use crate::ast::*;
+
+/// One difference between two versions of a program, found by comparing ASTs
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum Change {
+    WorkflowAdded { workflow: String },
+    WorkflowRemoved { workflow: String },
+    /// The workflow's `on_error` handler was added, removed or changed
+    ErrorHandlerModified { workflow: String },
+    StepAdded { workflow: String, step_id: u32 },
+    StepRemoved { workflow: String, step_id: u32 },
+    StepModified { workflow: String, step_id: u32 },
+    /// `workflow` is `None` for program-level variables
+    VariableAdded { workflow: Option<String>, name: String },
+    VariableRemoved { workflow: Option<String>, name: String },
+    VariableModified { workflow: Option<String>, name: String },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let location = |workflow: &Option<String>| match workflow {
+            Some(workflow) => format!("workflow '{}'", workflow),
+            None => "the program".to_string(),
+        };
+        match self {
+            Change::WorkflowAdded { workflow } => write!(f, "Workflow '{}' added", workflow),
+            Change::WorkflowRemoved { workflow } => write!(f, "Workflow '{}' removed", workflow),
+            Change::ErrorHandlerModified { workflow } => write!(f, "on_error handler of workflow '{}' modified", workflow),
+            Change::StepAdded { workflow, step_id } => write!(f, "Step {} added to workflow '{}'", step_id, workflow),
+            Change::StepRemoved { workflow, step_id } => write!(f, "Step {} removed from workflow '{}'", step_id, workflow),
+            Change::StepModified { workflow, step_id } => write!(f, "Step {} of workflow '{}' modified", step_id, workflow),
+            Change::VariableAdded { workflow, name } => write!(f, "Variable '{}' added to {}", name, location(workflow)),
+            Change::VariableRemoved { workflow, name } => write!(f, "Variable '{}' removed from {}", name, location(workflow)),
+            Change::VariableModified { workflow, name } => write!(f, "Variable '{}' of {} modified", name, location(workflow)),
+        }
+    }
+}
+
+impl Program {
+    /// What changed from `old` to `new`. Workflows are matched by name, variables by
+    /// name and top-level steps by id; a change anywhere inside a step, such as a
+    /// branch of its `if`, reports the whole step as modified. Bare statements
+    /// share id 0 and are compared as one group.
+    pub fn diff(old: &Program, new: &Program) -> Vec<Change> {
+        let mut changes = diff_variables(None, &old.variables, &new.variables);
+
+        for old_workflow in &old.workflows {
+            match new.workflows.iter().find(|workflow| workflow.name == old_workflow.name) {
+                Some(new_workflow) => changes.extend(diff_workflow(old_workflow, new_workflow)),
+                None => changes.push(Change::WorkflowRemoved { workflow: old_workflow.name.clone() }),
+            }
+        }
+        for new_workflow in &new.workflows {
+            if !old.workflows.iter().any(|workflow| workflow.name == new_workflow.name) {
+                changes.push(Change::WorkflowAdded { workflow: new_workflow.name.clone() });
+            }
+        }
+
+        changes
+    }
+}
+
+fn diff_workflow(old: &Workflow, new: &Workflow) -> Vec<Change> {
+    let name = &old.name;
+    let mut changes = diff_variables(Some(name), &old.variables, &new.variables);
+
+    let old_ids = step_ids(&old.steps);
+    let new_ids = step_ids(&new.steps);
+    for id in &old_ids {
+        if new_ids.contains(id) {
+            if !same(&steps_with_id(&old.steps, *id), &steps_with_id(&new.steps, *id)) {
+                changes.push(Change::StepModified { workflow: name.clone(), step_id: *id });
+            }
+        } else {
+            changes.push(Change::StepRemoved { workflow: name.clone(), step_id: *id });
+        }
+    }
+    for id in new_ids.iter().filter(|id| !old_ids.contains(id)) {
+        changes.push(Change::StepAdded { workflow: name.clone(), step_id: *id });
+    }
+
+    if !same(&old.on_error, &new.on_error) {
+        changes.push(Change::ErrorHandlerModified { workflow: name.clone() });
+    }
+
+    changes
+}
+
+fn diff_variables(workflow: Option<&String>, old: &[VariableDeclaration], new: &[VariableDeclaration]) -> Vec<Change> {
+    let mut changes = Vec::new();
+    for old_variable in old {
+        let change = match new.iter().find(|variable| variable.name == old_variable.name) {
+            Some(new_variable) if same(old_variable, new_variable) => continue,
+            Some(_) => Change::VariableModified { workflow: workflow.cloned(), name: old_variable.name.clone() },
+            None => Change::VariableRemoved { workflow: workflow.cloned(), name: old_variable.name.clone() },
+        };
+        changes.push(change);
+    }
+    for new_variable in new {
+        if !old.iter().any(|variable| variable.name == new_variable.name) {
+            changes.push(Change::VariableAdded { workflow: workflow.cloned(), name: new_variable.name.clone() });
+        }
+    }
+    changes
+}
+
+/// Top-level step ids in first-seen order
+fn step_ids(steps: &[Step]) -> Vec<u32> {
+    let mut ids = Vec::new();
+    for step in steps {
+        if !ids.contains(&step.id) {
+            ids.push(step.id);
+        }
+    }
+    ids
+}
+
+fn steps_with_id(steps: &[Step], id: u32) -> Vec<&Step> {
+    steps.iter().filter(|step| step.id == id).collect()
+}
+
+/// AST nodes don't implement `PartialEq`, so compare their serialized form.
+/// Source lines and spans are left out so that moving code around isn't a change.
+fn same<T: Serialize>(old: &T, new: &T) -> bool {
+    let normalized = |node: &T| serde_json::to_value(node).ok().map(|mut json| {
+        strip_positions(&mut json);
+        json
+    });
+    normalized(old) == normalized(new)
+}
+
+fn strip_positions(json: &mut serde_json::Value) {
+    match json {
+        serde_json::Value::Object(fields) => {
+            // Only steps carry a line; a named argument may well be called `line`
+            if fields.contains_key("id") && fields.contains_key("content") {
+                fields.remove("line");
+            }
+            // Likewise a named argument called `span` is an expression, not a `Span`
+            if fields.get("span").is_some_and(|span| span.get("start_line").is_some()) {
+                fields.remove("span");
+            }
+            fields.values_mut().for_each(strip_positions);
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(strip_positions),
+        _ => {}
+    }
+}
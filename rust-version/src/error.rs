@@ -0,0 +1,62 @@
+use thiserror::Error;
+
+/// Errors with enough structure for tooling to point at the offending source.
+/// They travel inside `anyhow::Error`; use `downcast_ref::<DslError>()` to match on them.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum DslError {
+    #[error("{expected}, found {found} at line {line}, column {column}")]
+    UnexpectedToken {
+        expected: String,
+        found: String,
+        line: usize,
+        column: usize,
+    },
+    #[error("Unexpected character: {character} at line {line}, column {column}")]
+    UnexpectedCharacter {
+        character: char,
+        line: usize,
+        column: usize,
+    },
+    #[error("Unterminated string starting at line {line}, column {column}")]
+    UnterminatedString {
+        line: usize,
+        column: usize,
+    },
+    #[error("Number literal too large at line {line}, column {column}")]
+    NumberTooLarge {
+        line: usize,
+        column: usize,
+    },
+    #[error("Undefined variable: {name}")]
+    UndefinedVariable {
+        name: String,
+    },
+    #[error("Environment variable '{name}' is not set")]
+    UnsetEnvironmentVariable {
+        name: String,
+    },
+    #[error("Step {step_id} not found")]
+    StepNotFound {
+        step_id: u32,
+    },
+    #[error("Step {step_id} is defined more than once in workflow '{workflow}'")]
+    DuplicateStepId {
+        step_id: u32,
+        workflow: String,
+    },
+    #[error("Step {step_id} is referenced in workflow '{workflow}' before it is defined")]
+    UndefinedStepReference {
+        step_id: u32,
+        workflow: String,
+    },
+    #[error("Execution limit exceeded: more than {max} {limit}")]
+    LimitExceeded {
+        limit: String,
+        max: usize,
+    },
+    #[error("Step {step_id} failed: {message}")]
+    StepFailed {
+        step_id: u32,
+        message: String,
+    },
+}
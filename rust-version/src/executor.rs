@@ -1,326 +1,2797 @@
 use anyhow::{anyhow, Result};
-use std::collections::HashMap;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use crate::ast::*;
+use crate::error::DslError;
+use crate::policy::GenerationPolicy;
+use crate::visit::{walk_step, Visitor};
+use crate::value::{format_number, FunctionValue, Value};
+use indexmap::IndexMap;
 
-#[derive(Debug, Clone)]
+/// Print progress output only when the executor is verbose
+macro_rules! say {
+    ($executor:expr, $($arg:tt)*) => {
+        if $executor.verbose {
+            println!($($arg)*);
+        }
+    };
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct StepResult {
     pub success: bool,
-    pub data: String,
+    pub data: Value,
     pub status: u32,
     pub message: String,
+    pub duration: Duration,
+    /// Extra details a command reports next to its data, read back as `step N.meta.key`
+    pub metadata: IndexMap<String, Value>,
 }
 
 impl StepResult {
-    pub fn new(success: bool, data: String, status: u32, message: String) -> Self {
+    pub fn new(success: bool, data: impl Into<Value>, status: u32, message: String) -> Self {
         StepResult {
             success,
-            data,
+            data: data.into(),
             status,
             message,
+            duration: Duration::ZERO,
+            metadata: IndexMap::new(),
+        }
+    }
+    
+    pub fn with_metadata(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.metadata.insert(key.to_string(), value.into());
+        self
+    }
+}
+
+/// Value of one sub-expression, with the traces of the operands it was computed from
+#[derive(Debug, Clone)]
+pub struct ExpressionTrace {
+    pub expression: String,
+    pub value: Result<Value, String>,
+    pub children: Vec<ExpressionTrace>,
+}
+
+/// What a run produced, for callers that need the data rather than the console output
+#[derive(Debug, Clone, Serialize)]
+pub struct ExecutionReport {
+    /// Names of the executed workflows, in order
+    pub workflows: Vec<String>,
+    /// Latest result of every step that produced one, by workflow name and then step id
+    pub steps: BTreeMap<String, BTreeMap<u32, StepResult>>,
+    /// Program-level variable values after the run; workflow and block scopes are gone by then
+    pub variables: BTreeMap<String, Value>,
+    /// Side effects skipped because the executor was in dry-run mode
+    pub planned_actions: Vec<PlannedAction>,
+    /// Result of every iteration of steps inside loop bodies, by workflow name and then step id
+    pub loop_results: BTreeMap<String, BTreeMap<u32, Vec<StepResult>>>,
+    /// Workflows that failed while `continue_workflows_on_error` or `ErrorPolicy::HaltWorkflow` let the rest run
+    pub failed_workflows: Vec<WorkflowFailure>,
+    /// Values the top-level runs of workflows handed back with `return value`, by workflow name
+    pub return_values: BTreeMap<String, Value>,
+    /// Values saved with `store(key, value)` by the end of the run
+    pub store: BTreeMap<String, Value>,
+    pub metrics: ExecutionMetrics,
+    /// Every run of a command or `call` step, in execution order; loop bodies add one per iteration
+    pub timings: Vec<StepTiming>,
+    /// Wall-clock time of the whole run
+    pub total_duration: Duration,
+}
+
+impl ExecutionReport {
+    /// Result of step `step_id` of `workflow`, if it produced one
+    pub fn step(&self, workflow: &str, step_id: u32) -> Option<&StepResult> {
+        self.steps.get(workflow).and_then(|steps| steps.get(&step_id))
+    }
+    
+    /// The `n` longest step runs, longest first
+    pub fn slowest_steps(&self, n: usize) -> Vec<StepTiming> {
+        let mut timings = self.timings.clone();
+        timings.sort_by_key(|timing| std::cmp::Reverse(timing.duration));
+        timings.truncate(n);
+        timings
+    }
+}
+
+/// When one run of a step started, relative to the start of the program, and how long it took
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StepTiming {
+    pub workflow: String,
+    pub step_id: u32,
+    pub started: Duration,
+    pub duration: Duration,
+    pub success: bool,
+}
+
+/// Approximate resource use of a run. Only values held in variables and step
+/// results count, not temporaries, which is enough to spot runaway growth.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct ExecutionMetrics {
+    /// Most values alive at once, each array element and object field counting as one
+    pub peak_values: usize,
+    /// Most bytes those values took at once
+    pub peak_bytes: usize,
+}
+
+/// Values and bytes held, see `Value::footprint`
+#[derive(Debug, Clone, Copy, Default)]
+struct Footprint {
+    values: usize,
+    bytes: usize,
+}
+
+impl Footprint {
+    fn of(value: &Value) -> Self {
+        let (values, bytes) = value.footprint();
+        Footprint { values, bytes }
+    }
+    
+    fn of_result(result: &StepResult) -> Self {
+        let mut footprint = Footprint::of(&result.data);
+        footprint.bytes += result.message.len();
+        for (key, value) in &result.metadata {
+            let (values, bytes) = value.footprint();
+            footprint.values += values;
+            footprint.bytes += key.len() + bytes;
+        }
+        footprint
+    }
+}
+
+/// Time spent under each stack of workflows and steps, for `Executor::folded_stacks`
+#[derive(Debug, Default)]
+struct Profiler {
+    /// Open frames, outermost first, with when they started and how long their children took
+    frames: Vec<(String, Instant, Duration)>,
+    /// Time spent in the innermost frame of each stack, by `;`-joined stack
+    self_times: IndexMap<String, Duration>,
+}
+
+impl Profiler {
+    fn enter(&mut self, frame: String) {
+        // `;` separates frames in the folded format
+        self.frames.push((frame.replace(';', ":"), Instant::now(), Duration::ZERO));
+    }
+    
+    fn exit(&mut self) {
+        let stack = self.frames.iter().map(|(frame, _, _)| frame.as_str()).collect::<Vec<_>>().join(";");
+        let Some((_, started, children)) = self.frames.pop() else { return };
+        let elapsed = started.elapsed();
+        *self.self_times.entry(stack).or_default() += elapsed.saturating_sub(children);
+        if let Some((_, _, parent_children)) = self.frames.last_mut() {
+            *parent_children += elapsed;
+        }
+    }
+}
+
+/// What the executor does after a command step reports `success: false`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorPolicy {
+    /// Keep going; later steps can inspect `step N.success`
+    #[default]
+    Continue,
+    /// Skip the rest of the workflow (after its `on_error` handler) and go on with the next one
+    HaltWorkflow,
+    /// Abort the whole program with a `DslError::StepFailed`
+    HaltProgram,
+}
+
+/// A workflow that stopped with an error
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkflowFailure {
+    pub workflow: String,
+    pub error: String,
+}
+
+/// Progress of a run as it happens, for `Executor::with_event_handler`
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type")]
+pub enum ExecutionEvent {
+    /// A workflow began, either top-level or through `call`
+    WorkflowStarted { workflow: String },
+    /// A command or `call` step is about to run
+    StepStarted { workflow: String, step_id: u32 },
+    /// A command or `call` step stored its result, failed ones included
+    StepFinished { workflow: String, step_id: u32, result: StepResult },
+    /// A workflow stopped with an error
+    Error { workflow: String, message: String },
+}
+
+/// Point-in-time view of an executor, for debugging executions that hang
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ExecutorSnapshot {
+    /// Workflow being executed, if any
+    pub workflow: Option<String>,
+    /// Command step being executed, if any
+    pub current_step: Option<u32>,
+    /// Every variable in scope, inner bindings hiding outer ones
+    pub variables: BTreeMap<String, Value>,
+    /// Results of the steps completed so far, by workflow name and then step id
+    pub step_results: BTreeMap<String, BTreeMap<u32, StepResult>>,
+}
+
+/// Shared view of a running executor's latest snapshot that other threads can read
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotHandle(Arc<Mutex<ExecutorSnapshot>>);
+
+impl SnapshotHandle {
+    pub fn snapshot(&self) -> ExecutorSnapshot {
+        self.0.lock().map(|snapshot| snapshot.clone()).unwrap_or_default()
+    }
+}
+
+/// What an `input` step asks for
+#[derive(Debug, Clone, Serialize)]
+pub struct InputRequest {
+    pub variable: String,
+    pub input_type: String,
+    pub placeholder: String,
+    /// Checks the answer must pass, in `validate` syntax such as `required` or `min:0`
+    pub rules: Vec<String>,
+}
+
+/// Answers `input` steps, e.g. from a form or a terminal prompt. Without one the
+/// executor only records what would have been asked.
+pub trait InputProvider: Send {
+    /// The value for `request`, or `None` when there is no answer
+    fn provide(&mut self, request: &InputRequest) -> Option<Value>;
+}
+
+/// Where `pick` gets its randomness. Inject a `SeededRandom` to make picks repeatable.
+pub trait RandomSource: Send {
+    /// An index below `len`, which is never 0
+    fn next_index(&mut self, len: usize) -> usize;
+}
+
+/// A splitmix64 generator; the same seed always gives the same sequence
+#[derive(Debug, Clone)]
+pub struct SeededRandom {
+    state: u64,
+}
+
+impl SeededRandom {
+    pub fn new(seed: u64) -> Self {
+        SeededRandom { state: seed }
+    }
+    
+    /// Seeded from the clock, for runs that don't need to be repeatable
+    pub fn from_clock() -> Self {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+        SeededRandom::new(nanos)
+    }
+    
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+impl RandomSource for SeededRandom {
+    fn next_index(&mut self, len: usize) -> usize {
+        (self.next_u64() % len as u64) as usize
+    }
+}
+
+/// What a command handler knows about the step it runs for
+pub struct CommandContext<'a> {
+    executor: &'a mut Executor,
+    step_id: u32,
+    command: &'a Command,
+}
+
+impl CommandContext<'_> {
+    pub fn step_id(&self) -> u32 {
+        self.step_id
+    }
+    
+    /// Name the command was called by, for handlers registered under several names
+    pub fn name(&self) -> &str {
+        &self.command.name
+    }
+    
+    /// Print a progress line unless the executor is quiet
+    pub fn log(&self, message: &str) {
+        say!(self.executor, "    {}", message);
+    }
+    
+    /// Warn about `message` and turn it into a failed result with status 400, for
+    /// arguments the command can't run with
+    pub fn fail(&self, message: String) -> StepResult {
+        say!(self.executor, "    ⚠️  {}", message);
+        StepResult::new(false, Value::Null, 400, message)
+    }
+    
+    /// A failed check's result, or an error when the executor halts on invalid data;
+    /// the result is kept for the report either way
+    fn halt_if_invalid(&mut self, result: StepResult, what: &str, reason: &str) -> Result<StepResult> {
+        if self.executor.halt_on_invalid {
+            let key = self.executor.step_key(self.step_id);
+            self.executor.step_results.insert(key, result);
+            return Err(anyhow!("{} in step {}: {}", what, self.step_id, reason));
         }
+        Ok(result)
+    }
+}
+
+/// A command steps can call by name, registered with `Executor::register_command`.
+/// Built-in commands such as `fetch` are handlers too. Closures taking the
+/// arguments are handlers as well.
+pub trait CommandHandler: Send {
+    /// Run the command with its evaluated arguments: positional ones first, then
+    /// named ones at their parameter's position, with `null` filling any gap
+    fn call(&mut self, context: &mut CommandContext, arguments: &[Value]) -> Result<StepResult>;
+    
+    /// Parameter names in positional order, so arguments can be passed by name
+    fn parameters(&self) -> &[&str] {
+        &[]
+    }
+}
+
+impl<F> CommandHandler for F
+where
+    F: FnMut(&[Value]) -> Result<StepResult> + Send,
+{
+    fn call(&mut self, _context: &mut CommandContext, arguments: &[Value]) -> Result<StepResult> {
+        self(arguments)
+    }
+}
+
+type BuiltinCommand = fn(&mut CommandContext, &[Value]) -> Result<StepResult>;
+
+struct Builtin {
+    run: BuiltinCommand,
+    parameters: &'static [&'static str],
+}
+
+impl CommandHandler for Builtin {
+    fn call(&mut self, context: &mut CommandContext, arguments: &[Value]) -> Result<StepResult> {
+        (self.run)(context, arguments)
+    }
+    
+    fn parameters(&self) -> &[&str] {
+        self.parameters
     }
 }
 
+/// A side-effecting command that a dry run recorded instead of performing
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedAction {
+    pub step_id: u32,
+    pub command: String,
+    pub arguments: Vec<Value>,
+}
+
+/// Commands that reach outside the executor and are skipped in dry-run mode
+const SIDE_EFFECT_COMMANDS: [&str; 4] = ["fetch", "send_email", "notify", "output"];
+
+const DEFAULT_MAX_LOOP_ITERATIONS: usize = 1000;
+
+/// Steps one `execute` may run in total, loop iterations and called workflows included
+const DEFAULT_MAX_STEPS: usize = 100_000;
+
+/// How deeply `call` may nest workflows inside each other
+const DEFAULT_MAX_CALL_DEPTH: usize = 32;
+
+/// How long `fetch` waits for a response when no `timeout_ms` is given
+const DEFAULT_FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What `fetch` requests when no url is given
+const DEFAULT_FETCH_URL: &str = "https://api.example.com";
+
+const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Results of the steps in a loop body during one iteration, so `step N` inside the
+/// body only sees what ran in the current iteration
+#[derive(Debug, Default)]
+struct IterationScope {
+    step_ids: HashSet<u32>,
+    results: HashMap<u32, StepResult>,
+}
+
+/// A step of one workflow, by workflow name and step id; ids are only unique within a workflow
+type StepKey = (String, u32);
+
+/// Bindings of the program, a workflow, or one run of a block
+#[derive(Debug, Default)]
+struct Scope {
+    variables: HashMap<String, Value>,
+    constants: HashSet<String>,
+}
+
 pub struct Executor {
-    variables: HashMap<String, String>,
-    step_results: HashMap<u32, StepResult>,
+    /// Innermost scope last; the first scope holds program-level variables
+    scopes: Vec<Scope>,
+    step_results: HashMap<StepKey, StepResult>,
+    executed_steps: Vec<StepKey>,
+    /// Start of the current `execute`, which step timings are measured from
+    run_started: Instant,
+    timings: Vec<StepTiming>,
+    previous_step: Option<u32>,
+    max_loop_iterations: usize,
+    max_steps: usize,
+    /// Steps run by the current `execute`, checked against `max_steps`
+    steps_executed: usize,
+    max_call_depth: usize,
+    verbose: bool,
+    dry_run: bool,
+    planned_actions: Vec<PlannedAction>,
+    ai_endpoint: Option<String>,
+    halt_on_invalid: bool,
+    continue_workflows_on_error: bool,
+    error_policy: ErrorPolicy,
+    try_depth: usize,
+    iteration_scopes: Vec<IterationScope>,
+    loop_results: HashMap<StepKey, Vec<StepResult>>,
+    generation_policy: Option<GenerationPolicy>,
+    current_workflow: Option<String>,
+    current_step: Option<u32>,
+    snapshot_handle: Option<SnapshotHandle>,
+    event_handler: Option<Box<dyn FnMut(ExecutionEvent) + Send>>,
+    /// Workflows of the program being executed, for `call`
+    workflows: Vec<Workflow>,
+    /// `fn` declarations of the program being executed, by name
+    functions: IndexMap<String, FunctionDeclaration>,
+    /// Functions whose bodies are being evaluated, outermost first
+    function_stack: Vec<String>,
+    /// Names of the running workflow and the workflows calling it, outermost first
+    call_stack: Vec<String>,
+    /// Set by `return` until the workflow it ends has stopped
+    returning: Option<Value>,
+    return_values: BTreeMap<String, Value>,
+    /// What each finished top-level workflow returned, `null` when nothing, for `workflow("Name").result`
+    workflow_results: HashMap<String, Value>,
+    /// Values saved with `store`, shared by every workflow of the run
+    store: IndexMap<String, Value>,
+    /// Host patterns `fetch` may reach; `None` allows every host
+    fetch_allowlist: Option<Vec<String>>,
+    /// Wait before the first retry of a failed command, doubled for every later one
+    retry_backoff: Duration,
+    input_provider: Option<Box<dyn InputProvider>>,
+    random: Box<dyn RandomSource>,
+    /// Handlers of the commands steps can call, built-in ones included
+    commands: HashMap<String, Box<dyn CommandHandler>>,
+    /// Runtime `fetch` and `generate` await on when run by an `AsyncExecutor`
+    #[cfg(feature = "tokio")]
+    runtime: Option<tokio::runtime::Handle>,
+    profiler: Option<Profiler>,
+    /// What variables, step results and loop histories hold right now
+    live: Footprint,
+    /// What each step's current result adds to `live`
+    result_footprints: HashMap<StepKey, Footprint>,
+    metrics: ExecutionMetrics,
+    failed_workflows: Vec<WorkflowFailure>,
+}
+
+impl Default for Executor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Executor {
     pub fn new() -> Self {
         Executor {
-            variables: HashMap::new(),
+            scopes: vec![Scope::default()],
             step_results: HashMap::new(),
+            executed_steps: Vec::new(),
+            run_started: Instant::now(),
+            timings: Vec::new(),
+            previous_step: None,
+            max_loop_iterations: DEFAULT_MAX_LOOP_ITERATIONS,
+            max_steps: DEFAULT_MAX_STEPS,
+            steps_executed: 0,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            verbose: true,
+            dry_run: false,
+            planned_actions: Vec::new(),
+            ai_endpoint: None,
+            halt_on_invalid: false,
+            continue_workflows_on_error: false,
+            error_policy: ErrorPolicy::Continue,
+            try_depth: 0,
+            iteration_scopes: Vec::new(),
+            loop_results: HashMap::new(),
+            generation_policy: None,
+            current_workflow: None,
+            current_step: None,
+            snapshot_handle: None,
+            event_handler: None,
+            workflows: Vec::new(),
+            functions: IndexMap::new(),
+            function_stack: Vec::new(),
+            call_stack: Vec::new(),
+            returning: None,
+            return_values: BTreeMap::new(),
+            workflow_results: HashMap::new(),
+            store: IndexMap::new(),
+            fetch_allowlist: None,
+            retry_backoff: DEFAULT_RETRY_BACKOFF,
+            input_provider: None,
+            random: Box::new(SeededRandom::from_clock()),
+            commands: builtin_commands(),
+            #[cfg(feature = "tokio")]
+            runtime: None,
+            profiler: None,
+            live: Footprint::default(),
+            result_footprints: HashMap::new(),
+            metrics: ExecutionMetrics::default(),
+            failed_workflows: Vec::new(),
+        }
+    }
+    
+    /// An executor that records side effects as planned actions instead of performing them
+    pub fn new_dry_run() -> Self {
+        Self::new().with_dry_run(true)
+    }
+    
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+    
+    /// Stop the workflow with an error when `validate` fails instead of only recording it
+    pub fn with_halt_on_invalid(mut self, halt_on_invalid: bool) -> Self {
+        self.halt_on_invalid = halt_on_invalid;
+        self
+    }
+    
+    /// Record a failed workflow in the report and go on with the next one instead of
+    /// aborting the program, for batches of independent workflows
+    pub fn with_continue_workflows_on_error(mut self, continue_workflows_on_error: bool) -> Self {
+        self.continue_workflows_on_error = continue_workflows_on_error;
+        self
+    }
+    
+    /// How to react to a command step that fails without an error, e.g. a failed fetch
+    pub fn with_error_policy(mut self, error_policy: ErrorPolicy) -> Self {
+        self.error_policy = error_policy;
+        self
+    }
+    
+    /// Fail `generate` steps whose model or temperature the policy doesn't allow
+    pub fn with_generation_policy(mut self, policy: GenerationPolicy) -> Self {
+        self.generation_policy = Some(policy);
+        self
+    }
+    
+    /// Only let `fetch` reach hosts matching one of `patterns`, either an exact host
+    /// such as `api.example.com` or `*.example.com` for its subdomains. Anything else
+    /// stops the workflow with an error, even when the fetch would be simulated.
+    pub fn with_fetch_allowlist(mut self, patterns: &[&str]) -> Self {
+        self.fetch_allowlist = Some(patterns.iter().map(|pattern| pattern.to_ascii_lowercase()).collect());
+        self
+    }
+    
+    /// Wait before the first retry of a command given `retries: N`; each later retry waits twice as long
+    pub fn with_retry_backoff(mut self, backoff: Duration) -> Self {
+        self.retry_backoff = backoff;
+        self
+    }
+    
+    /// Answer `input` steps with `provider` and check the answers against the step's
+    /// `required`, `min` and `max` options
+    pub fn with_input_provider(mut self, provider: impl InputProvider + 'static) -> Self {
+        self.input_provider = Some(Box::new(provider));
+        self
+    }
+    
+    /// Draw `pick` choices from `source` instead of a clock-seeded generator
+    pub fn with_random_source(mut self, source: impl RandomSource + 'static) -> Self {
+        self.random = Box::new(source);
+        self
+    }
+    
+    /// Run `handler` for `name(...)` steps, replacing the built-in command of that name if there is one
+    pub fn register_command(&mut self, name: &str, handler: impl CommandHandler + 'static) {
+        self.commands.insert(name.to_string(), Box::new(handler));
+    }
+    
+    /// Chat completions endpoint `generate` posts to with the `ai` feature, overriding
+    /// `AI_API_ENDPOINT` and the Mistral default
+    pub fn with_ai_endpoint(mut self, endpoint: &str) -> Self {
+        self.ai_endpoint = Some(endpoint.to_string());
+        self
+    }
+    
+    /// Print progress to stdout while executing (on by default)
+    pub fn with_verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+    
+    /// Cap on iterations of a single `while` loop before execution is aborted
+    pub fn with_max_loop_iterations(mut self, max_loop_iterations: usize) -> Self {
+        self.max_loop_iterations = max_loop_iterations;
+        self
+    }
+    
+    /// Cap on the steps one `execute` runs, counting every loop iteration and called
+    /// workflow, before it fails with `DslError::LimitExceeded` (100,000 by default)
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+    
+    /// Cap on how many `call`s may be running inside each other before the next one
+    /// fails with `DslError::LimitExceeded` (32 by default)
+    pub fn with_max_call_depth(mut self, max_call_depth: usize) -> Self {
+        self.max_call_depth = max_call_depth;
+        self
+    }
+    
+    /// Current variables, completed step results and the step being executed
+    pub fn snapshot(&self) -> ExecutorSnapshot {
+        let mut variables = BTreeMap::new();
+        for scope in &self.scopes {
+            variables.extend(scope.variables.iter().map(|(name, value)| (name.clone(), value.clone())));
+        }
+        ExecutorSnapshot {
+            workflow: self.current_workflow.clone(),
+            current_step: self.current_step,
+            variables,
+            step_results: by_workflow(&self.step_results),
+        }
+    }
+    
+    /// A handle another thread can read snapshots from while `execute` runs. The
+    /// executor refreshes it around every command step once a handle exists.
+    pub fn snapshot_handle(&mut self) -> SnapshotHandle {
+        self.snapshot_handle.get_or_insert_with(SnapshotHandle::default).clone()
+    }
+    
+    /// Call `handler` with every `ExecutionEvent` as the run goes. Progress is still
+    /// printed unless the executor is also made quiet with `with_verbose(false)`.
+    pub fn with_event_handler(mut self, handler: impl FnMut(ExecutionEvent) + Send + 'static) -> Self {
+        self.event_handler = Some(Box::new(handler));
+        self
+    }
+    
+    /// Hand the event `make` builds to the event handler; nothing is built without one
+    fn emit(&mut self, make: impl FnOnce(&Self) -> ExecutionEvent) {
+        if self.event_handler.is_none() {
+            return;
+        }
+        let event = make(self);
+        if let Some(handler) = self.event_handler.as_mut() {
+            handler(event);
+        }
+    }
+    
+    fn publish_snapshot(&self) {
+        if let Some(handle) = &self.snapshot_handle {
+            let snapshot = self.snapshot();
+            if let Ok(mut shared) = handle.0.lock() {
+                *shared = snapshot;
+            }
         }
     }
     
-    pub fn execute(&mut self, program: &Program) -> Result<()> {
-        println!("🚀 Executing TradeMinutes DSL Program");
-        println!("=====================================");
+    /// Record how long each workflow and step takes, nested steps under the steps
+    /// that contain them, for `folded_stacks`
+    pub fn with_profiling(mut self, profiling: bool) -> Self {
+        self.profiler = profiling.then(Profiler::default);
+        self
+    }
+    
+    /// The profile in the folded-stack format flamegraph tools read: one
+    /// `workflow;step 1 for;step 2 fetch 1234` line per stack with the microseconds
+    /// spent in its innermost frame. Empty unless profiling is on.
+    pub fn folded_stacks(&self) -> String {
+        let Some(profiler) = &self.profiler else { return String::new() };
+        profiler.self_times
+            .iter()
+            .map(|(stack, time)| format!("{} {}\n", stack, time.as_micros()))
+            .collect()
+    }
+    
+    /// Workflow, id and duration of the executed command steps, in execution order
+    pub fn step_timings(&self) -> Vec<(String, u32, Duration)> {
+        self.executed_steps
+            .iter()
+            .filter_map(|key| self.step_results.get(key).map(|result| (key.0.clone(), key.1, result.duration)))
+            .collect()
+    }
+    
+    /// Where the result of step `step_id` of the running workflow is kept
+    fn step_key(&self, step_id: u32) -> StepKey {
+        (self.current_workflow.clone().unwrap_or_default(), step_id)
+    }
+    
+    pub fn execute(&mut self, program: &Program) -> Result<ExecutionReport> {
+        self.execute_with_constants(program, &IndexMap::new())
+    }
+    
+    #[cfg(feature = "tokio")]
+    pub(crate) fn set_runtime(&mut self, runtime: Option<tokio::runtime::Handle>) {
+        self.runtime = runtime;
+    }
+    
+    /// Execute with program-level constants already evaluated, see `CompiledProgram`
+    pub(crate) fn execute_with_constants(&mut self, program: &Program, constants: &IndexMap<String, Value>) -> Result<ExecutionReport> {
+        self.reset_run_state();
+        say!(self, "🚀 Executing TradeMinutes DSL Program");
+        say!(self, "=====================================");
+        
+        // Functions come first, so variable declarations can already call them
+        self.functions.clear();
+        for function in &program.functions {
+            if self.functions.insert(function.name.clone(), function.clone()).is_some() {
+                return Err(anyhow!("Function '{}' is declared more than once", function.name));
+            }
+        }
         
         // Execute variable declarations
         for variable in &program.variables {
-            self.execute_variable(variable)?;
+            match constants.get(&variable.name) {
+                Some(value) if variable.keyword == "const" => {
+                    say!(self, "📦 Variable '{}' = '{}' (cached)", variable.name, value);
+                    self.declare(&variable.name, value.clone(), true)?;
+                }
+                _ => self.execute_variable(variable)?,
+            }
         }
         
         // Execute workflows
+        self.workflows = program.workflows.clone();
         for workflow in &program.workflows {
-            self.execute_workflow(workflow)?;
+            match self.execute_workflow(workflow) {
+                Ok(Some(value)) => {
+                    self.workflow_results.insert(workflow.name.clone(), value.clone());
+                    self.return_values.insert(workflow.name.clone(), value);
+                }
+                Ok(None) => {
+                    self.workflow_results.insert(workflow.name.clone(), Value::Null);
+                }
+                Err(error) if self.continue_workflows_on_error || self.halts_workflow_only(&error) => {
+                    say!(self, "❌ Workflow '{}' failed: {}", workflow.name, error);
+                    self.failed_workflows.push(WorkflowFailure {
+                        workflow: workflow.name.clone(),
+                        error: error.to_string(),
+                    });
+                }
+                Err(error) => return Err(error),
+            }
         }
         
-        Ok(())
+        Ok(ExecutionReport {
+            workflows: program.workflows.iter().map(|workflow| workflow.name.clone()).collect(),
+            steps: by_workflow(&self.step_results),
+            variables: self.scopes[0].variables.iter().map(|(name, value)| (name.clone(), value.clone())).collect(),
+            planned_actions: self.planned_actions.clone(),
+            loop_results: by_workflow(&self.loop_results),
+            failed_workflows: self.failed_workflows.clone(),
+            return_values: self.return_values.clone(),
+            store: self.store.iter().map(|(key, value)| (key.clone(), value.clone())).collect(),
+            metrics: self.metrics,
+            timings: self.timings.clone(),
+            total_duration: self.run_started.elapsed(),
+        })
+    }
+    
+    /// Forget everything an earlier `execute` left behind, so each run of the same
+    /// executor starts from scratch; configuration such as handlers and limits stays
+    fn reset_run_state(&mut self) {
+        self.scopes = vec![Scope::default()];
+        self.step_results.clear();
+        self.executed_steps.clear();
+        self.run_started = Instant::now();
+        self.timings.clear();
+        self.previous_step = None;
+        self.steps_executed = 0;
+        self.planned_actions.clear();
+        self.try_depth = 0;
+        self.iteration_scopes.clear();
+        self.loop_results.clear();
+        self.current_workflow = None;
+        self.current_step = None;
+        self.call_stack.clear();
+        self.returning = None;
+        self.return_values.clear();
+        self.workflow_results.clear();
+        self.store.clear();
+        if let Some(profiler) = self.profiler.as_mut() {
+            *profiler = Profiler::default();
+        }
+        self.live = Footprint::default();
+        self.result_footprints.clear();
+        self.metrics = ExecutionMetrics::default();
+        self.failed_workflows.clear();
+    }
+    
+    /// Value of a constant initializer that only needs literals and the given earlier
+    /// constants, or `None` when it has to wait for run time
+    pub(crate) fn fold_constant(expression: &Expression, constants: &IndexMap<String, Value>) -> Option<Value> {
+        Self::evaluate_literal(expression, constants).ok()
+    }
+    
+    /// Evaluate an expression that doesn't read any step, with only `bindings` declared
+    pub(crate) fn evaluate_literal(expression: &Expression, bindings: &IndexMap<String, Value>) -> Result<Value> {
+        let mut executor = Executor::new().with_verbose(false);
+        for (name, value) in bindings {
+            executor.declare(name, value.clone(), true)?;
+        }
+        executor.evaluate_expression(expression)
+    }
+    
+    /// Whether `error` is a failed step that `ErrorPolicy::HaltWorkflow` confines to its workflow
+    fn halts_workflow_only(&self, error: &anyhow::Error) -> bool {
+        self.error_policy == ErrorPolicy::HaltWorkflow
+            && matches!(error.downcast_ref::<DslError>(), Some(DslError::StepFailed { .. }))
     }
     
     fn execute_variable(&mut self, variable: &VariableDeclaration) -> Result<()> {
         let value = self.evaluate_expression(&variable.value)?;
-        self.variables.insert(variable.name.clone(), value);
-        println!("📦 Variable '{}' = '{}'", variable.name, self.variables[&variable.name]);
+        let display = value.to_display_string();
+        self.declare(&variable.name, value, variable.keyword == "const")?;
+        say!(self, "📦 Variable '{}' = '{}'", variable.name, display);
+        Ok(())
+    }
+    
+    fn execute_assignment(&mut self, assignment: &Assignment) -> Result<()> {
+        let value = self.evaluate_expression(&assignment.value)?;
+        
+        // Assignment updates the innermost binding, wherever it was declared
+        let scope = self.scopes
+            .iter_mut()
+            .rev()
+            .find(|scope| scope.variables.contains_key(&assignment.name))
+            .ok_or_else(|| anyhow!("Cannot assign to undeclared variable: {}", assignment.name))?;
+        if scope.constants.contains(&assignment.name) {
+            return Err(anyhow!("Cannot assign to constant: {}", assignment.name));
+        }
+        let added = Footprint::of(&value);
+        let replaced = scope.variables.insert(assignment.name.clone(), value.clone());
+        self.track(added, replaced.as_ref().map(Footprint::of));
+        
+        say!(self, "    ✏️  {} = '{}'", assignment.name, value.to_display_string());
+        Ok(())
+    }
+    
+    /// Bind a name in the innermost scope; an inner scope may shadow an outer constant
+    fn declare(&mut self, name: &str, value: Value, is_const: bool) -> Result<()> {
+        let scope = self.scopes.last_mut().expect("the program scope is never popped");
+        if scope.constants.contains(name) {
+            return Err(anyhow!("Cannot redeclare constant: {}", name));
+        }
+        if is_const {
+            scope.constants.insert(name.to_string());
+        }
+        let added = Footprint::of(&value);
+        let replaced = scope.variables.insert(name.to_string(), value);
+        self.track(added, replaced.as_ref().map(Footprint::of));
+        Ok(())
+    }
+    
+    /// Account for a value being stored in place of `removed`, updating the peaks
+    fn track(&mut self, added: Footprint, removed: Option<Footprint>) {
+        let removed = removed.unwrap_or_default();
+        self.live.values = (self.live.values + added.values).saturating_sub(removed.values);
+        self.live.bytes = (self.live.bytes + added.bytes).saturating_sub(removed.bytes);
+        self.metrics.peak_values = self.metrics.peak_values.max(self.live.values);
+        self.metrics.peak_bytes = self.metrics.peak_bytes.max(self.live.bytes);
+    }
+    
+    fn lookup(&self, name: &str) -> Option<&Value> {
+        self.scopes.iter().rev().find_map(|scope| scope.variables.get(name))
+    }
+    
+    /// Run `body` in a fresh scope that is dropped afterwards, even on error
+    fn with_scope<T>(&mut self, body: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.scopes.push(Scope::default());
+        let result = body(self);
+        if let Some(scope) = self.scopes.pop() {
+            for value in scope.variables.values() {
+                self.track(Footprint::default(), Some(Footprint::of(value)));
+            }
+        }
+        result
+    }
+    
+    fn execute_block(&mut self, steps: &[Step]) -> Result<()> {
+        self.with_scope(|executor| executor.execute_steps(steps))
+    }
+    
+    /// Run steps in order, stopping early once a `return` has run
+    fn execute_steps(&mut self, steps: &[Step]) -> Result<()> {
+        for step in steps {
+            self.execute_step(step)?;
+            if self.returning.is_some() {
+                break;
+            }
+        }
         Ok(())
     }
     
-    fn execute_workflow(&mut self, workflow: &Workflow) -> Result<()> {
-        println!("\n🔄 Executing workflow: {}", workflow.name);
+    /// Run a workflow, giving back what it returned with `return value`
+    fn execute_workflow(&mut self, workflow: &Workflow) -> Result<Option<Value>> {
+        say!(self, "\n🔄 Executing workflow: {}", workflow.name);
         
+        #[cfg(feature = "otel")]
+        let _workflow_span = tracing::info_span!("workflow", name = %workflow.name).entered();
+        
+        self.previous_step = None;
+        self.current_workflow = Some(workflow.name.clone());
+        self.publish_snapshot();
+        self.emit(|_| ExecutionEvent::WorkflowStarted { workflow: workflow.name.clone() });
+        
+        // Workflow declarations live in their own scope, shadowing program-level bindings
+        self.call_stack.push(workflow.name.clone());
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.enter(workflow.name.clone());
+        }
+        let result = self.with_scope(|executor| executor.execute_workflow_body(workflow));
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.exit();
+        }
+        if let Err(error) = &result {
+            self.emit(|_| ExecutionEvent::Error { workflow: workflow.name.clone(), message: error.to_string() });
+        }
+        self.call_stack.pop();
+        self.current_workflow = None;
+        self.current_step = None;
+        self.publish_snapshot();
+        let returned = self.returning.take();
+        result.map(|()| returned)
+    }
+    
+    fn execute_workflow_body(&mut self, workflow: &Workflow) -> Result<()> {
         // Execute workflow variables first
         for variable in &workflow.variables {
             self.execute_variable(variable)?;
         }
         
         for step in &workflow.steps {
-            self.execute_step(step)?;
+            if let Err(error) = self.execute_step(step) {
+                if let Some(handler) = &workflow.on_error {
+                    say!(self, "    🚨 Step {} failed, running on_error handler", step.id);
+                    self.with_scope(|executor| {
+                        executor.declare(&handler.variable, Value::String(error.to_string()), false)?;
+                        executor.execute_steps(&handler.steps)
+                    })?;
+                }
+                return Err(error);
+            }
+            if self.returning.is_some() {
+                break;
+            }
         }
         
         Ok(())
     }
     
     fn execute_step(&mut self, step: &Step) -> Result<()> {
-        println!("  📋 Step {}: ", step.id);
+        // Bare statements have no id of their own and count towards the enclosing frame
+        let profiled = step.id != 0 && self.profiler.is_some();
+        if let (true, Some(profiler)) = (profiled, self.profiler.as_mut()) {
+            profiler.enter(step_frame(step));
+        }
+        let result = self.execute_step_content(step).map_err(|error| locate_error(error, step));
+        if let (true, Some(profiler)) = (profiled, self.profiler.as_mut()) {
+            profiler.exit();
+        }
+        result
+    }
+    
+    fn execute_step_content(&mut self, step: &Step) -> Result<()> {
+        if self.steps_executed >= self.max_steps {
+            return Err(DslError::LimitExceeded { limit: "steps executed".to_string(), max: self.max_steps }.into());
+        }
+        self.steps_executed += 1;
+        if step.id != 0 {
+            say!(self, "  📋 Step {}: ", step.id);
+        }
+        
+        if let Some(guard) = &step.guard {
+            if !self.evaluate_condition(guard)? {
+                say!(self, "    ⏭️  Skipped, guard ({}) is false", guard);
+                // Steps that store a result store a marker instead, so later steps can tell
+                if matches!(step.content, StepContent::Command(_) | StepContent::CallWorkflow(_)) {
+                    let mut result = StepResult::new(true, Value::Null, 200, "Skipped because its guard is false".to_string());
+                    result.metadata.insert("skipped".to_string(), Value::Bool(true));
+                    self.step_results.insert(self.step_key(step.id), result);
+                    self.complete_step(step.id, Instant::now())?;
+                }
+                return Ok(());
+            }
+        }
+        
+        #[cfg(feature = "otel")]
+        let span = tracing::info_span!(
+            "step",
+            step_id = step.id,
+            command = tracing::field::Empty,
+            status = tracing::field::Empty,
+            success = tracing::field::Empty,
+            duration_ms = tracing::field::Empty,
+        )
+        .entered();
         
         match &step.content {
             StepContent::Command(command) => {
-                self.execute_command(step.id, command)?;
+                let started = Instant::now();
+                self.current_step = Some(step.id);
+                self.publish_snapshot();
+                self.emit_step_started(step.id);
+                self.execute_command_with_retries(step.id, command)?;
+                self.current_step = None;
+                
+                #[cfg(feature = "otel")]
+                if let Some(result) = self.step_results.get(&self.step_key(step.id)) {
+                    span.record("command", command.name.as_str());
+                    span.record("status", result.status);
+                    span.record("success", result.success);
+                    span.record("duration_ms", started.elapsed().as_secs_f64() * 1000.0);
+                }
+                self.complete_step(step.id, started)?;
+            }
+            StepContent::CallWorkflow(call) => {
+                let started = Instant::now();
+                self.current_step = Some(step.id);
+                self.publish_snapshot();
+                self.emit_step_started(step.id);
+                self.call_workflow(step.id, call)?;
+                self.current_step = None;
+                self.complete_step(step.id, started)?;
             }
             StepContent::Conditional(conditional) => {
                 self.execute_conditional(conditional)?;
             }
+            StepContent::WhileLoop(while_loop) => {
+                self.execute_while_loop(while_loop)?;
+            }
+            StepContent::ForEach(for_each) => {
+                self.execute_for_each(for_each)?;
+            }
+            StepContent::TryCatch(try_catch) => {
+                self.execute_try_catch(try_catch)?;
+            }
+            StepContent::Return(return_statement) => {
+                let value = match &return_statement.value {
+                    Some(value) => self.evaluate_expression(value)?,
+                    None => Value::Null,
+                };
+                say!(self, "    ↩️  Return '{}'", value.to_display_string());
+                self.returning = Some(value);
+            }
+            StepContent::Assignment(assignment) => {
+                self.execute_assignment(assignment)?;
+            }
+            StepContent::Declaration(variable) => {
+                self.execute_variable(variable)?;
+            }
         }
         
         Ok(())
     }
     
-    fn execute_command(&mut self, step_id: u32, command: &Command) -> Result<()> {
-        let args: Vec<String> = command.arguments
+    fn emit_step_started(&mut self, step_id: u32) {
+        self.emit(|executor| ExecutionEvent::StepStarted {
+            workflow: executor.current_workflow.clone().unwrap_or_default(),
+            step_id,
+        });
+    }
+    
+    /// Bookkeeping once a step has stored its result. Under a halting error policy or
+    /// inside `try`, a result with `success: false` becomes an error.
+    fn complete_step(&mut self, step_id: u32, started: Instant) -> Result<()> {
+        let key = self.step_key(step_id);
+        if let Some(result) = self.step_results.get_mut(&key) {
+            result.duration = started.elapsed();
+            self.timings.push(StepTiming {
+                workflow: key.0.clone(),
+                step_id,
+                started: started.saturating_duration_since(self.run_started),
+                duration: result.duration,
+                success: result.success,
+            });
+            let footprint = Footprint::of_result(result);
+            let replaced = self.result_footprints.insert(key.clone(), footprint);
+            self.track(footprint, replaced);
+        }
+        if let Some(result) = self.event_handler.as_ref().and(self.step_results.get(&key)).cloned() {
+            self.emit(|_| ExecutionEvent::StepFinished { workflow: key.0.clone(), step_id, result });
+        }
+        self.executed_steps.push(key.clone());
+        self.previous_step = Some(step_id);
+        self.record_iteration_result(step_id);
+        self.publish_snapshot();
+        
+        if self.error_policy != ErrorPolicy::Continue || self.try_depth > 0 {
+            if let Some(result) = self.step_results.get(&key).filter(|result| !result.success) {
+                return Err(DslError::StepFailed { step_id, message: result.message.clone() }.into());
+            }
+        }
+        Ok(())
+    }
+    
+    /// Run another workflow of the program with only program-level bindings in view.
+    /// The step's data is the callee's return value, or null if it didn't return one;
+    /// its metadata lists the callee's steps that ran.
+    fn call_workflow(&mut self, step_id: u32, call: &CallWorkflow) -> Result<()> {
+        if self.call_stack.contains(&call.name) {
+            return Err(anyhow!("Recursive workflow call: {} -> {}", self.call_stack.join(" -> "), call.name));
+        }
+        // The first entry of the call stack is the top-level workflow, not a call
+        if self.call_stack.len() > self.max_call_depth {
+            return Err(DslError::LimitExceeded { limit: "nested workflow calls".to_string(), max: self.max_call_depth }.into());
+        }
+        let callee = self.workflows
+            .iter()
+            .find(|workflow| workflow.name == call.name)
+            .cloned()
+            .ok_or_else(|| anyhow!("Workflow '{}' not found", call.name))?;
+        
+        // The caller's own scopes, loop iterations and `prev` come back once the callee is done
+        let caller_scopes = self.scopes.split_off(1);
+        let caller_iterations = std::mem::take(&mut self.iteration_scopes);
+        let caller_workflow = self.current_workflow.clone();
+        let caller_previous = self.previous_step;
+        let first_callee_step = self.executed_steps.len();
+        
+        let returned = self.execute_workflow(&callee);
+        
+        self.scopes.extend(caller_scopes);
+        self.iteration_scopes = caller_iterations;
+        self.current_workflow = caller_workflow;
+        self.previous_step = caller_previous;
+        let returned = returned?.unwrap_or(Value::Null);
+        
+        let callee_steps = &self.executed_steps[first_callee_step..];
+        let success = callee_steps
+            .iter()
+            .all(|key| self.step_results.get(key).is_none_or(|result| result.success));
+        // Steps of workflows the callee called in turn are in their own workflow's results
+        let steps = callee_steps
+            .iter()
+            .filter(|(workflow, _)| *workflow == call.name)
+            .map(|(_, id)| Value::Number(*id as f64))
+            .collect();
+        let steps = Value::Array(steps);
+        let message = format!("Workflow '{}' completed", call.name);
+        say!(self, "    📞 {}", message);
+        let result = StepResult::new(success, returned, if success { 200 } else { 500 }, message)
+            .with_metadata("workflow", call.name.as_str())
+            .with_metadata("steps", steps);
+        self.step_results.insert(self.step_key(step_id), result);
+        Ok(())
+    }
+    
+    /// Evaluate positional arguments, then slot named ones in at their parameter's position
+    fn resolve_arguments(&self, command: &Command) -> Result<Vec<Value>> {
+        let mut values = command.arguments
             .iter()
             .map(|expr| self.evaluate_expression(expr))
-            .collect::<Result<Vec<String>>>()?;
-        
-        match command.name.as_str() {
-            "print" => {
-                let message = args.join(" ");
-                println!("    📤 Print: {}", message);
-                self.step_results.insert(step_id, StepResult::new(
-                    true, message, 200, "Print executed successfully".to_string()
-                ));
-            }
-            "log" => {
-                let message = args.join(" ");
-                println!("    📝 Log: {}", message);
-                self.step_results.insert(step_id, StepResult::new(
-                    true, message, 200, "Log executed successfully".to_string()
-                ));
+            .collect::<Result<Vec<Value>>>()?;
+        
+        let parameters = self.commands.get(&command.name).map_or(&[][..], |handler| handler.parameters());
+        for (name, expression) in &command.named_arguments {
+            let index = match self.functions.get(&command.name) {
+                Some(function) => function.parameters.iter().position(|parameter| parameter == name),
+                None => parameters.iter().position(|parameter| parameter == name),
+            };
+            let index = index
+                .ok_or_else(|| anyhow!("Unknown argument '{}' for {}", name, command.name))?;
+            if index < command.arguments.len() {
+                return Err(anyhow!("Argument '{}' of {} is given both by position and by name", name, command.name));
             }
-            "fetch" => {
-                let default_url = "https://api.example.com".to_string();
-                let url = args.get(0).unwrap_or(&default_url);
-                println!("    🌐 Fetch: {}", url);
-                // Simulate fetch result
-                let result = StepResult::new(
-                    true,
-                    format!("{{\"data\": \"Sample data from {}\"}}", url),
-                    200,
-                    "Fetch completed successfully".to_string()
-                );
-                self.step_results.insert(step_id, result);
-            }
-            "send_email" => {
-                let default_to = "user@example.com".to_string();
-                let default_subject = "Notification".to_string();
-                let to = args.get(0).unwrap_or(&default_to);
-                let subject = args.get(1).unwrap_or(&default_subject);
-                println!("    📧 Send Email: {} - {}", to, subject);
-                self.step_results.insert(step_id, StepResult::new(
-                    true, format!("Email sent to {}", to), 200, "Email sent successfully".to_string()
-                ));
+            if values.len() <= index {
+                values.resize(index + 1, Value::Null);
             }
-            "notify" => {
-                let message = args.join(" ");
-                println!("    🔔 Notify: {}", message);
-                self.step_results.insert(step_id, StepResult::new(
-                    true, message, 200, "Notification sent successfully".to_string()
-                ));
+            values[index] = self.evaluate_expression(expression)?;
+        }
+        
+        Ok(values)
+    }
+    
+    /// Run a command, running it again while its result is unsuccessful when it has a
+    /// `retries: N` named argument. Errors, such as a failed argument, are never retried.
+    fn execute_command_with_retries(&mut self, step_id: u32, command: &Command) -> Result<()> {
+        let Some(retries) = command.named_arguments.get("retries") else {
+            return self.execute_command(step_id, command);
+        };
+        let retries = match self.evaluate_expression(retries)?.as_number() {
+            Some(retries) if retries >= 0.0 && retries.fract() == 0.0 => retries as u32,
+            _ => return Err(anyhow!("retries of {} must be a whole number of at least 0", command.name)),
+        };
+        let mut command = command.clone();
+        command.named_arguments.shift_remove("retries");
+        
+        let mut attempts = 1;
+        loop {
+            self.execute_command(step_id, &command)?;
+            let succeeded = self.step_results.get(&self.step_key(step_id)).is_some_and(|result| result.success);
+            if succeeded || attempts > retries {
+                break;
             }
-            // AI-specific commands for workflow integration
-            "input" => {
-                let variable_name = args.get(0).unwrap_or(&"user_input".to_string()).clone();
-                let input_type = args.get(1).unwrap_or(&"text".to_string()).clone();
-                let placeholder = args.get(2).unwrap_or(&"Enter value".to_string()).clone();
-                println!("    📝 Input: Collect '{}' as {} ({})", variable_name, input_type, placeholder);
-                
-                // Simulate user input collection
-                let result = StepResult::new(
-                    true,
-                    format!("{{\"variable\": \"{}\", \"type\": \"{}\", \"placeholder\": \"{}\"}}", 
-                           variable_name, input_type, placeholder),
-                    200,
-                    "Input collected successfully".to_string()
-                );
-                self.step_results.insert(step_id, result);
-            }
-            "generate" => {
-                let prompt = args.get(0).unwrap_or(&"Generate content".to_string()).clone();
-                let model = args.get(1).unwrap_or(&"mistral-small-latest".to_string()).clone();
-                let temperature = args.get(2).unwrap_or(&"0.7".to_string()).clone();
-                println!("    🤖 Generate: Using {} (temp: {}) with prompt: '{}'", model, temperature, prompt);
-                
-                // This would call the actual AI API in production
-                let result = StepResult::new(
-                    true,
-                    format!("{{\"content\": \"Generated content for: {}\", \"model\": \"{}\", \"temperature\": \"{}\"}}", 
-                           prompt, model, temperature),
-                    200,
-                    "Content generated successfully".to_string()
-                );
-                self.step_results.insert(step_id, result);
-            }
-            "output" => {
-                let data_ref = args.get(0).unwrap_or(&"data".to_string()).clone();
-                let format = args.get(1).unwrap_or(&"text".to_string()).clone();
-                let filename = args.get(2).unwrap_or(&"output".to_string()).clone();
-                println!("    📤 Output: Export {} as {} to {}", data_ref, format, filename);
-                
-                let result = StepResult::new(
-                    true,
-                    format!("{{\"exported\": \"{}\", \"format\": \"{}\", \"file\": \"{}\"}}", 
-                           data_ref, format, filename),
-                    200,
-                    "Output exported successfully".to_string()
-                );
-                self.step_results.insert(step_id, result);
-            }
-            "transform" => {
-                let data_ref = args.get(0).unwrap_or(&"data".to_string()).clone();
-                let transformation = args.get(1).unwrap_or(&"format".to_string()).clone();
-                println!("    🔄 Transform: Apply {} to {}", transformation, data_ref);
-                
-                let result = StepResult::new(
-                    true,
-                    format!("{{\"transformed\": \"{}\", \"type\": \"{}\"}}", data_ref, transformation),
-                    200,
-                    "Data transformed successfully".to_string()
-                );
-                self.step_results.insert(step_id, result);
-            }
-            "validate" => {
-                let data_ref = args.get(0).unwrap_or(&"data".to_string()).clone();
-                let validation_type = args.get(1).unwrap_or(&"required".to_string()).clone();
-                println!("    ✅ Validate: Check {} for {}", data_ref, validation_type);
-                
-                let result = StepResult::new(
-                    true,
-                    format!("{{\"validated\": \"{}\", \"type\": \"{}\", \"valid\": true}}", 
-                           data_ref, validation_type),
-                    200,
-                    "Validation completed successfully".to_string()
-                );
-                self.step_results.insert(step_id, result);
+            let wait = self.retry_backoff.saturating_mul(2u32.saturating_pow(attempts - 1));
+            say!(self, "    🔁 Retrying {} in {}ms (attempt {} of {})", command.name, wait.as_millis(), attempts + 1, retries + 1);
+            std::thread::sleep(wait);
+            attempts += 1;
+        }
+        
+        if attempts > 1 {
+            let key = self.step_key(step_id);
+            if let Some(result) = self.step_results.get_mut(&key) {
+                result.message = format!("{} (after {} attempts)", result.message, attempts);
             }
-            _ => {
-                println!("    ⚠️  Unknown command: {}", command.name);
-                self.step_results.insert(step_id, StepResult::new(
-                    false, "".to_string(), 400, format!("Unknown command: {}", command.name)
+        }
+        Ok(())
+    }
+    
+    fn execute_command(&mut self, step_id: u32, command: &Command) -> Result<()> {
+        let values = self.resolve_arguments(command)?;
+        
+        // A step naming a function value or a user function calls it
+        let function = match self.lookup(&command.name) {
+            Some(Value::Function(function)) => Some(function.clone()),
+            _ if self.functions.contains_key(&command.name) => Some(FunctionValue::new(&command.name)),
+            _ => None,
+        };
+        match function {
+            Some(function) => self.execute_function_step(step_id, command, function, values),
+            None => self.run_command(step_id, command, values),
+        }
+    }
+    
+    /// A user function's value becomes the step's data; a bound command runs with its
+    /// bound arguments before the step's own
+    fn execute_function_step(&mut self, step_id: u32, command: &Command, function: FunctionValue, values: Vec<Value>) -> Result<()> {
+        if !self.functions.contains_key(&function.name) {
+            let mut arguments = function.bound;
+            arguments.extend(values);
+            let command = Command { name: function.name, ..command.clone() };
+            return self.run_command(step_id, &command, arguments);
+        }
+        let value = self.call_function(&function, values)?;
+        say!(self, "    ƒ  {}: {}", command.name, value);
+        let message = format!("{} executed successfully", command.name);
+        self.step_results.insert(self.step_key(step_id), StepResult::new(true, value, 200, message));
+        Ok(())
+    }
+    
+    /// Call `function` with `arguments` after the ones it already has. A user function
+    /// given all its parameters is evaluated; one given fewer, or a command, which only
+    /// runs as a step, comes back with the arguments bound.
+    fn call_function(&self, function: &FunctionValue, arguments: Vec<Value>) -> Result<Value> {
+        let mut bound = function.bound.clone();
+        bound.extend(arguments);
+        let declaration = match self.functions.get(&function.name) {
+            Some(declaration) if bound.len() >= declaration.parameters.len() => declaration,
+            _ => return Ok(Value::Function(FunctionValue { name: function.name.clone(), bound })),
+        };
+        if bound.len() > declaration.parameters.len() {
+            return Err(anyhow!(
+                "Function {} takes {} argument(s), got {}", function.name, declaration.parameters.len(), bound.len()
+            ));
+        }
+        // Bodies can't branch, so a function reaching itself would never finish
+        if self.function_stack.contains(&function.name) {
+            return Err(anyhow!("Recursive function call: {} -> {}", self.function_stack.join(" -> "), function.name));
+        }
+        
+        // The body sees program-level variables and its parameters, nothing else
+        let mut executor = Executor::new().with_verbose(false);
+        executor.functions = self.functions.clone();
+        executor.function_stack = self.function_stack.clone();
+        executor.function_stack.push(function.name.clone());
+        executor.scopes[0].variables = self.scopes[0].variables.clone();
+        executor.scopes.push(Scope {
+            variables: declaration.parameters.iter().cloned().zip(bound).collect(),
+            constants: HashSet::new(),
+        });
+        executor.evaluate_expression(&declaration.body)
+    }
+    
+    /// Run a command by its handler, or only record it when it has side effects in a dry run
+    fn run_command(&mut self, step_id: u32, command: &Command, values: Vec<Value>) -> Result<()> {
+        // Checked before the dry-run branch, so a dry run rejects the same hosts a real run does
+        if command.name == "fetch" {
+            self.check_fetch_host(&argument(&values, 0, DEFAULT_FETCH_URL))?;
+        }
+        
+        if self.dry_run && SIDE_EFFECT_COMMANDS.contains(&command.name.as_str()) {
+            let args: Vec<String> = values.iter().map(Value::to_display_string).collect();
+            say!(self, "    🧪 Dry run: would {}({})", command.name, args.join(", "));
+            self.planned_actions.push(PlannedAction {
+                step_id,
+                command: command.name.clone(),
+                arguments: values,
+            });
+            self.step_results.insert(self.step_key(step_id), StepResult::new(
+                true, Value::Null, 200, format!("{} skipped in dry run", command.name)
+            ));
+            return Ok(());
+        }
+        
+        // Taken out while it runs, so the handler can borrow the executor
+        let Some(mut handler) = self.commands.remove(&command.name) else {
+            self.record_failure(step_id, format!("Unknown command: {}", command.name));
+            return Ok(());
+        };
+        let result = handler.call(&mut CommandContext { executor: self, step_id, command }, &values);
+        self.commands.insert(command.name.clone(), handler);
+        self.step_results.insert(self.step_key(step_id), result?);
+        Ok(())
+    }
+    
+    /// Fail unless the allow-list, when there is one, covers the host of `url`
+    fn check_fetch_host(&self, url: &str) -> Result<()> {
+        if let Some(allowlist) = &self.fetch_allowlist {
+            let host = url_host(url);
+            if !allowlist.iter().any(|pattern| host_matches(pattern, &host)) {
+                return Err(anyhow!(
+                    "Fetch from host '{}' is not allowed (allowed: {})", host, allowlist.join(", ")
                 ));
             }
         }
-        
         Ok(())
     }
     
+    /// Record a failed result for a command that couldn't run with the given arguments
+    fn record_failure(&mut self, step_id: u32, message: String) {
+        say!(self, "    ⚠️  {}", message);
+        self.step_results.insert(self.step_key(step_id), StepResult::new(false, Value::Null, 400, message));
+    }
+    
     fn execute_conditional(&mut self, conditional: &ConditionalStatement) -> Result<()> {
         let condition_result = self.evaluate_condition(&conditional.condition)?;
         
         if condition_result {
-            println!("    ✅ Condition is true, executing if block");
-            for step in &conditional.if_steps {
-                self.execute_step(step)?;
-            }
+            say!(self, "    ✅ Condition is true, executing if block");
+            self.execute_block(&conditional.if_steps)?;
         } else {
-            println!("    ❌ Condition is false");
+            say!(self, "    ❌ Condition is false");
             if let Some(else_steps) = &conditional.else_steps {
-                println!("    🔄 Executing else block");
-                for step in else_steps {
-                    self.execute_step(step)?;
+                say!(self, "    🔄 Executing else block");
+                self.execute_block(else_steps)?;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Re-evaluate the expressions of a step against the current state and
+    /// report every sub-expression's value. Call after `execute` to see why a
+    /// condition went the way it did.
+    pub fn explain_step(&mut self, program: &Program, step_id: u32) -> Result<Vec<ExpressionTrace>> {
+        let (workflow, step) = program.workflows
+            .iter()
+            .find_map(|workflow| find_step(&workflow.steps, step_id).map(|step| (workflow, step)))
+            .ok_or(DslError::StepNotFound { step_id })?;
+        
+        // Step references resolve within the step's own workflow, also once the run is over
+        let outer_workflow = self.current_workflow.replace(workflow.name.clone());
+        let traces = self.trace_step(step);
+        self.current_workflow = outer_workflow;
+        Ok(traces)
+    }
+    
+    fn trace_step(&self, step: &Step) -> Vec<ExpressionTrace> {
+        let mut traces: Vec<ExpressionTrace> = step.guard.iter().map(|guard| self.trace_expression(guard)).collect();
+        traces.extend(match &step.content {
+            StepContent::Command(command) => command.arguments
+                .iter()
+                .chain(command.named_arguments.values())
+                .map(|argument| self.trace_expression(argument))
+                .collect(),
+            StepContent::Conditional(conditional) => vec![self.trace_expression(&conditional.condition)],
+            StepContent::WhileLoop(while_loop) => vec![self.trace_expression(&while_loop.condition)],
+            StepContent::ForEach(for_each) => vec![self.trace_expression(&for_each.iterable)],
+            StepContent::TryCatch(_) | StepContent::CallWorkflow(_) => Vec::new(),
+            StepContent::Return(return_statement) => return_statement.value
+                .iter()
+                .map(|value| self.trace_expression(value))
+                .collect(),
+            StepContent::Assignment(assignment) => vec![self.trace_expression(&assignment.value)],
+            StepContent::Declaration(variable) => vec![self.trace_expression(&variable.value)],
+        });
+        traces
+    }
+    
+    fn trace_expression(&self, expression: &Expression) -> ExpressionTrace {
+        let children = match &expression.kind {
+            ExpressionKind::BinaryExpression { left, operator, right } => {
+                let left_trace = self.trace_expression(left);
+                // Mirror short-circuiting: the right side isn't shown when it was never evaluated
+                let short_circuited = match (operator.as_str(), &left_trace.value) {
+                    ("&&", Ok(value)) => !value.is_truthy(),
+                    ("||", Ok(value)) => value.is_truthy(),
+                    _ => false,
+                };
+                if short_circuited {
+                    vec![left_trace]
+                } else {
+                    vec![left_trace, self.trace_expression(right)]
                 }
             }
+            ExpressionKind::UnaryExpression { operand, .. } => vec![self.trace_expression(operand)],
+            ExpressionKind::PropertyAccess { object, .. } => vec![self.trace_expression(object)],
+            ExpressionKind::ArrayLiteral(elements) => elements
+                .iter()
+                .map(|element| self.trace_expression(element))
+                .collect(),
+            ExpressionKind::Interpolation(parts) => parts
+                .iter()
+                .filter_map(InterpolationPart::expression)
+                .map(|expression| self.trace_expression(expression))
+                .collect(),
+            _ => Vec::new(),
+        };
+        
+        ExpressionTrace {
+            expression: expression.to_string(),
+            value: self.evaluate_expression(expression).map_err(|e| e.to_string()),
+            children,
         }
+    }
+    
+    fn execute_while_loop(&mut self, while_loop: &WhileLoop) -> Result<()> {
+        let mut iterations = 0;
         
+        while self.evaluate_condition(&while_loop.condition)? {
+            if iterations >= self.max_loop_iterations {
+                return Err(anyhow!(
+                    "While loop exceeded the maximum of {} iterations",
+                    self.max_loop_iterations
+                ));
+            }
+            iterations += 1;
+            
+            say!(self, "    🔁 Iteration {}", iterations);
+            self.with_iteration(&while_loop.body, |executor| executor.execute_block(&while_loop.body))?;
+            if self.returning.is_some() {
+                break;
+            }
+        }
+        
+        say!(self, "    ⏹️  Loop finished after {} iterations", iterations);
+        Ok(())
+    }
+    
+    fn execute_for_each(&mut self, for_each: &ForEach) -> Result<()> {
+        let items = match self.evaluate_expression(&for_each.iterable)? {
+            Value::Array(items) => items,
+            other => return Err(anyhow!("Cannot iterate over {}, expected an array", other.type_name())),
+        };
+        
+        // Each item gets a fresh scope, so the loop variable only lives inside the body
+        for (index, item) in items.into_iter().enumerate() {
+            say!(self, "    🔁 Item {}: {}", index + 1, item);
+            self.with_iteration(&for_each.body, |executor| executor.with_scope(|executor| {
+                executor.declare(&for_each.var_name, item, false)?;
+                executor.execute_steps(&for_each.body)
+            }))?;
+            if self.returning.is_some() {
+                break;
+            }
+        }
+        Ok(())
+    }
+    
+    fn execute_try_catch(&mut self, try_catch: &TryCatch) -> Result<()> {
+        // Inside a try block every failed step result is an error, whatever the policy
+        self.try_depth += 1;
+        let outcome = self.execute_block(&try_catch.try_steps);
+        self.try_depth -= 1;
+        
+        if let Err(error) = outcome {
+            say!(self, "    🛟 Caught: {}", error);
+            self.with_scope(|executor| {
+                executor.declare(&try_catch.catch_variable, Value::String(error.to_string()), false)?;
+                executor.execute_steps(&try_catch.catch_steps)
+            })?;
+        }
         Ok(())
     }
     
+    /// Run one loop iteration with its own view of the body's step results
+    fn with_iteration(&mut self, body: &[Step], iteration: impl FnOnce(&mut Self) -> Result<()>) -> Result<()> {
+        let mut ids = StepIds::default();
+        body.iter().for_each(|step| ids.visit_step(step));
+        self.iteration_scopes.push(IterationScope { step_ids: ids.0, results: HashMap::new() });
+        let result = iteration(self);
+        self.iteration_scopes.pop();
+        result
+    }
+    
+    /// Keep a loop body step's result for the current iteration and the loop history
+    fn record_iteration_result(&mut self, step_id: u32) {
+        let key = self.step_key(step_id);
+        let Some(result) = self.step_results.get(&key) else { return };
+        let mut in_loop = false;
+        for scope in self.iteration_scopes.iter_mut().filter(|scope| scope.step_ids.contains(&step_id)) {
+            scope.results.insert(step_id, result.clone());
+            in_loop = true;
+        }
+        if in_loop {
+            let footprint = Footprint::of_result(result);
+            self.loop_results.entry(key).or_default().push(result.clone());
+            self.track(footprint, None);
+        }
+    }
+    
+    /// Result `step N` refers to: inside a loop body that defines step N, only a
+    /// result from the current iteration counts
+    fn step_result(&self, step_id: u32) -> Option<&StepResult> {
+        match self.iteration_scopes.iter().rev().find(|scope| scope.step_ids.contains(&step_id)) {
+            Some(scope) => scope.results.get(&step_id),
+            None => self.step_results.get(&self.step_key(step_id)),
+        }
+    }
+    
     fn evaluate_condition(&self, condition: &Expression) -> Result<bool> {
-        match condition {
-            Expression::BinaryExpression { left, operator, right } => {
-                let left_val = self.evaluate_expression(left)?;
-                let right_val = self.evaluate_expression(right)?;
-                
+        match &condition.kind {
+            ExpressionKind::BinaryExpression { left, operator, right } if !is_arithmetic(operator) => {
                 match operator.as_str() {
-                    "==" => Ok(left_val == right_val),
-                    "!=" => Ok(left_val != right_val),
-                    ">" => {
-                        let left_num: f64 = left_val.parse().unwrap_or(0.0);
-                        let right_num: f64 = right_val.parse().unwrap_or(0.0);
-                        Ok(left_num > right_num)
-                    }
-                    "<" => {
-                        let left_num: f64 = left_val.parse().unwrap_or(0.0);
-                        let right_num: f64 = right_val.parse().unwrap_or(0.0);
-                        Ok(left_num < right_num)
-                    }
-                    ">=" => {
-                        let left_num: f64 = left_val.parse().unwrap_or(0.0);
-                        let right_num: f64 = right_val.parse().unwrap_or(0.0);
-                        Ok(left_num >= right_num)
-                    }
-                    "<=" => {
-                        let left_num: f64 = left_val.parse().unwrap_or(0.0);
-                        let right_num: f64 = right_val.parse().unwrap_or(0.0);
-                        Ok(left_num <= right_num)
-                    }
-                    _ => Err(anyhow!("Unknown comparison operator: {}", operator)),
+                    // Short-circuit so the right side is only evaluated when it matters
+                    "&&" => Ok(self.evaluate_condition(left)? && self.evaluate_condition(right)?),
+                    "||" => Ok(self.evaluate_condition(left)? || self.evaluate_condition(right)?),
+                    _ => self.evaluate_comparison(left, operator, right),
                 }
             }
+            ExpressionKind::UnaryExpression { operator, operand } if operator == "!" => {
+                Ok(!self.evaluate_condition(operand)?)
+            }
             _ => {
                 let value = self.evaluate_expression(condition)?;
-                Ok(!value.is_empty() && value != "0" && value != "false")
+                Ok(value.is_truthy())
             }
         }
     }
     
-    fn evaluate_expression(&self, expression: &Expression) -> Result<String> {
-        match expression {
-            Expression::StringLiteral(value) => Ok(value.clone()),
-            Expression::NumberLiteral(value) => Ok(value.to_string()),
-            Expression::Identifier(name) => {
-                self.variables.get(name)
-                    .cloned()
-                    .ok_or_else(|| anyhow!("Undefined variable: {}", name))
+    fn evaluate_comparison(&self, left: &Expression, operator: &str, right: &Expression) -> Result<bool> {
+        let left_val = self.evaluate_expression(left)?;
+        let right_val = self.evaluate_expression(right)?;
+        
+        match operator {
+            "==" => Ok(values_equal(&left_val, &right_val)),
+            "!=" => Ok(!values_equal(&left_val, &right_val)),
+            ">" => Ok(compare_values(&left_val, &right_val)?.is_gt()),
+            "<" => Ok(compare_values(&left_val, &right_val)?.is_lt()),
+            ">=" => Ok(compare_values(&left_val, &right_val)?.is_ge()),
+            "<=" => Ok(compare_values(&left_val, &right_val)?.is_le()),
+            _ => Err(anyhow!("Unknown comparison operator: {}", operator)),
+        }
+    }
+    
+    fn evaluate_expression(&self, expression: &Expression) -> Result<Value> {
+        match &expression.kind {
+            ExpressionKind::StringLiteral(value) => Ok(Value::string(value)),
+            ExpressionKind::NumberLiteral(value) => Ok(Value::Number(*value)),
+            ExpressionKind::ArrayLiteral(elements) => Ok(Value::Array(
+                elements
+                    .iter()
+                    .map(|element| self.evaluate_expression(element))
+                    .collect::<Result<Vec<Value>>>()?,
+            )),
+            ExpressionKind::Identifier(name) => {
+                if let Some(value) = self.lookup(name) {
+                    return Ok(value.clone());
+                }
+                match name.as_str() {
+                    "true" => Ok(Value::Bool(true)),
+                    "false" => Ok(Value::Bool(false)),
+                    "null" => Ok(Value::Null),
+                    // Functions and commands are values too, ready to be called or bound
+                    _ if self.functions.contains_key(name) || self.commands.contains_key(name) => {
+                        Ok(Value::Function(FunctionValue::new(name)))
+                    }
+                    _ => Err(DslError::UndefinedVariable { name: name.clone() }.into()),
+                }
             }
-            Expression::BinaryExpression { left, operator, right } => {
+            ExpressionKind::BinaryExpression { left, operator, right } if is_arithmetic(operator) => {
                 let left_val = self.evaluate_expression(left)?;
                 let right_val = self.evaluate_expression(right)?;
                 
-                match operator.as_str() {
-                    "+" => Ok(format!("{}{}", left_val, right_val)),
-                    _ => Err(anyhow!("Unknown binary operator: {}", operator)),
+                match (left_val.as_number(), right_val.as_number()) {
+                    (Some(left_num), Some(right_num)) => arithmetic(left_num, operator, right_num),
+                    _ if operator == "+" => Ok(Value::String(format!(
+                        "{}{}",
+                        left_val.to_display_string(),
+                        right_val.to_display_string()
+                    ))),
+                    _ => Err(anyhow!(
+                        "Cannot apply '{}' to {} and {}",
+                        operator, left_val.type_name(), right_val.type_name()
+                    )),
                 }
             }
-            Expression::PropertyAccess { object, property } => {
-                let object_val = self.evaluate_expression(object)?;
-                // For now, just return the property name as a simple simulation
-                Ok(format!("{}.{}", object_val, property))
-            }
-            Expression::StepReference { step_id, property } => {
-                if let Some(result) = self.step_results.get(step_id) {
-                    match property.as_deref() {
-                        Some("status") => Ok(result.status.to_string()),
-                        Some("data") => Ok(result.data.clone()),
-                        Some("message") => Ok(result.message.clone()),
-                        Some("success") => Ok(result.success.to_string()),
-                        _ => Ok(result.data.clone()),
-                    }
+            // Logical operators short-circuit and always produce a Bool, never one of their operands,
+            // so `let ready = a && b` is true or false whatever the types of `a` and `b`
+            ExpressionKind::BinaryExpression { .. } => Ok(Value::Bool(self.evaluate_condition(expression)?)),
+            ExpressionKind::UnaryExpression { operator, operand } if operator == "-" => {
+                let value = self.evaluate_expression(operand)?;
+                match value.as_number() {
+                    Some(number) => Ok(Value::Number(-number)),
+                    None => Err(anyhow!("Cannot negate {}", value.type_name())),
+                }
+            }
+            ExpressionKind::UnaryExpression { .. } => Ok(Value::Bool(self.evaluate_condition(expression)?)),
+            ExpressionKind::PropertyAccess { object, property } => {
+                match self.evaluate_expression(object)? {
+                    Value::Object(fields) => Ok(fields.get(property).cloned().unwrap_or(Value::Null)),
+                    // Missing keys stay missing down the rest of the chain, and scalars
+                    // and arrays have no properties, so `step 1.status.foo` is null too
+                    _ => Ok(Value::Null),
+                }
+            }
+            ExpressionKind::StepReference { step_id, property } => {
+                if let Some(result) = self.step_result(*step_id) {
+                    Ok(step_property(result, property.as_deref()))
                 } else {
-                    Err(anyhow!("Step {} not found", step_id))
+                    Err(DslError::StepNotFound { step_id: *step_id }.into())
+                }
+            }
+            ExpressionKind::Interpolation(parts) => {
+                let mut text = String::new();
+                for part in parts {
+                    match part {
+                        InterpolationPart::Literal(literal) => text.push_str(literal),
+                        InterpolationPart::Expression(expression) => {
+                            text.push_str(&self.evaluate_expression(expression)?.to_display_string())
+                        }
+                    }
+                }
+                Ok(Value::String(text))
+            }
+            ExpressionKind::PreviousStep { property } => {
+                let result = self.previous_step
+                    .and_then(|step_id| self.step_results.get(&self.step_key(step_id)))
+                    .ok_or_else(|| anyhow!("'prev' used before any step of the workflow has run"))?;
+                Ok(step_property(result, property.as_deref()))
+            }
+            ExpressionKind::WorkflowReference { name } => match self.workflow_results.get(name) {
+                Some(result) => Ok(Value::object(vec![
+                    ("name", Value::string(name)),
+                    ("result", result.clone()),
+                ])),
+                None => Err(anyhow!("Workflow '{}' has not finished yet, so its result isn't available", name)),
+            },
+            ExpressionKind::EnvironmentVariable { name, default } => match (std::env::var(name), default) {
+                (Ok(value), _) => Ok(Value::String(value)),
+                (Err(_), Some(default)) => Ok(Value::string(default)),
+                (Err(_), None) => Err(DslError::UnsetEnvironmentVariable { name: name.clone() }.into()),
+            },
+            ExpressionKind::Call { callee, arguments } => {
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| self.evaluate_expression(argument))
+                    .collect::<Result<Vec<Value>>>()?;
+                match self.evaluate_expression(callee)? {
+                    Value::Function(function) => self.call_function(&function, arguments),
+                    other => Err(anyhow!("Cannot call {}, a {}", callee, other.type_name())),
                 }
             }
         }
     }
-} 
\ No newline at end of file
+}
+
+/// Results kept by step key, grouped by workflow name and then step id
+fn by_workflow<T: Clone>(results: &HashMap<StepKey, T>) -> BTreeMap<String, BTreeMap<u32, T>> {
+    let mut grouped: BTreeMap<String, BTreeMap<u32, T>> = BTreeMap::new();
+    for ((workflow, step_id), result) in results {
+        grouped.entry(workflow.clone()).or_default().insert(*step_id, result.clone());
+    }
+    grouped
+}
+
+/// Profile frame name of a step, e.g. `step 3 fetch` or `step 2 if`
+fn step_frame(step: &Step) -> String {
+    let kind = match &step.content {
+        StepContent::Command(command) => command.name.as_str(),
+        StepContent::Conditional(_) => "if",
+        StepContent::WhileLoop(_) => "while",
+        StepContent::ForEach(_) => "for",
+        StepContent::TryCatch(_) => "try",
+        StepContent::CallWorkflow(_) => "call",
+        StepContent::Return(_) => "return",
+        StepContent::Assignment(_) | StepContent::Declaration(_) => "assign",
+    };
+    format!("step {} {}", step.id, kind)
+}
+
+/// Ids of every step in a block, nested ones included
+#[derive(Default)]
+pub(crate) struct StepIds(pub(crate) HashSet<u32>);
+
+impl Visitor for StepIds {
+    fn visit_step(&mut self, step: &Step) {
+        if step.id != 0 {
+            self.0.insert(step.id);
+        }
+        walk_step(self, step);
+    }
+}
+
+/// Where a runtime error happened, added as context by the innermost step it passed through
+#[derive(Debug)]
+struct StepLocation(String);
+
+impl std::fmt::Display for StepLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Prefix an error with its step and source line, e.g. "Step 3 (line 12): Undefined variable: x".
+/// The underlying `DslError` can still be reached with `downcast_ref`.
+fn locate_error(error: anyhow::Error, step: &Step) -> anyhow::Error {
+    // Failed steps already name their step, and outer steps keep the innermost location
+    let located = error.downcast_ref::<StepLocation>().is_some()
+        || matches!(error.downcast_ref::<DslError>(), Some(DslError::StepFailed { .. }));
+    if located || step.line == 0 {
+        return error;
+    }
+    
+    let location = match step.id {
+        0 => format!("Line {}", step.line),
+        id => format!("Step {} (line {})", id, step.line),
+    };
+    let message = format!("{}: {}", location, error);
+    error.context(StepLocation(message))
+}
+
+/// Value of `step N.property` for a step's result
+fn step_property(result: &StepResult, property: Option<&str>) -> Value {
+    match property {
+        Some("status") => Value::Number(result.status as f64),
+        Some("data") => result.data.clone(),
+        Some("message") => Value::String(result.message.clone()),
+        Some("success") => Value::Bool(result.success),
+        Some("meta") => Value::Object(result.metadata.clone()),
+        // Other properties index into structured data, e.g. `step 2.valid`
+        Some(property) => match &result.data {
+            Value::Object(fields) => fields.get(property).cloned().unwrap_or(Value::Null),
+            other => other.clone(),
+        },
+        None => result.data.clone(),
+    }
+}
+
+/// Built-in command handlers and the parameters they take by name
+fn builtin_commands() -> HashMap<String, Box<dyn CommandHandler>> {
+    let builtins: [(&str, BuiltinCommand); 28] = [
+        ("print", print_command),
+        ("log", print_command),
+        ("fetch", fetch_command),
+        ("send_email", send_email_command),
+        ("notify", print_command),
+        ("sleep", sleep_command),
+        ("set", set_command),
+        ("del", set_command),
+        ("merge", merge_command),
+        ("keys", keys_command),
+        ("values", keys_command),
+        ("to_json", serialize_command),
+        ("to_yaml", serialize_command),
+        ("to_csv", serialize_command),
+        ("store", store_command),
+        ("load", load_command),
+        ("version_compare", version_compare_command),
+        ("assert_duration", assert_duration_command),
+        ("input", input_command),
+        ("generate", generate_command),
+        ("output", output_command),
+        ("transform", transform_command),
+        ("filter", filter_command),
+        ("sort", sort_command),
+        ("pick", pick_command),
+        ("summarize", summarize_command),
+        ("analyze", analyze_command),
+        ("validate", validate_command),
+    ];
+    builtins
+        .into_iter()
+        .map(|(name, run)| {
+            let handler: Box<dyn CommandHandler> = Box::new(Builtin { run, parameters: command_parameters(name) });
+            (name.to_string(), handler)
+        })
+        .collect()
+}
+
+/// Display text of an argument; arguments left out, or skipped over by a later named
+/// argument, take the default
+fn argument(values: &[Value], index: usize, default: &str) -> String {
+    match values.get(index) {
+        Some(Value::Null) | None => default.to_string(),
+        Some(value) => value.to_display_string(),
+    }
+}
+
+/// `print`, `log` and `notify` join all their arguments into one message
+fn print_command(context: &mut CommandContext, values: &[Value]) -> Result<StepResult> {
+    let message = values.iter().map(Value::to_display_string).collect::<Vec<_>>().join(" ");
+    let (icon, label, done) = match context.name() {
+        "log" => ("📝", "Log", "Log executed successfully"),
+        "notify" => ("🔔", "Notify", "Notification sent successfully"),
+        _ => ("📤", "Print", "Print executed successfully"),
+    };
+    say!(context.executor, "    {} {}: {}", icon, label, message);
+    Ok(StepResult::new(true, message, 200, done.to_string()))
+}
+
+fn fetch_command(context: &mut CommandContext, values: &[Value]) -> Result<StepResult> {
+    let executor = &*context.executor;
+    let url = argument(values, 0, DEFAULT_FETCH_URL);
+    let timeout = match values.get(1).and_then(Value::as_number) {
+        Some(ms) => Duration::from_millis(ms.max(0.0) as u64),
+        None => DEFAULT_FETCH_TIMEOUT,
+    };
+    say!(executor, "    🌐 Fetch: {}", url);
+    #[cfg(feature = "tokio")]
+    let result = match &executor.runtime {
+        Some(runtime) => runtime.block_on(fetch_url_async(&url, timeout)),
+        None => fetch_url(&url, timeout),
+    };
+    #[cfg(not(feature = "tokio"))]
+    let result = fetch_url(&url, timeout);
+    if !result.success {
+        say!(executor, "    ⚠️  {}", result.message);
+    }
+    Ok(result)
+}
+
+fn send_email_command(context: &mut CommandContext, values: &[Value]) -> Result<StepResult> {
+    let to = argument(values, 0, "user@example.com");
+    let subject = argument(values, 1, "Notification");
+    say!(context.executor, "    📧 Send Email: {} - {}", to, subject);
+    Ok(StepResult::new(true, format!("Email sent to {}", to), 200, "Email sent successfully".to_string()))
+}
+
+fn sleep_command(context: &mut CommandContext, values: &[Value]) -> Result<StepResult> {
+    let millis: u64 = argument(values, 0, "0")
+        .parse::<f64>()
+        .map(|ms| ms.max(0.0) as u64)
+        .unwrap_or(0);
+    say!(context.executor, "    💤 Sleep: {}ms", millis);
+    std::thread::sleep(Duration::from_millis(millis));
+    Ok(StepResult::new(true, millis.to_string(), 200, "Sleep completed successfully".to_string()))
+}
+
+/// `set` and `del` return a modified copy of the object, the input is never changed
+fn set_command(context: &mut CommandContext, values: &[Value]) -> Result<StepResult> {
+    let mut fields = match values.first() {
+        Some(Value::Object(fields)) => fields.clone(),
+        Some(Value::Null) | None => IndexMap::new(),
+        Some(other) => return Ok(context.fail(format!("{} expects an object, got {}", context.name(), other.type_name()))),
+    };
+    let key = argument(values, 1, "");
+    
+    if context.name() == "set" {
+        let value = values.get(2).cloned().unwrap_or(Value::Null);
+        say!(context.executor, "    🗂️  Set: {} = {}", key, value);
+        fields.insert(key, value);
+    } else {
+        say!(context.executor, "    🗂️  Del: {}", key);
+        fields.shift_remove(&key);
+    }
+    
+    Ok(StepResult::new(true, Value::Object(fields), 200, format!("{} executed successfully", context.name())))
+}
+
+/// Shallow merge: a key of a later object replaces the whole value of an earlier
+/// one, nested objects included, and keeps the position it first appeared at
+fn merge_command(context: &mut CommandContext, values: &[Value]) -> Result<StepResult> {
+    let mut merged = IndexMap::new();
+    for (index, value) in values.iter().enumerate() {
+        let Value::Object(fields) = value else {
+            return Ok(context.fail(format!("merge expects objects, got {} as argument {}", value.type_name(), index + 1)));
+        };
+        merged.extend(fields.iter().map(|(key, value)| (key.clone(), value.clone())));
+    }
+    say!(context.executor, "    🧩 Merge: {} object(s) into {} key(s)", values.len(), merged.len());
+    Ok(StepResult::new(true, Value::Object(merged), 200, "Merge executed successfully".to_string()))
+}
+
+fn keys_command(context: &mut CommandContext, values: &[Value]) -> Result<StepResult> {
+    let items = match values.first() {
+        Some(Value::Object(fields)) if context.name() == "keys" => {
+            fields.keys().map(|key| Value::string(key)).collect()
+        }
+        Some(Value::Object(fields)) => fields.values().cloned().collect(),
+        other => {
+            let type_name = other.map(Value::type_name).unwrap_or("nothing");
+            return Ok(context.fail(format!("{} expects an object, got {}", context.name(), type_name)));
+        }
+    };
+    let items = Value::Array(items);
+    say!(context.executor, "    🗂️  {}: {}", context.name(), items);
+    Ok(StepResult::new(true, items, 200, format!("{} executed successfully", context.name())))
+}
+
+/// Serialize a value into a string for use in later steps, unlike `output` nothing is written
+fn serialize_command(context: &mut CommandContext, values: &[Value]) -> Result<StepResult> {
+    let value = values.first().cloned().unwrap_or(Value::Null);
+    let text = match context.name() {
+        "to_json" => value.to_json(),
+        "to_yaml" => value.to_yaml(),
+        _ => match value.to_csv() {
+            Ok(text) => text,
+            Err(e) => return Ok(context.fail(e.to_string())),
+        },
+    };
+    say!(context.executor, "    🧾 {}: {}", context.name(), text.replace('\n', "\\n"));
+    Ok(StepResult::new(true, text, 200, format!("{} executed successfully", context.name())))
+}
+
+/// A key-value store that outlives the workflow, for handing data to later workflows
+fn store_command(context: &mut CommandContext, values: &[Value]) -> Result<StepResult> {
+    let executor = &mut *context.executor;
+    let key = argument(values, 0, "");
+    let value = values.get(1).cloned().unwrap_or(Value::Null);
+    say!(executor, "    💾 Store: {} = {}", key, value);
+    let added = Footprint::of(&value);
+    let replaced = executor.store.insert(key.clone(), value.clone());
+    executor.track(added, replaced.as_ref().map(Footprint::of));
+    Ok(StepResult::new(true, value, 200, format!("Stored '{}'", key)))
+}
+
+fn load_command(context: &mut CommandContext, values: &[Value]) -> Result<StepResult> {
+    let key = argument(values, 0, "");
+    match context.executor.store.get(&key).cloned() {
+        Some(value) => {
+            say!(context.executor, "    💾 Load: {} = {}", key, value);
+            Ok(StepResult::new(true, value, 200, format!("Loaded '{}'", key)))
+        }
+        None => {
+            let message = format!("Nothing stored under '{}'", key);
+            say!(context.executor, "    ⚠️  {}", message);
+            Ok(StepResult::new(false, Value::Null, 404, message))
+        }
+    }
+}
+
+/// -1, 0 or 1 as `a` is older than, the same as or newer than `b`
+fn version_compare_command(context: &mut CommandContext, values: &[Value]) -> Result<StepResult> {
+    let (left, right) = (argument(values, 0, ""), argument(values, 1, ""));
+    let ordering = match compare_versions(&left, &right) {
+        Ok(ordering) => ordering as i8,
+        Err(e) => return Ok(context.fail(e)),
+    };
+    say!(context.executor, "    🔢 version_compare({}, {}) = {}", left, right, ordering);
+    Ok(StepResult::new(true, Value::Number(ordering as f64), 200, "version_compare executed successfully".to_string()))
+}
+
+/// Fails the step when the referenced step ran longer than `ms` milliseconds
+fn assert_duration_command(context: &mut CommandContext, values: &[Value]) -> Result<StepResult> {
+    let command = context.command;
+    let reference = command.arguments.first().or_else(|| command.named_arguments.get("step"));
+    // The reference was already evaluated, so a step that hasn't run has errored by now
+    let measured = match reference.map(|expression| &expression.kind) {
+        Some(ExpressionKind::StepReference { step_id, property: None }) => Some(*step_id),
+        Some(ExpressionKind::PreviousStep { property: None }) => context.executor.previous_step,
+        _ => None,
+    };
+    let (Some(measured), Ok(limit)) = (measured, argument(values, 1, "").parse::<f64>()) else {
+        return Ok(context.fail("assert_duration expects a step reference and a limit in ms, e.g. assert_duration(step 1, 500)".to_string()));
+    };
+    let elapsed = context.executor.step_result(measured)
+        .map(|result| result.duration.as_secs_f64() * 1000.0)
+        .unwrap_or_default();
+    let within = elapsed <= limit;
+    say!(context.executor, "    ⏱️  Step {} took {:.1}ms (limit {}ms)", measured, elapsed, format_number(limit));
+    let data = Value::object(vec![
+        ("step", Value::Number(measured as f64)),
+        ("duration_ms", Value::Number(elapsed)),
+        ("limit_ms", Value::Number(limit)),
+        ("within", Value::Bool(within)),
+    ]);
+    Ok(if within {
+        StepResult::new(true, data, 200, format!("Step {} finished within {}ms", measured, format_number(limit)))
+    } else {
+        StepResult::new(false, data, 422, format!(
+            "Step {} took {:.1}ms, over the {}ms limit", measured, elapsed, format_number(limit)
+        ))
+    })
+}
+
+fn input_command(context: &mut CommandContext, values: &[Value]) -> Result<StepResult> {
+    let variable_name = argument(values, 0, "user_input");
+    let input_type = argument(values, 1, "text");
+    let placeholder = argument(values, 2, "Enter value");
+    say!(context.executor, "    📝 Input: Collect '{}' as {} ({})", variable_name, input_type, placeholder);
+    
+    let mut rules = Vec::new();
+    if values.get(3).is_some_and(Value::is_truthy) {
+        rules.push("required".to_string());
+    }
+    for (index, bound) in [(4, "min"), (5, "max")] {
+        if let Some(limit) = values.get(index).filter(|limit| **limit != Value::Null) {
+            rules.push(format!("{}:{}", bound, limit.to_display_string()));
+        }
+    }
+    let request = InputRequest { variable: variable_name, input_type, placeholder, rules };
+    let mut fields = vec![
+        ("variable", Value::String(request.variable.clone())),
+        ("type", Value::String(request.input_type.clone())),
+        ("placeholder", Value::String(request.placeholder.clone())),
+    ];
+    
+    // Without a provider, input collection is only simulated
+    let Some(provider) = context.executor.input_provider.as_mut() else {
+        return Ok(StepResult::new(true, Value::object(fields), 200, "Input collected successfully".to_string()));
+    };
+    let Some(answer) = provider.provide(&request) else {
+        return Ok(context.fail(format!("No input provided for '{}'", request.variable)));
+    };
+    fields.push(("value", answer.clone()));
+    match request.rules.iter().try_for_each(|rule| validate_value(&answer, rule)) {
+        Ok(()) => Ok(StepResult::new(true, Value::object(fields), 200, "Input collected successfully".to_string())),
+        Err(reason) => {
+            say!(context.executor, "    ⚠️  Input '{}' is invalid: {}", request.variable, reason);
+            fields.push(("error", Value::String(reason.clone())));
+            let message = format!("Invalid input for '{}': {}", request.variable, reason);
+            context.halt_if_invalid(StepResult::new(false, Value::object(fields), 422, message), "Invalid input", &reason)
+        }
+    }
+}
+
+fn generate_command(context: &mut CommandContext, values: &[Value]) -> Result<StepResult> {
+    let executor = &*context.executor;
+    let prompt = argument(values, 0, "Generate content");
+    let model = argument(values, 1, "mistral-small-latest");
+    let temperature = argument(values, 2, "0.7");
+    say!(executor, "    🤖 Generate: Using {} (temp: {}) with prompt: '{}'", model, temperature, prompt);
+    
+    if let Some(policy) = &executor.generation_policy {
+        if let Some(violation) = policy.check_model(&model).or_else(|| policy.check_temperature(&temperature)) {
+            return Ok(context.fail(format!("Generation policy: {}", violation)));
+        }
+    }
+    
+    #[cfg(all(feature = "tokio", feature = "ai"))]
+    let result = match &executor.runtime {
+        Some(runtime) => runtime.block_on(generate_content_async(executor.ai_endpoint.as_deref(), &prompt, &model, &temperature)),
+        None => generate_content(executor.ai_endpoint.as_deref(), &prompt, &model, &temperature),
+    };
+    #[cfg(not(all(feature = "tokio", feature = "ai")))]
+    let result = generate_content(executor.ai_endpoint.as_deref(), &prompt, &model, &temperature);
+    if !result.success {
+        say!(executor, "    ⚠️  {}", result.message);
+    }
+    Ok(result)
+}
+
+fn output_command(context: &mut CommandContext, values: &[Value]) -> Result<StepResult> {
+    let data_ref = argument(values, 0, "data");
+    let format = argument(values, 1, "text");
+    let filename = argument(values, 2, "output");
+    say!(context.executor, "    📤 Output: Export {} as {} to {}", data_ref, format, filename);
+    
+    Ok(StepResult::new(
+        true,
+        Value::object(vec![
+            ("exported", Value::String(data_ref)),
+            ("format", Value::String(format)),
+            ("file", Value::String(filename)),
+        ]),
+        200,
+        "Output exported successfully".to_string()
+    ))
+}
+
+fn transform_command(context: &mut CommandContext, values: &[Value]) -> Result<StepResult> {
+    let data_ref = argument(values, 0, "data");
+    let transformation = argument(values, 1, "format");
+    say!(context.executor, "    🔄 Transform: Apply {} to {}", transformation, data_ref);
+    
+    Ok(StepResult::new(
+        true,
+        Value::object(vec![
+            ("transformed", Value::String(data_ref)),
+            ("type", Value::String(transformation)),
+        ]),
+        200,
+        "Data transformed successfully".to_string()
+    ))
+}
+
+/// Keep the elements of an array that match a predicate such as "price > 10"
+fn filter_command(context: &mut CommandContext, values: &[Value]) -> Result<StepResult> {
+    let predicate = argument(values, 1, "");
+    let filtered = match values.first() {
+        Some(Value::Array(items)) => filter_items(items, &predicate),
+        other => Err(format!("filter expects an array, got {}", other.map(Value::type_name).unwrap_or("nothing"))),
+    };
+    match filtered {
+        Ok(items) => {
+            say!(context.executor, "    🔎 Filter: {} item(s) match '{}'", items.len(), predicate);
+            Ok(StepResult::new(true, Value::Array(items), 200, "Filter executed successfully".to_string()))
+        }
+        Err(e) => Ok(context.fail(e)),
+    }
+}
+
+/// A sorted copy of an array of numbers or strings, or of objects by one of their fields
+fn sort_command(context: &mut CommandContext, values: &[Value]) -> Result<StepResult> {
+    let key = values.get(1).filter(|key| **key != Value::Null).map(Value::to_display_string);
+    let sorted = match values.first() {
+        Some(Value::Array(items)) => sort_items(items, key.as_deref()),
+        other => Err(format!("sort expects an array, got {}", other.map(Value::type_name).unwrap_or("nothing"))),
+    };
+    match sorted {
+        Ok(items) => {
+            say!(context.executor, "    🔀 Sort: {} item(s){}", items.len(), key.map(|key| format!(" by '{}'", key)).unwrap_or_default());
+            Ok(StepResult::new(true, Value::Array(items), 200, "Sort executed successfully".to_string()))
+        }
+        Err(e) => Ok(context.fail(e)),
+    }
+}
+
+fn pick_command(context: &mut CommandContext, values: &[Value]) -> Result<StepResult> {
+    let items = match values.first() {
+        Some(Value::Array(items)) if !items.is_empty() => items,
+        Some(Value::Array(_)) => return Ok(context.fail("pick needs at least one item to choose from".to_string())),
+        other => {
+            let found = other.map(Value::type_name).unwrap_or("nothing");
+            return Ok(context.fail(format!("pick expects an array, got {}", found)));
+        }
+    };
+    let index = context.executor.random.next_index(items.len());
+    say!(context.executor, "    🎲 Pick: item {} of {}", index + 1, items.len());
+    let mut result = StepResult::new(true, items[index].clone(), 200, "Pick executed successfully".to_string());
+    result.metadata.insert("index".to_string(), Value::Number(index as f64));
+    Ok(result)
+}
+
+fn summarize_command(context: &mut CommandContext, values: &[Value]) -> Result<StepResult> {
+    let summary = summarize_value(values.first().unwrap_or(&Value::Null));
+    say!(context.executor, "    📋 Summarize: {}", summary);
+    Ok(StepResult::new(true, summary, 200, "Summary created successfully".to_string()))
+}
+
+fn analyze_command(context: &mut CommandContext, values: &[Value]) -> Result<StepResult> {
+    let stats = match values.first() {
+        Some(Value::Array(items)) => analyze_numbers(items),
+        other => Err(format!("analyze expects an array of numbers, got {}", other.map(Value::type_name).unwrap_or("nothing"))),
+    };
+    match stats {
+        Ok(stats) => {
+            say!(context.executor, "    📊 Analyze: {}", stats);
+            Ok(StepResult::new(true, stats, 200, "Analysis completed successfully".to_string()))
+        }
+        Err(e) => Ok(context.fail(e)),
+    }
+}
+
+fn validate_command(context: &mut CommandContext, values: &[Value]) -> Result<StepResult> {
+    let data_ref = argument(values, 0, "data");
+    let validation_type = argument(values, 1, "required");
+    say!(context.executor, "    ✅ Validate: Check {} for {}", data_ref, validation_type);
+    
+    let outcome = validate_value(values.first().unwrap_or(&Value::Null), &validation_type);
+    let mut fields = vec![
+        ("validated", Value::String(data_ref)),
+        ("type", Value::String(validation_type)),
+        ("valid", Value::Bool(outcome.is_ok())),
+    ];
+    match outcome {
+        Ok(()) => Ok(StepResult::new(true, Value::object(fields), 200, "Validation completed successfully".to_string())),
+        Err(reason) => {
+            say!(context.executor, "    ⚠️  Validation failed: {}", reason);
+            fields.push(("error", Value::String(reason.clone())));
+            let message = format!("Validation failed: {}", reason);
+            context.halt_if_invalid(StepResult::new(false, Value::object(fields), 422, message), "Validation failed", &reason)
+        }
+    }
+}
+
+/// Names of the built-in commands' parameters in positional order, for named arguments.
+/// Commands that join all their arguments, like `print`, take no named arguments.
+fn command_parameters(command: &str) -> &'static [&'static str] {
+    match command {
+        "fetch" => &["url", "timeout_ms"],
+        "send_email" => &["to", "subject"],
+        "sleep" => &["ms"],
+        "set" => &["object", "key", "value"],
+        "del" => &["object", "key"],
+        "keys" | "values" => &["object"],
+        "to_json" | "to_yaml" | "to_csv" => &["value"],
+        "version_compare" => &["a", "b"],
+        "store" => &["key", "value"],
+        "load" => &["key"],
+        "assert_duration" => &["step", "ms"],
+        "input" => &["variable", "type", "placeholder", "required", "min", "max"],
+        "generate" => &["prompt", "model", "temperature"],
+        "output" => &["data", "format", "file"],
+        "transform" => &["data", "transformation"],
+        "validate" => &["data", "type"],
+        "filter" => &["data", "predicate"],
+        "sort" => &["data", "key"],
+        "pick" | "summarize" | "analyze" => &["data"],
+        _ => &[],
+    }
+}
+
+/// Compare two semantic versions segment by segment, so `1.10.0` is newer than `1.9.0`.
+/// A leading `v` and `+build` metadata are ignored, missing segments count as 0 and a
+/// `-prerelease` version sorts before its release, with prerelease identifiers compared as semver does.
+fn compare_versions(left: &str, right: &str) -> std::result::Result<Ordering, String> {
+    let parse = |version: &str| -> std::result::Result<(Vec<u64>, Option<String>), String> {
+        let invalid = || format!("'{}' is not a valid version", version);
+        let trimmed = version.trim();
+        let trimmed = trimmed.strip_prefix(['v', 'V']).unwrap_or(trimmed);
+        let trimmed = trimmed.split_once('+').map_or(trimmed, |(core, _)| core);
+        let (core, prerelease) = match trimmed.split_once('-') {
+            Some((core, prerelease)) if !prerelease.is_empty() => (core, Some(prerelease.to_string())),
+            Some(_) => return Err(invalid()),
+            None => (trimmed, None),
+        };
+        let segments = core
+            .split('.')
+            .map(|segment| segment.parse::<u64>().map_err(|_| invalid()))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        if prerelease.as_deref().is_some_and(|prerelease| prerelease.split('.').any(str::is_empty)) {
+            return Err(invalid());
+        }
+        Ok((segments, prerelease))
+    };
+    
+    let (left_segments, left_pre) = parse(left)?;
+    let (right_segments, right_pre) = parse(right)?;
+    
+    let length = left_segments.len().max(right_segments.len());
+    for index in 0..length {
+        let ordering = left_segments.get(index).unwrap_or(&0).cmp(right_segments.get(index).unwrap_or(&0));
+        if ordering != Ordering::Equal {
+            return Ok(ordering);
+        }
+    }
+    
+    Ok(match (left_pre, right_pre) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(left_pre), Some(right_pre)) => {
+            let mut left_ids = left_pre.split('.');
+            let mut right_ids = right_pre.split('.');
+            loop {
+                match (left_ids.next(), right_ids.next()) {
+                    (None, None) => break Ordering::Equal,
+                    (None, Some(_)) => break Ordering::Less,
+                    (Some(_), None) => break Ordering::Greater,
+                    (Some(left_id), Some(right_id)) => {
+                        // Numeric identifiers compare numerically and sort before alphanumeric ones
+                        let ordering = match (left_id.parse::<u64>(), right_id.parse::<u64>()) {
+                            (Ok(left_number), Ok(right_number)) => left_number.cmp(&right_number),
+                            (Ok(_), Err(_)) => Ordering::Less,
+                            (Err(_), Ok(_)) => Ordering::Greater,
+                            (Err(_), Err(_)) => left_id.cmp(right_id),
+                        };
+                        if ordering != Ordering::Equal {
+                            break ordering;
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Elements matching `predicate`: an empty predicate keeps truthy elements, `field`
+/// keeps elements whose field is truthy and `[field] op value` compares the element
+/// or its field with `op` (`==`, `!=`, `>`, `<`, `>=` or `<=`). The value is read as
+/// JSON, falling back to plain text, and elements that can't be compared are dropped.
+fn filter_items(items: &[Value], predicate: &str) -> std::result::Result<Vec<Value>, String> {
+    let operators = ["==", "!=", ">=", "<=", ">", "<"];
+    // The first operator wins, and `>=` beats the `>` at the same position
+    let first = operators
+        .iter()
+        .filter_map(|op| predicate.find(op).map(|index| (index, *op)))
+        .min_by_key(|(index, op)| (*index, std::cmp::Reverse(op.len())));
+    let (path, comparison) = match first {
+        Some((index, op)) => {
+            let expected = Value::from_json_text(predicate[index + op.len()..].trim());
+            (predicate[..index].trim(), Some((op, expected)))
+        }
+        None => (predicate.trim(), None),
+    };
+    if !path.is_empty() && path.split('.').any(|segment| segment.trim().is_empty()) {
+        return Err(format!("'{}' is not a valid filter predicate", predicate));
+    }
+    
+    let field = |item: &Value| -> Value {
+        if path.is_empty() {
+            return item.clone();
+        }
+        path.split('.').try_fold(item, |value, segment| match value {
+            Value::Object(fields) => fields.get(segment.trim()),
+            _ => None,
+        })
+        .cloned()
+        .unwrap_or(Value::Null)
+    };
+    Ok(items
+        .iter()
+        .filter(|item| {
+            let actual = field(item);
+            match &comparison {
+                None => actual.is_truthy(),
+                Some((op, expected)) => match *op {
+                    "==" => values_equal(&actual, expected),
+                    "!=" => !values_equal(&actual, expected),
+                    _ => compare_values(&actual, expected).is_ok_and(|ordering| match *op {
+                        ">" => ordering.is_gt(),
+                        "<" => ordering.is_lt(),
+                        ">=" => ordering.is_ge(),
+                        _ => ordering.is_le(),
+                    }),
+                },
+            }
+        })
+        .cloned()
+        .collect())
+}
+
+/// Type and size of a value, plus the keys of an object or of an array's objects
+fn summarize_value(value: &Value) -> Value {
+    let mut fields = vec![("type", Value::string(value.type_name()))];
+    match value {
+        Value::Array(items) => {
+            fields.push(("length", Value::Number(items.len() as f64)));
+            let mut keys: Vec<&String> = Vec::new();
+            for item in items {
+                if let Value::Object(item_fields) = item {
+                    for key in item_fields.keys() {
+                        if !keys.contains(&key) {
+                            keys.push(key);
+                        }
+                    }
+                }
+            }
+            if !keys.is_empty() {
+                fields.push(("keys", Value::Array(keys.into_iter().map(|key| Value::string(key)).collect())));
+            }
+        }
+        Value::Object(object) => {
+            fields.push(("length", Value::Number(object.len() as f64)));
+            fields.push(("keys", Value::Array(object.keys().map(|key| Value::string(key)).collect())));
+        }
+        Value::String(text) => {
+            fields.push(("length", Value::Number(text.chars().count() as f64)));
+            fields.push(("words", Value::Number(text.split_whitespace().count() as f64)));
+        }
+        Value::Function(function) => fields.push(("name", Value::string(&function.name))),
+        Value::Number(_) | Value::Bool(_) | Value::Null => {}
+    }
+    Value::object(fields)
+}
+
+/// Count, sum, min, max, mean and median of an array of numbers (numeric text counts)
+fn analyze_numbers(items: &[Value]) -> std::result::Result<Value, String> {
+    let mut numbers = items
+        .iter()
+        .map(|item| item.as_number().ok_or_else(|| format!("analyze expects numbers, got {}", item.to_display_string())))
+        .collect::<std::result::Result<Vec<f64>, String>>()?;
+    numbers.sort_by(f64::total_cmp);
+    
+    let count = numbers.len();
+    let sum: f64 = numbers.iter().sum();
+    let stat = |value: Option<f64>| value.map(Value::Number).unwrap_or(Value::Null);
+    let median = match count {
+        0 => None,
+        _ if count % 2 == 1 => Some(numbers[count / 2]),
+        _ => Some((numbers[count / 2 - 1] + numbers[count / 2]) / 2.0),
+    };
+    Ok(Value::object(vec![
+        ("count", Value::Number(count as f64)),
+        ("sum", Value::Number(sum)),
+        ("min", stat(numbers.first().copied())),
+        ("max", stat(numbers.last().copied())),
+        ("mean", stat((count > 0).then(|| sum / count as f64))),
+        ("median", stat(median)),
+    ]))
+}
+
+/// Stable sort of numbers, numerically, or of strings, lexicographically. With a `key`
+/// the elements must be objects and are ordered by that field. Keys of different
+/// types, or of any other type, can't be ordered.
+fn sort_items(items: &[Value], key: Option<&str>) -> std::result::Result<Vec<Value>, String> {
+    let sort_keys = items
+        .iter()
+        .map(|item| match (key, item) {
+            (None, item) => Ok(item),
+            (Some(key), Value::Object(fields)) => fields.get(key).ok_or_else(|| format!("an element has no '{}' field", key)),
+            (Some(key), other) => Err(format!("cannot read '{}' of {}", key, other.type_name())),
+        })
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    if let Some(first) = sort_keys.first() {
+        if !matches!(first, Value::Number(_) | Value::String(_)) {
+            return Err(format!("cannot sort by {} values, only numbers or strings", first.type_name()));
+        }
+        if let Some(other) = sort_keys.iter().find(|other| other.type_name() != first.type_name()) {
+            return Err(format!("cannot sort a mix of {} and {} values", first.type_name(), other.type_name()));
+        }
+    }
+    
+    let mut order: Vec<usize> = (0..items.len()).collect();
+    order.sort_by(|&left, &right| match (sort_keys[left], sort_keys[right]) {
+        (Value::Number(left), Value::Number(right)) => left.total_cmp(right),
+        (Value::String(left), Value::String(right)) => left.cmp(right),
+        _ => Ordering::Equal,
+    });
+    Ok(order.into_iter().map(|index| items[index].clone()).collect())
+}
+
+/// Check a value against a `validate` rule: `required`, `email`, `number`, `min:N` or `max:N`.
+/// Returns why the value doesn't pass.
+fn validate_value(value: &Value, rule: &str) -> std::result::Result<(), String> {
+    let text = value.to_display_string();
+    match rule.split_once(':') {
+        None if rule == "required" => {
+            let empty = match value {
+                Value::Null => true,
+                Value::String(text) => text.trim().is_empty(),
+                Value::Array(items) => items.is_empty(),
+                Value::Object(fields) => fields.is_empty(),
+                Value::Number(_) | Value::Bool(_) | Value::Function(_) => false,
+            };
+            if empty { Err("value is required".to_string()) } else { Ok(()) }
+        }
+        None if rule == "email" => {
+            let valid = match text.split_once('@') {
+                Some((local, domain)) => !local.is_empty()
+                    && !domain.contains('@')
+                    && !text.chars().any(char::is_whitespace)
+                    && domain.split('.').count() >= 2
+                    && domain.split('.').all(|part| !part.is_empty()),
+                None => false,
+            };
+            if valid { Ok(()) } else { Err(format!("'{}' is not a valid email address", text)) }
+        }
+        None if rule == "number" => match value.as_number() {
+            Some(_) => Ok(()),
+            None => Err(format!("'{}' is not a number", text)),
+        },
+        Some((bound @ ("min" | "max"), limit)) => {
+            let limit: f64 = limit.trim().parse().map_err(|_| format!("'{}' is not a valid {} rule", rule, bound))?;
+            let number = value.as_number().ok_or_else(|| format!("'{}' is not a number", text))?;
+            match bound {
+                "min" if number < limit => Err(format!("{} is less than {}", text, format_number(limit))),
+                "max" if number > limit => Err(format!("{} is greater than {}", text, format_number(limit))),
+                _ => Ok(()),
+            }
+        }
+        _ => Err(format!("unknown validation type '{}'", rule)),
+    }
+}
+
+/// GET the URL for real; connection failures and running past `timeout` become a
+/// failed result with status 0 instead of an error
+#[cfg(feature = "http")]
+fn fetch_url(url: &str, timeout: Duration) -> StepResult {
+    let response = reqwest::blocking::Client::builder()
+        .timeout(timeout)
+        .build()
+        .and_then(|client| client.get(url).send())
+        .map(|response| {
+            let (status, headers) = (response.status(), response.headers().clone());
+            (status, headers, response.text())
+        });
+    fetch_result(url, timeout, response)
+}
+
+/// `fetch_url` with reqwest's async client, for the `AsyncExecutor`
+#[cfg(feature = "tokio")]
+async fn fetch_url_async(url: &str, timeout: Duration) -> StepResult {
+    let response = match reqwest::Client::builder().timeout(timeout).build() {
+        Ok(client) => client.get(url).send().await,
+        Err(e) => Err(e),
+    };
+    let response = match response {
+        Ok(response) => {
+            let (status, headers) = (response.status(), response.headers().clone());
+            Ok((status, headers, response.text().await))
+        }
+        Err(e) => Err(e),
+    };
+    fetch_result(url, timeout, response)
+}
+
+/// Step result for a fetch response: its status, headers and body
+#[cfg(feature = "http")]
+fn fetch_result(
+    url: &str,
+    timeout: Duration,
+    response: reqwest::Result<(reqwest::StatusCode, reqwest::header::HeaderMap, reqwest::Result<String>)>,
+) -> StepResult {
+    let timed_out = || StepResult::new(false, Value::Null, 0, format!("Fetch from {} failed: timeout after {}ms", url, timeout.as_millis()));
+    let result = match response {
+        Ok((status, headers, body)) => {
+            let code = status.as_u16() as u32;
+            let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok()).map(str::to_string);
+            let content_type = header("content-type");
+            let rate_limit = header("x-ratelimit-remaining");
+            let result = match body {
+                Ok(body) if status.is_success() => {
+                    StepResult::new(true, Value::from_json_text(&body), code, "Fetch completed successfully".to_string())
+                }
+                Ok(body) => StepResult::new(false, Value::from_json_text(&body), code, format!("Fetch returned HTTP {}", code)),
+                Err(e) if e.is_timeout() => timed_out(),
+                Err(e) => StepResult::new(false, Value::Null, code, format!("Failed to read response from {}: {}", url, e)),
+            };
+            let result = match content_type {
+                Some(content_type) => result.with_metadata("content_type", content_type),
+                None => result,
+            };
+            match rate_limit {
+                Some(remaining) => result.with_metadata("rate_limit", Value::from_json_text(&remaining)),
+                None => result,
+            }
+        }
+        Err(e) if e.is_timeout() => timed_out(),
+        Err(e) => StepResult::new(false, Value::Null, 0, format!("Fetch from {} failed: {}", url, e)),
+    };
+    result.with_metadata("url", url)
+}
+
+/// Lowercase host of a URL, without scheme, credentials, port or path
+fn url_host(url: &str) -> String {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let host = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    let host = match host.strip_prefix('[') {
+        // IPv6 literals keep their colons
+        Some(literal) => literal.split(']').next().unwrap_or(""),
+        None => host.split(':').next().unwrap_or(""),
+    };
+    host.to_ascii_lowercase()
+}
+
+/// Whether `host` is `pattern`, or one of its subdomains when the pattern starts with `*.`
+fn host_matches(pattern: &str, host: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host.strip_suffix(domain).is_some_and(|subdomain| subdomain.ends_with('.') && subdomain.len() > 1),
+        None => pattern == host,
+    }
+}
+
+/// Simulated fetch used when the `http` feature is disabled
+#[cfg(not(feature = "http"))]
+fn fetch_url(url: &str, _timeout: Duration) -> StepResult {
+    StepResult::new(
+        true,
+        Value::object(vec![("data", Value::String(format!("Sample data from {}", url)))]),
+        200,
+        "Fetch completed successfully".to_string()
+    )
+    .with_metadata("url", url)
+    .with_metadata("simulated", Value::Bool(true))
+}
+
+/// Ask a Mistral-compatible chat completions API for the completion text. The key is
+/// read from `AI_API_KEY`; missing keys, network and API failures become a failed result.
+#[cfg(feature = "ai")]
+fn generate_content(endpoint: Option<&str>, prompt: &str, model: &str, temperature: &str) -> StepResult {
+    let Some((endpoint, api_key, body)) = completion_request(endpoint, prompt, model, temperature) else {
+        return StepResult::new(false, Value::Null, 401, "AI_API_KEY is not set".to_string());
+    };
+    let response = reqwest::blocking::Client::new()
+        .post(&endpoint)
+        .bearer_auth(api_key)
+        .header("Content-Type", "application/json")
+        .body(body)
+        .send()
+        .map(|response| (response.status(), response.text()));
+    completion_result(&endpoint, model, response)
+}
+
+/// `generate_content` with reqwest's async client, for the `AsyncExecutor`
+#[cfg(all(feature = "tokio", feature = "ai"))]
+async fn generate_content_async(endpoint: Option<&str>, prompt: &str, model: &str, temperature: &str) -> StepResult {
+    let Some((endpoint, api_key, body)) = completion_request(endpoint, prompt, model, temperature) else {
+        return StepResult::new(false, Value::Null, 401, "AI_API_KEY is not set".to_string());
+    };
+    let response = reqwest::Client::new()
+        .post(&endpoint)
+        .bearer_auth(api_key)
+        .header("Content-Type", "application/json")
+        .body(body)
+        .send()
+        .await;
+    let response = match response {
+        Ok(response) => Ok((response.status(), response.text().await)),
+        Err(e) => Err(e),
+    };
+    completion_result(&endpoint, model, response)
+}
+
+/// Endpoint, API key and JSON body of a completion request; `None` when `AI_API_KEY` is not set
+#[cfg(feature = "ai")]
+fn completion_request(endpoint: Option<&str>, prompt: &str, model: &str, temperature: &str) -> Option<(String, String, String)> {
+    let endpoint = endpoint
+        .map(str::to_string)
+        .or_else(|| std::env::var("AI_API_ENDPOINT").ok())
+        .unwrap_or_else(|| DEFAULT_AI_ENDPOINT.to_string());
+    let api_key = std::env::var("AI_API_KEY").ok()?;
+    
+    let body = serde_json::json!({
+        "model": model,
+        "temperature": temperature.parse::<f64>().unwrap_or(0.7),
+        "messages": [{ "role": "user", "content": prompt }],
+    });
+    Some((endpoint, api_key, body.to_string()))
+}
+
+/// Step result for a completion response: its status and body
+#[cfg(feature = "ai")]
+fn completion_result(endpoint: &str, model: &str, response: reqwest::Result<(reqwest::StatusCode, reqwest::Result<String>)>) -> StepResult {
+    match response {
+        Ok((status, text)) => {
+            let code = status.as_u16() as u32;
+            let text = match text {
+                Ok(text) => text,
+                Err(e) => return StepResult::new(false, Value::Null, code, format!("Failed to read completion from {}: {}", endpoint, e)),
+            };
+            if !status.is_success() {
+                return StepResult::new(false, Value::from_json_text(&text), code, format!("Generate returned HTTP {}", code));
+            }
+            
+            let json = serde_json::from_str::<serde_json::Value>(&text).unwrap_or_default();
+            let completion = json["choices"][0]["message"]["content"].as_str().map(str::to_string);
+            match completion {
+                Some(content) => {
+                    let result = StepResult::new(true, content, code, "Content generated successfully".to_string())
+                        .with_metadata("model", json["model"].as_str().unwrap_or(model));
+                    match json["usage"]["total_tokens"].as_f64() {
+                        Some(tokens) => result.with_metadata("total_tokens", Value::Number(tokens)),
+                        None => result,
+                    }
+                }
+                None => StepResult::new(false, Value::from_json_text(&text), code, "Response has no completion text".to_string()),
+            }
+        }
+        Err(e) => StepResult::new(false, Value::Null, 0, format!("Generate request to {} failed: {}", endpoint, e)),
+    }
+}
+
+#[cfg(feature = "ai")]
+const DEFAULT_AI_ENDPOINT: &str = "https://api.mistral.ai/v1/chat/completions";
+
+/// Simulated completion used when the `ai` feature is disabled
+#[cfg(not(feature = "ai"))]
+fn generate_content(_endpoint: Option<&str>, prompt: &str, model: &str, temperature: &str) -> StepResult {
+    StepResult::new(
+        true,
+        Value::object(vec![
+            ("content", Value::String(format!("Generated content for: {}", prompt))),
+            ("model", Value::string(model)),
+            ("temperature", Value::string(temperature)),
+        ]),
+        200,
+        "Content generated successfully".to_string()
+    )
+}
+
+/// Largest whole number an f64 holds exactly; integer results must stay within it
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+
+fn is_arithmetic(operator: &str) -> bool {
+    matches!(operator, "+" | "-" | "*" | "/")
+}
+
+/// Apply `+`, `-`, `*` or `/` to two numbers. The DSL has no separate integer
+/// type: every number is an f64, so whole numbers are exact only within
+/// ±`MAX_SAFE_INTEGER` (2^53 - 1), not the i64 range. Whole-number operands use
+/// checked integer arithmetic and error instead of silently losing precision when
+/// the result leaves that range, and an operand already outside it is rejected as
+/// unsafe rather than overflowed (`10000000000000000 + 0`); other results,
+/// including every quotient, must be finite. Dividing by zero is an error.
+fn arithmetic(left: f64, operator: &str, right: f64) -> Result<Value> {
+    let overflow = || anyhow!(
+        "Integer overflow in {} {} {}",
+        format_number(left), operator, format_number(right)
+    );
+    
+    if operator == "/" && right == 0.0 {
+        return Err(anyhow!("Division by zero in {} / {}", format_number(left), format_number(right)));
+    }
+    if operator != "/" && left.fract() == 0.0 && right.fract() == 0.0 {
+        if let Some(operand) = [left, right].into_iter().find(|operand| operand.abs() > MAX_SAFE_INTEGER) {
+            return Err(anyhow!(
+                "{} is outside the safe integer range of ±{} in {} {} {}",
+                format_number(operand), format_number(MAX_SAFE_INTEGER),
+                format_number(left), operator, format_number(right)
+            ));
+        }
+        let (left_int, right_int) = (left as i64, right as i64);
+        let result = match operator {
+            "+" => left_int.checked_add(right_int),
+            "-" => left_int.checked_sub(right_int),
+            _ => left_int.checked_mul(right_int),
+        };
+        return match result {
+            Some(value) if (value as f64).abs() <= MAX_SAFE_INTEGER => Ok(Value::Number(value as f64)),
+            _ => Err(overflow()),
+        };
+    }
+    
+    let result = match operator {
+        "+" => left + right,
+        "-" => left - right,
+        "/" => left / right,
+        _ => left * right,
+    };
+    if result.is_finite() {
+        Ok(Value::Number(result))
+    } else {
+        Err(anyhow!("Numeric overflow in {} {} {}", left, operator, right))
+    }
+}
+
+/// Find a step by id, searching nested conditional and loop bodies
+fn find_step(steps: &[Step], step_id: u32) -> Option<&Step> {
+    steps.iter().find_map(|step| {
+        if step.id == step_id {
+            return Some(step);
+        }
+        match &step.content {
+            StepContent::Command(_)
+            | StepContent::CallWorkflow(_)
+            | StepContent::Return(_)
+            | StepContent::Assignment(_)
+            | StepContent::Declaration(_) => None,
+            StepContent::Conditional(conditional) => find_step(&conditional.if_steps, step_id)
+                .or_else(|| conditional.else_steps.as_deref().and_then(|steps| find_step(steps, step_id))),
+            StepContent::WhileLoop(while_loop) => find_step(&while_loop.body, step_id),
+            StepContent::ForEach(for_each) => find_step(&for_each.body, step_id),
+            StepContent::TryCatch(try_catch) => find_step(&try_catch.try_steps, step_id)
+                .or_else(|| find_step(&try_catch.catch_steps, step_id)),
+        }
+    })
+}
+
+/// Equality between typed values; numbers compare numerically and strings
+/// compare against the display form of scalars so `step 1.data == 200` works.
+/// Arrays compare element-wise in order, objects by key regardless of order.
+fn values_equal(left: &Value, right: &Value) -> bool {
+    if let (Some(left_num), Some(right_num)) = (left.as_number(), right.as_number()) {
+        return left_num == right_num;
+    }
+    match (left, right) {
+        (Value::Array(left_items), Value::Array(right_items)) => {
+            left_items.len() == right_items.len()
+                && left_items
+                    .iter()
+                    .zip(right_items)
+                    .all(|(left_item, right_item)| values_equal(left_item, right_item))
+        }
+        (Value::Object(left_fields), Value::Object(right_fields)) => {
+            left_fields.len() == right_fields.len()
+                && left_fields.iter().all(|(key, left_value)| {
+                    right_fields
+                        .get(key)
+                        .is_some_and(|right_value| values_equal(left_value, right_value))
+                })
+        }
+        (Value::String(text), other) | (other, Value::String(text))
+            if !matches!(other, Value::String(_) | Value::Array(_) | Value::Object(_)) =>
+        {
+            *text == other.to_display_string()
+        }
+        _ => left == right,
+    }
+}
+
+/// Ordering for `<`, `>`, `<=`, `>=`; numbers compare numerically, strings lexicographically
+fn compare_values(left: &Value, right: &Value) -> Result<std::cmp::Ordering> {
+    if let (Some(left_num), Some(right_num)) = (left.as_number(), right.as_number()) {
+        return left_num
+            .partial_cmp(&right_num)
+            .ok_or_else(|| anyhow!("Cannot compare {} with {}", left_num, right_num));
+    }
+    match (left, right) {
+        (Value::String(left_text), Value::String(right_text)) => Ok(left_text.cmp(right_text)),
+        _ => Err(anyhow!("Cannot compare {} with {}", left.type_name(), right.type_name())),
+    }
+}
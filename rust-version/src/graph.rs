@@ -0,0 +1,251 @@
+use std::collections::HashSet;
+use crate::analyzer::StepReferences;
+use crate::ast::*;
+use crate::printer::command_call;
+use crate::visit::Visitor;
+
+/// Graphviz DOT for the control flow of `workflow`: a node per step, solid edges
+/// for the order steps run in, labelled `true`/`false` out of conditionals, and
+/// dashed edges from each step to the steps it reads results from.
+pub fn workflow_to_dot(workflow: &Workflow) -> String {
+    let graph = FlowGraph::build(workflow, command_call);
+    let mut dot = format!("digraph {} {{\n    node [shape=box];\n", dot_string(&workflow.name));
+    for node in &graph.nodes {
+        let shape = match node.shape {
+            NodeShape::Step => String::new(),
+            NodeShape::Decision => ", shape=diamond".to_string(),
+            NodeShape::Handler => ", shape=ellipse".to_string(),
+        };
+        dot.push_str(&format!("    {} [label={}{}];\n", node.name, dot_string(&node.label), shape));
+    }
+    for edge in &graph.edges {
+        match &edge.label {
+            Some(label) => dot.push_str(&format!("    {} -> {} [label={}];\n", edge.from, edge.to, dot_string(label))),
+            None => dot.push_str(&format!("    {} -> {};\n", edge.from, edge.to)),
+        }
+    }
+    for (from, to) in &graph.dependencies {
+        dot.push_str(&format!("    {} -> {} [style=dashed];\n", from, to));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Mermaid `flowchart TD` for the control flow of `workflow`: a node per step, labelled
+/// with the command and its first argument when that is short, and decision diamonds
+/// whose `true`/`false` edges lead into each branch.
+pub fn workflow_to_mermaid(workflow: &Workflow) -> String {
+    let graph = FlowGraph::build(workflow, short_command);
+    let mut mermaid = "flowchart TD\n".to_string();
+    for node in &graph.nodes {
+        let label = mermaid_string(&node.label);
+        let shape = match node.shape {
+            NodeShape::Step => format!("[{}]", label),
+            NodeShape::Decision => format!("{{{}}}", label),
+            NodeShape::Handler => format!("([{}])", label),
+        };
+        mermaid.push_str(&format!("    {}{}\n", node.name, shape));
+    }
+    for edge in &graph.edges {
+        match &edge.label {
+            Some(label) => mermaid.push_str(&format!("    {} -->|{}| {}\n", edge.from, label, edge.to)),
+            None => mermaid.push_str(&format!("    {} --> {}\n", edge.from, edge.to)),
+        }
+    }
+    mermaid
+}
+
+/// The command name, followed by its first argument when that fits in a short label
+fn short_command(command: &Command) -> String {
+    let first = command.arguments.first().map(|argument| match &argument.kind {
+        ExpressionKind::StringLiteral(text) => text.clone(),
+        _ => argument.to_string(),
+    });
+    match first {
+        Some(first) if first.chars().count() <= 40 => format!("{} {}", command.name, first),
+        _ => command.name.clone(),
+    }
+}
+
+/// A double-quoted Mermaid label; quotes inside become the `#quot;` entity
+fn mermaid_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "#quot;"))
+}
+
+/// A double-quoted DOT string
+fn dot_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum NodeShape {
+    Step,
+    Decision,
+    Handler,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Node {
+    pub(crate) name: String,
+    pub(crate) label: String,
+    pub(crate) shape: NodeShape,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Edge {
+    pub(crate) from: String,
+    pub(crate) to: String,
+    pub(crate) label: Option<String>,
+}
+
+/// Where flow leaves a step: the node it leaves from and the label of the edge
+type Exit = (String, Option<String>);
+
+/// The steps of a workflow as nodes and edges, shared by the graph exporters
+pub(crate) struct FlowGraph {
+    pub(crate) nodes: Vec<Node>,
+    pub(crate) edges: Vec<Edge>,
+    /// (referenced step, reading step) node names
+    pub(crate) dependencies: Vec<(String, String)>,
+    /// Node of every step id, for drawing dependencies
+    step_nodes: Vec<(u32, String)>,
+    used_names: HashSet<String>,
+    command_label: fn(&Command) -> String,
+}
+
+impl FlowGraph {
+    /// The graph of `workflow`, with command steps labelled by `command_label`
+    pub(crate) fn build(workflow: &Workflow, command_label: fn(&Command) -> String) -> Self {
+        let mut graph = FlowGraph {
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            dependencies: Vec::new(),
+            step_nodes: Vec::new(),
+            used_names: HashSet::new(),
+            command_label,
+        };
+        let mut readers = Vec::new();
+        graph.steps(&workflow.steps, Vec::new(), &mut readers);
+        if let Some(handler) = &workflow.on_error {
+            let name = graph.add_node("on_error", format!("on_error({})", handler.variable), NodeShape::Handler);
+            graph.steps(&handler.steps, vec![(name, None)], &mut readers);
+        }
+        for (reader, references) in readers {
+            for id in references {
+                if let Some((_, node)) = graph.step_nodes.iter().find(|(step_id, _)| *step_id == id) {
+                    graph.dependencies.push((node.clone(), reader.clone()));
+                }
+            }
+        }
+        graph
+    }
+
+    /// Chain `steps` after `entries`, returning where flow leaves the last of them
+    fn steps(&mut self, steps: &[Step], entries: Vec<Exit>, readers: &mut Vec<(String, Vec<u32>)>) -> Vec<Exit> {
+        steps.iter().fold(entries, |entries, step| self.step(step, entries, readers))
+    }
+
+    fn step(&mut self, step: &Step, entries: Vec<Exit>, readers: &mut Vec<(String, Vec<u32>)>) -> Vec<Exit> {
+        let (text, shape, expressions): (String, NodeShape, Vec<&Expression>) = match &step.content {
+            StepContent::Command(command) => (
+                (self.command_label)(command),
+                NodeShape::Step,
+                command.arguments.iter().chain(command.named_arguments.values()).collect(),
+            ),
+            StepContent::Conditional(conditional) => {
+                (format!("if ({})", conditional.condition), NodeShape::Decision, vec![&conditional.condition])
+            }
+            StepContent::WhileLoop(while_loop) => {
+                (format!("while ({})", while_loop.condition), NodeShape::Decision, vec![&while_loop.condition])
+            }
+            StepContent::ForEach(for_each) => (
+                format!("for {} in {}", for_each.var_name, for_each.iterable),
+                NodeShape::Decision,
+                vec![&for_each.iterable],
+            ),
+            StepContent::TryCatch(_) => ("try".to_string(), NodeShape::Step, Vec::new()),
+            StepContent::CallWorkflow(call) => (format!("call {}", quote(&call.name)), NodeShape::Step, Vec::new()),
+            StepContent::Return(return_statement) => match &return_statement.value {
+                Some(value) => (format!("return {}", value), NodeShape::Step, vec![value]),
+                None => ("return".to_string(), NodeShape::Step, Vec::new()),
+            },
+            StepContent::Assignment(assignment) => {
+                (format!("{} = {}", assignment.name, assignment.value), NodeShape::Step, vec![&assignment.value])
+            }
+            StepContent::Declaration(variable) => (
+                format!("{} {} = {}", variable.keyword, variable.name, variable.value),
+                NodeShape::Step,
+                vec![&variable.value],
+            ),
+        };
+        let text = match &step.guard {
+            Some(guard) => format!("{} when ({})", text, guard),
+            None => text,
+        };
+        let label = match step.id {
+            0 => text,
+            id => format!("Step {}: {}", id, text),
+        };
+        let name = self.add_node(&format!("step{}", step.id), label, shape);
+        if step.id != 0 {
+            self.step_nodes.push((step.id, name.clone()));
+        }
+        self.connect(entries, &name);
+        let mut references = StepReferences::default();
+        expressions.into_iter().chain(&step.guard).for_each(|expression| references.visit_expression(expression));
+        readers.push((name.clone(), references.0.into_iter().collect()));
+
+        let labelled = |label: &str| vec![(name.clone(), Some(label.to_string()))];
+        match &step.content {
+            StepContent::Conditional(conditional) => self.branches(&name, conditional, readers),
+            StepContent::WhileLoop(while_loop) => {
+                let exits = self.steps(&while_loop.body, labelled("true"), readers);
+                self.connect(exits, &name);
+                labelled("false")
+            }
+            StepContent::ForEach(for_each) => {
+                let exits = self.steps(&for_each.body, labelled("each"), readers);
+                self.connect(exits, &name);
+                labelled("done")
+            }
+            StepContent::TryCatch(try_catch) => {
+                let mut exits = self.steps(&try_catch.try_steps, vec![(name.clone(), None)], readers);
+                exits.extend(self.steps(&try_catch.catch_steps, labelled("error"), readers));
+                exits
+            }
+            // Nothing runs after a return
+            StepContent::Return(_) => Vec::new(),
+            _ => vec![(name, None)],
+        }
+    }
+
+    /// Both branches of a conditional; an `else if` hangs off the `false` edge as its own decision
+    fn branches(&mut self, name: &str, conditional: &ConditionalStatement, readers: &mut Vec<(String, Vec<u32>)>) -> Vec<Exit> {
+        let labelled = |label: &str| vec![(name.to_string(), Some(label.to_string()))];
+        let mut exits = self.steps(&conditional.if_steps, labelled("true"), readers);
+        match &conditional.else_steps {
+            Some(else_steps) => exits.extend(self.steps(else_steps, labelled("false"), readers)),
+            None => exits.extend(labelled("false")),
+        }
+        exits
+    }
+
+    /// Add a node named `name`, or `name_2`, `name_3`... when an `else if` or a
+    /// statement without an id has already taken it
+    fn add_node(&mut self, name: &str, label: String, shape: NodeShape) -> String {
+        let mut unique = name.to_string();
+        let mut suffix = 1;
+        while !self.used_names.insert(unique.clone()) {
+            suffix += 1;
+            unique = format!("{}_{}", name, suffix);
+        }
+        self.nodes.push(Node { name: unique.clone(), label, shape });
+        unique
+    }
+
+    fn connect(&mut self, entries: Vec<Exit>, to: &str) {
+        for (from, label) in entries {
+            self.edges.push(Edge { from, to: to.to_string(), label });
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use crate::ast::*;
+
+/// One plain-language line per step, for readers who don't know the DSL. Steps inside
+/// conditionals, loops and `try` blocks follow their parent, indented one level per
+/// block and keeping the step ids the source gives them. A step's `///` doc comment
+/// is used as written in place of the generated text.
+pub fn human_steps(program: &Program) -> Vec<String> {
+    let mut lines = Vec::new();
+    for workflow in &program.workflows {
+        describe_steps(&workflow.steps, 0, &mut lines);
+    }
+    lines
+}
+
+fn describe_steps(steps: &[Step], depth: usize, lines: &mut Vec<String>) {
+    for step in steps {
+        describe_step(step, depth, lines);
+    }
+}
+
+fn describe_step(step: &Step, depth: usize, lines: &mut Vec<String>) {
+    let indent = "  ".repeat(depth);
+    let numbered = |text: String| {
+        let text = match &step.guard {
+            Some(guard) => format!("{}, only when {}", text, guard),
+            None => text,
+        };
+        match step.id {
+            0 => format!("{}{}", indent, text),
+            id => format!("{}Step {}: {}", indent, id, text),
+        }
+    };
+    if let Some(description) = &step.description {
+        lines.push(numbered(description.clone()));
+        return;
+    }
+    match &step.content {
+        StepContent::Command(command) => lines.push(numbered(describe_command(command))),
+        StepContent::Conditional(conditional) => {
+            lines.push(numbered(format!("If {}", conditional.condition)));
+            describe_branches(step.id, conditional, depth, lines);
+        }
+        StepContent::WhileLoop(while_loop) => {
+            lines.push(numbered(format!("Repeat while {}", while_loop.condition)));
+            describe_steps(&while_loop.body, depth + 1, lines);
+        }
+        StepContent::ForEach(for_each) => {
+            lines.push(numbered(format!("Repeat for each {} in {}", for_each.var_name, for_each.iterable)));
+            describe_steps(&for_each.body, depth + 1, lines);
+        }
+        StepContent::TryCatch(try_catch) => {
+            lines.push(numbered("Try these steps".to_string()));
+            describe_steps(&try_catch.try_steps, depth + 1, lines);
+            lines.push(format!("{}If they fail", indent));
+            describe_steps(&try_catch.catch_steps, depth + 1, lines);
+        }
+        StepContent::CallWorkflow(call) => lines.push(numbered(format!("Run the {} workflow", call.name))),
+        StepContent::Return(_) => lines.push(numbered("Finish the workflow".to_string())),
+        StepContent::Assignment(assignment) => lines.push(numbered(format!("Update {}", assignment.name))),
+        StepContent::Declaration(variable) => lines.push(numbered(format!("Define {}", variable.name))),
+    }
+}
+
+/// The steps of both branches; `else if` chains read as "Otherwise, if ..."
+fn describe_branches(id: u32, conditional: &ConditionalStatement, depth: usize, lines: &mut Vec<String>) {
+    let indent = "  ".repeat(depth);
+    describe_steps(&conditional.if_steps, depth + 1, lines);
+    match conditional.else_steps.as_deref() {
+        Some([Step { id: nested_id, content: StepContent::Conditional(nested), .. }]) if *nested_id == id => {
+            lines.push(format!("{}Otherwise, if {}", indent, nested.condition));
+            describe_branches(id, nested, depth, lines);
+        }
+        Some(else_steps) => {
+            lines.push(format!("{}Otherwise", indent));
+            describe_steps(else_steps, depth + 1, lines);
+        }
+        None => {}
+    }
+}
+
+fn describe_command(command: &Command) -> String {
+    // Literal text reads better without its quotes
+    let argument = |index: usize, name: &str| {
+        command.arguments.get(index).or_else(|| command.named_arguments.get(name)).map(|argument| match &argument.kind {
+            ExpressionKind::StringLiteral(text) => text.clone(),
+            _ => argument.to_string(),
+        })
+    };
+    let data = argument(0, "data").unwrap_or_else(|| "data".to_string());
+    let with = |text: &str, index: usize, name: &str, joiner: &str| match argument(index, name) {
+        Some(value) => format!("{} {} {}", text, joiner, value),
+        None => text.to_string(),
+    };
+    match command.name.as_str() {
+        "input" => with("Collect user input", 0, "variable", "for"),
+        "generate" => with("Generate AI content", 0, "prompt", "from"),
+        "output" => with("Export results", 1, "format", "as"),
+        "fetch" => with("Fetch data", 0, "url", "from"),
+        "transform" => with(&format!("Transform {}", data), 1, "transformation", "with"),
+        "validate" => with(&format!("Validate {}", data), 1, "type", "as"),
+        "filter" => with(&format!("Filter {}", data), 1, "predicate", "by"),
+        "sort" => with(&format!("Sort {}", data), 1, "key", "by"),
+        "pick" => format!("Pick a random value from {}", data),
+        "summarize" => format!("Summarize {}", data),
+        "analyze" => format!("Analyze numbers in {}", data),
+        name => format!("Execute {}", name),
+    }
+}
@@ -0,0 +1,86 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+use crate::ast::*;
+use crate::executor::StepIds;
+use crate::visit::Visitor;
+
+impl Program {
+    /// Prepend the variables and steps of every workflow named in an `includes`
+    /// clause, in the order listed and after that workflow's own includes. A
+    /// workflow reached twice is only merged once. A variable name or step id that
+    /// the merged parts share is an error, as are unknown and circular includes.
+    /// The included workflows keep running on their own as well.
+    pub(crate) fn resolve_includes(&mut self) -> Result<()> {
+        let mut merged = Vec::new();
+        for workflow in &self.workflows {
+            let mut parts = Merged::default();
+            self.merge(workflow, &mut vec![workflow.name.clone()], &mut parts)?;
+            merged.push(parts);
+        }
+        for (workflow, parts) in self.workflows.iter_mut().zip(merged) {
+            workflow.variables = parts.variables;
+            workflow.steps = parts.steps;
+        }
+        Ok(())
+    }
+
+    /// Merge `workflow`'s includes and then `workflow` itself into `parts`
+    fn merge(&self, workflow: &Workflow, stack: &mut Vec<String>, parts: &mut Merged) -> Result<()> {
+        for name in &workflow.includes {
+            if stack.contains(name) {
+                return Err(anyhow!("Circular include: {} -> {}", stack.join(" -> "), name));
+            }
+            if parts.included.contains(name) {
+                continue;
+            }
+            let base = self.workflows.iter()
+                .find(|candidate| &candidate.name == name)
+                .ok_or_else(|| anyhow!("Workflow '{}' includes unknown workflow '{}'", workflow.name, name))?;
+            stack.push(name.clone());
+            self.merge(base, stack, parts)?;
+            stack.pop();
+            parts.included.insert(name.clone());
+        }
+
+        for variable in &workflow.variables {
+            if let Some(other) = parts.variables.iter().find(|other| other.name == variable.name) {
+                return Err(anyhow!(
+                    "Variable '{}' of workflow '{}' conflicts with one of the same name from '{}'",
+                    variable.name, workflow.name, parts.origin(&other.name)
+                ));
+            }
+            parts.variable_origins.push(workflow.name.clone());
+            parts.variables.push(variable.clone());
+        }
+
+        let mut ids = StepIds::default();
+        workflow.steps.iter().for_each(|step| ids.visit_step(step));
+        if let Some(id) = ids.0.iter().filter(|id| parts.step_ids.contains(id)).min() {
+            return Err(anyhow!(
+                "Step {} of workflow '{}' conflicts with an included step of the same id", id, workflow.name
+            ));
+        }
+        parts.step_ids.extend(ids.0);
+        parts.steps.extend(workflow.steps.iter().cloned());
+        Ok(())
+    }
+}
+
+/// The variables and steps a workflow ends up with, built up one include at a time
+#[derive(Default)]
+struct Merged {
+    variables: Vec<VariableDeclaration>,
+    /// Workflow each of `variables` came from
+    variable_origins: Vec<String>,
+    steps: Vec<Step>,
+    step_ids: HashSet<u32>,
+    included: HashSet<String>,
+}
+
+impl Merged {
+    fn origin(&self, variable: &str) -> &str {
+        self.variables.iter()
+            .position(|other| other.name == variable)
+            .map_or("", |index| self.variable_origins[index].as_str())
+    }
+}
@@ -0,0 +1,234 @@
+use anyhow::{anyhow, Result};
+use std::ops::Range;
+use crate::ast::*;
+use crate::lexer::{Lexer, Token};
+use crate::parser::{Parser, TopLevelItem};
+use crate::visit::{walk_command_mut, walk_expression_mut, walk_function_mut, walk_step_mut, walk_variable_mut, VisitorMut};
+
+/// A top-level item together with the character range it was parsed from
+#[derive(Debug, Clone)]
+struct SourceItem {
+    range: Range<usize>,
+    item: TopLevelItem,
+}
+
+/// A parsed program that remembers where each top-level item came from, so an
+/// edit only re-parses the workflows and declarations it touches. The result
+/// of an edit is always the same as parsing the edited source from scratch.
+#[derive(Debug, Clone)]
+pub struct ParsedSource {
+    source: String,
+    items: Vec<SourceItem>,
+    program: Program,
+    reparsed_items: usize,
+}
+
+impl ParsedSource {
+    pub fn parse(source: &str) -> Result<Self> {
+        let items = parse_items(source, &line_starts(source), 0..source.chars().count())?;
+        let reparsed_items = items.len();
+        Self::from_items(source.to_string(), items, reparsed_items)
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Number of top-level items parsed by the last `parse` or `apply_edit`
+    pub fn reparsed_items(&self) -> usize {
+        self.reparsed_items
+    }
+
+    /// Replace the characters in `edit` (character offsets into the current
+    /// source) with `replacement`, re-parsing only the affected items
+    pub fn apply_edit(&self, edit: Range<usize>, replacement: &str) -> Result<Self> {
+        let old_len = self.source.chars().count();
+        if edit.start > edit.end || edit.end > old_len {
+            return Err(anyhow!("Edit range {}..{} is outside the source", edit.start, edit.end));
+        }
+
+        let new_source = splice(&self.source, &edit, replacement);
+        let new_len = new_source.chars().count();
+        let shift = |offset: usize| offset + new_len - old_len;
+
+        // Items strictly before or after the edit are untouched; an edit that merely
+        // touches an item's boundary could extend its first or last token
+        let before = self.items.iter().take_while(|item| item.range.end < edit.start).count();
+        let after = self.items
+            .iter()
+            .position(|item| item.range.start > edit.end)
+            .unwrap_or(self.items.len());
+
+        let region_start = if before > 0 { self.items[before - 1].range.end } else { 0 };
+        let region_end = if after < self.items.len() {
+            shift(self.items[after].range.start)
+        } else {
+            new_len
+        };
+
+        let new_line_starts = line_starts(&new_source);
+        let reparsed = match parse_items(&new_source, &new_line_starts, region_start..region_end) {
+            Ok(items) => items,
+            // Unbalanced braces or strings can reach beyond the region, let a full parse decide
+            Err(_) => return Self::parse(&new_source),
+        };
+
+        let reparsed_items = reparsed.len();
+        // Items after the edit move by the lines it added or removed, and those
+        // starting on the line it ended on also move sideways
+        let (old_line, old_column) = position(&line_starts(&self.source), edit.end);
+        let (new_line, new_column) = position(&new_line_starts, shift(edit.end));
+        let mut positions = ShiftPositions {
+            line: old_line,
+            lines: new_line as isize - old_line as isize,
+            columns: new_column as isize - old_column as isize,
+        };
+
+        let mut items: Vec<SourceItem> = self.items[..before].to_vec();
+        items.extend(reparsed);
+        items.extend(self.items[after..].iter().map(|item| {
+            let mut item = SourceItem {
+                range: shift(item.range.start)..shift(item.range.end),
+                item: item.item.clone(),
+            };
+            match &mut item.item {
+                TopLevelItem::Workflow(workflow) => positions.visit_workflow_mut(workflow),
+                TopLevelItem::Variables(declarations) => declarations.iter_mut().for_each(|declaration| positions.visit_variable_mut(declaration)),
+                TopLevelItem::Function(function) => positions.visit_function_mut(function),
+            }
+            item
+        }));
+
+        Self::from_items(new_source, items, reparsed_items)
+    }
+
+    fn from_items(source: String, items: Vec<SourceItem>, reparsed_items: usize) -> Result<Self> {
+        let mut program = Program { workflows: Vec::new(), variables: Vec::new(), functions: Vec::new() };
+        for source_item in &items {
+            match &source_item.item {
+                TopLevelItem::Workflow(workflow) => program.workflows.push(workflow.clone()),
+                TopLevelItem::Variables(declarations) => program.variables.extend(declarations.iter().cloned()),
+                TopLevelItem::Function(function) => program.functions.push(function.clone()),
+            }
+        }
+        program.resolve_includes()?;
+
+        Ok(ParsedSource { source, items, program, reparsed_items })
+    }
+}
+
+/// Parse every top-level item in the `region` of `source`, with positions and
+/// ranges as if the whole source had been parsed
+fn parse_items(source: &str, line_starts: &[usize], region: Range<usize>) -> Result<Vec<SourceItem>> {
+    let (region_line, region_column) = position(line_starts, region.start);
+    let mut tokens = Lexer::new(&char_slice(source, region)).tokenize()?;
+    for token in &mut tokens {
+        if token.line == 1 {
+            token.column += region_column - 1;
+        }
+        token.line += region_line - 1;
+    }
+    let mut parser = Parser::new(tokens);
+    let mut items = Vec::new();
+
+    while !parser.at_end() {
+        let start = token_offset(line_starts, parser.current_token());
+        let item = parser.parse_item()?;
+        let last = parser.previous_token().ok_or_else(|| anyhow!("Expected a top-level item"))?;
+        let end = token_offset(line_starts, last) + last.lexeme.chars().count();
+
+        items.push(SourceItem { range: start..end, item });
+    }
+
+    Ok(items)
+}
+
+/// Line and column of a character offset
+fn position(line_starts: &[usize], offset: usize) -> (usize, usize) {
+    let line = line_starts.partition_point(|start| *start <= offset);
+    (line, offset - line_starts[line - 1] + 1)
+}
+
+/// Moves the positions of an unchanged item to where it sits after an edit
+struct ShiftPositions {
+    /// Line the edit ended on, before the edit
+    line: usize,
+    lines: isize,
+    columns: isize,
+}
+
+impl ShiftPositions {
+    fn shift(&self, line: usize, column: usize) -> (usize, usize) {
+        let column = if line == self.line { column.saturating_add_signed(self.columns) } else { column };
+        (line.saturating_add_signed(self.lines), column)
+    }
+
+    fn shift_span(&self, span: &mut Span) {
+        // Generated nodes have no position to move
+        if span.is_known() {
+            (span.start_line, span.start_col) = self.shift(span.start_line, span.start_col);
+            (span.end_line, span.end_col) = self.shift(span.end_line, span.end_col);
+        }
+    }
+}
+
+impl VisitorMut for ShiftPositions {
+    fn visit_function_mut(&mut self, function: &mut FunctionDeclaration) {
+        self.shift_span(&mut function.span);
+        walk_function_mut(self, function);
+    }
+
+    fn visit_variable_mut(&mut self, variable: &mut VariableDeclaration) {
+        self.shift_span(&mut variable.span);
+        walk_variable_mut(self, variable);
+    }
+
+    fn visit_step_mut(&mut self, step: &mut Step) {
+        if step.line != 0 {
+            step.line = step.line.saturating_add_signed(self.lines);
+        }
+        self.shift_span(&mut step.span);
+        walk_step_mut(self, step);
+    }
+
+    fn visit_command_mut(&mut self, command: &mut Command) {
+        self.shift_span(&mut command.span);
+        walk_command_mut(self, command);
+    }
+
+    fn visit_expression_mut(&mut self, expression: &mut Expression) {
+        self.shift_span(&mut expression.span);
+        walk_expression_mut(self, expression);
+    }
+}
+
+/// Character offset at which each line starts
+pub(crate) fn line_starts(source: &str) -> Vec<usize> {
+    let mut starts = vec![0];
+    starts.extend(
+        source.chars()
+            .enumerate()
+            .filter(|(_, c)| *c == '\n')
+            .map(|(index, _)| index + 1),
+    );
+    starts
+}
+
+pub(crate) fn token_offset(line_starts: &[usize], token: &Token) -> usize {
+    line_starts[token.line - 1] + token.column - 1
+}
+
+fn char_slice(source: &str, range: Range<usize>) -> String {
+    source.chars().skip(range.start).take(range.end - range.start).collect()
+}
+
+fn splice(source: &str, edit: &Range<usize>, replacement: &str) -> String {
+    let mut result: String = source.chars().take(edit.start).collect();
+    result.push_str(replacement);
+    result.extend(source.chars().skip(edit.end));
+    result
+}
@@ -0,0 +1,239 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use crate::ast::*;
+
+impl Program {
+    /// Flatten every `run("Name", args...)` command into the steps of the named
+    /// workflow. The callee's workflow variables act as its parameters: they
+    /// become declarations in front of its steps, with the call's extra arguments
+    /// replacing their initial values in order. Inlined steps get fresh ids after
+    /// the caller's highest id, and their step references are renumbered to match.
+    /// Named arguments bind parameters by name, e.g. `run("Notify", channel: "ops")`.
+    pub fn inline_workflow_calls(&self) -> Result<Program> {
+        let mut program = self.clone();
+        for workflow in &mut program.workflows {
+            let mut next_id = max_step_id(&workflow.steps) + 1;
+            let mut stack = vec![workflow.name.clone()];
+            workflow.steps = self.expand_steps(&workflow.steps, &mut stack, &mut next_id)?;
+        }
+        Ok(program)
+    }
+
+    fn expand_steps(&self, steps: &[Step], stack: &mut Vec<String>, next_id: &mut u32) -> Result<Vec<Step>> {
+        let mut expanded = Vec::new();
+        for step in steps {
+            let content = match &step.content {
+                StepContent::Command(command) if command.name == "run" => {
+                    let inlined = self.inline_call(command, stack, next_id)?;
+                    match &step.guard {
+                        // A guarded call becomes an `if` around the inlined steps
+                        Some(guard) => expanded.push(Step {
+                            id: 0,
+                            content: StepContent::Conditional(ConditionalStatement {
+                                condition: guard.clone(),
+                                if_steps: inlined,
+                                else_steps: None,
+                            }),
+                            description: step.description.clone(),
+                            guard: None,
+                            ..*step
+                        }),
+                        None => expanded.extend(inlined),
+                    }
+                    continue;
+                }
+                StepContent::Conditional(conditional) => StepContent::Conditional(ConditionalStatement {
+                    condition: conditional.condition.clone(),
+                    if_steps: self.expand_steps(&conditional.if_steps, stack, next_id)?,
+                    else_steps: match &conditional.else_steps {
+                        Some(else_steps) => Some(self.expand_steps(else_steps, stack, next_id)?),
+                        None => None,
+                    },
+                }),
+                StepContent::WhileLoop(while_loop) => StepContent::WhileLoop(WhileLoop {
+                    condition: while_loop.condition.clone(),
+                    body: self.expand_steps(&while_loop.body, stack, next_id)?,
+                }),
+                StepContent::ForEach(for_each) => StepContent::ForEach(ForEach {
+                    var_name: for_each.var_name.clone(),
+                    iterable: for_each.iterable.clone(),
+                    body: self.expand_steps(&for_each.body, stack, next_id)?,
+                }),
+                StepContent::TryCatch(try_catch) => StepContent::TryCatch(TryCatch {
+                    try_steps: self.expand_steps(&try_catch.try_steps, stack, next_id)?,
+                    catch_variable: try_catch.catch_variable.clone(),
+                    catch_steps: self.expand_steps(&try_catch.catch_steps, stack, next_id)?,
+                }),
+                other => other.clone(),
+            };
+            expanded.push(Step { content, description: step.description.clone(), guard: step.guard.clone(), ..*step });
+        }
+        Ok(expanded)
+    }
+
+    fn inline_call(&self, command: &Command, stack: &mut Vec<String>, next_id: &mut u32) -> Result<Vec<Step>> {
+        let name = match command.arguments.first() {
+            Some(Expression { kind: ExpressionKind::StringLiteral(name), .. }) => name.clone(),
+            _ => return Err(anyhow!("run expects a workflow name as its first argument")),
+        };
+        if stack.contains(&name) {
+            return Err(anyhow!("Recursive workflow call: {} -> {}", stack.join(" -> "), name));
+        }
+        let callee = self.workflows
+            .iter()
+            .find(|workflow| workflow.name == name)
+            .ok_or_else(|| anyhow!("Workflow '{}' not found", name))?;
+
+        let arguments = &command.arguments[1..];
+        if arguments.len() > callee.variables.len() {
+            return Err(anyhow!(
+                "Workflow '{}' takes {} parameters but run passed {}",
+                name, callee.variables.len(), arguments.len()
+            ));
+        }
+        if let Some(unknown) = command.named_arguments
+            .keys()
+            .find(|argument| !callee.variables.iter().any(|variable| &variable.name == *argument))
+        {
+            return Err(anyhow!("Workflow '{}' has no parameter '{}'", name, unknown));
+        }
+
+        let mut steps: Vec<Step> = callee.variables
+            .iter()
+            .enumerate()
+            .map(|(index, variable)| Step {
+                id: 0,
+                content: StepContent::Declaration(VariableDeclaration {
+                    value: arguments.get(index)
+                        .or_else(|| command.named_arguments.get(&variable.name))
+                        .cloned()
+                        .unwrap_or_else(|| variable.value.clone()),
+                    ..variable.clone()
+                }),
+                line: 0,
+                span: Span::default(),
+                description: None,
+                guard: None,
+            })
+            .collect();
+
+        // Expand the callee's own calls in its numbering first, then move everything to fresh ids
+        stack.push(name);
+        let mut callee_next_id = max_step_id(&callee.steps) + 1;
+        let body = self.expand_steps(&callee.steps, stack, &mut callee_next_id);
+        stack.pop();
+
+        let mut ids = HashMap::new();
+        steps.extend(renumber_steps(&body?, &mut ids, next_id));
+        Ok(steps)
+    }
+}
+
+fn max_step_id(steps: &[Step]) -> u32 {
+    steps.iter()
+        .map(|step| {
+            let nested = match &step.content {
+                StepContent::Conditional(conditional) => max_step_id(&conditional.if_steps)
+                    .max(conditional.else_steps.as_deref().map(max_step_id).unwrap_or(0)),
+                StepContent::WhileLoop(while_loop) => max_step_id(&while_loop.body),
+                StepContent::ForEach(for_each) => max_step_id(&for_each.body),
+                StepContent::TryCatch(try_catch) => max_step_id(&try_catch.try_steps).max(max_step_id(&try_catch.catch_steps)),
+                _ => 0,
+            };
+            step.id.max(nested)
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Give every step a fresh id (else-if branches sharing an id keep sharing one)
+fn renumber_steps(steps: &[Step], ids: &mut HashMap<u32, u32>, next_id: &mut u32) -> Vec<Step> {
+    steps.iter()
+        .map(|step| {
+            let id = match step.id {
+                0 => 0,
+                old => *ids.entry(old).or_insert_with(|| {
+                    *next_id += 1;
+                    *next_id - 1
+                }),
+            };
+            let content = match &step.content {
+                StepContent::Command(command) => StepContent::Command(Command {
+                    name: command.name.clone(),
+                    arguments: command.arguments.iter().map(|argument| renumber_expression(argument, ids)).collect(),
+                    named_arguments: command.named_arguments
+                        .iter()
+                        .map(|(name, argument)| (name.clone(), renumber_expression(argument, ids)))
+                        .collect(),
+                    span: command.span,
+                }),
+                StepContent::Conditional(conditional) => StepContent::Conditional(ConditionalStatement {
+                    condition: renumber_expression(&conditional.condition, ids),
+                    if_steps: renumber_steps(&conditional.if_steps, ids, next_id),
+                    else_steps: conditional.else_steps.as_deref().map(|else_steps| renumber_steps(else_steps, ids, next_id)),
+                }),
+                StepContent::WhileLoop(while_loop) => StepContent::WhileLoop(WhileLoop {
+                    condition: renumber_expression(&while_loop.condition, ids),
+                    body: renumber_steps(&while_loop.body, ids, next_id),
+                }),
+                StepContent::ForEach(for_each) => StepContent::ForEach(ForEach {
+                    var_name: for_each.var_name.clone(),
+                    iterable: renumber_expression(&for_each.iterable, ids),
+                    body: renumber_steps(&for_each.body, ids, next_id),
+                }),
+                StepContent::TryCatch(try_catch) => StepContent::TryCatch(TryCatch {
+                    try_steps: renumber_steps(&try_catch.try_steps, ids, next_id),
+                    catch_variable: try_catch.catch_variable.clone(),
+                    catch_steps: renumber_steps(&try_catch.catch_steps, ids, next_id),
+                }),
+                StepContent::CallWorkflow(call) => StepContent::CallWorkflow(call.clone()),
+                StepContent::Return(return_statement) => StepContent::Return(ReturnStatement {
+                    value: return_statement.value.as_ref().map(|value| renumber_expression(value, ids)),
+                }),
+                StepContent::Assignment(assignment) => StepContent::Assignment(Assignment {
+                    name: assignment.name.clone(),
+                    value: renumber_expression(&assignment.value, ids),
+                }),
+                StepContent::Declaration(variable) => StepContent::Declaration(VariableDeclaration {
+                    value: renumber_expression(&variable.value, ids),
+                    ..variable.clone()
+                }),
+            };
+            let guard = step.guard.as_ref().map(|guard| renumber_expression(guard, ids));
+            Step { id, content, description: step.description.clone(), guard, ..*step }
+        })
+        .collect()
+}
+
+/// Point step references at the renumbered ids; references to steps outside the
+/// inlined body are left alone
+fn renumber_expression(expression: &Expression, ids: &HashMap<u32, u32>) -> Expression {
+    let renumbered = match &expression.kind {
+        ExpressionKind::StepReference { step_id, property } => {
+            Expression::step_reference(ids.get(step_id).copied().unwrap_or(*step_id), property.as_deref())
+        }
+        ExpressionKind::ArrayLiteral(elements) => {
+            Expression::array(elements.iter().map(|element| renumber_expression(element, ids)).collect())
+        }
+        ExpressionKind::BinaryExpression { left, operator, right } => {
+            Expression::binary(renumber_expression(left, ids), operator, renumber_expression(right, ids))
+        }
+        ExpressionKind::UnaryExpression { operator, operand } => {
+            Expression::unary(operator, renumber_expression(operand, ids))
+        }
+        ExpressionKind::PropertyAccess { object, property } => {
+            Expression::property_access(renumber_expression(object, ids), property)
+        }
+        ExpressionKind::Interpolation(parts) => Expression::from(ExpressionKind::Interpolation(
+            parts
+                .iter()
+                .map(|part| match part {
+                    InterpolationPart::Expression(expression) => InterpolationPart::Expression(renumber_expression(expression, ids)),
+                    literal => literal.clone(),
+                })
+                .collect(),
+        )),
+        _ => return expression.clone(),
+    };
+    renumbered.with_span(expression.span)
+}
@@ -1,5 +1,6 @@
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use std::collections::HashMap;
+use crate::error::DslError;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TokenType {
@@ -11,6 +12,16 @@ pub enum TokenType {
     Const,
     If,
     Else,
+    While,
+    For,
+    In,
+    Try,
+    Catch,
+    Call,
+    Return,
+    Fn,
+    OnError,
+    Prev,
     Print,
     Log,
     Fetch,
@@ -30,6 +41,9 @@ pub enum TokenType {
     
     // Operators
     Plus,
+    Minus,
+    Star,
+    Slash,
     Equal,
     EqualEqual,
     NotEqual,
@@ -37,6 +51,9 @@ pub enum TokenType {
     Less,
     GreaterEqual,
     LessEqual,
+    AndAnd,
+    OrOr,
+    Bang,
     Dot,
     
     // Punctuation
@@ -44,9 +61,12 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Colon,
     Semicolon,
     Comma,
+    At,
     
     // Special
     Eof,
@@ -58,15 +78,32 @@ pub struct Token {
     pub lexeme: String,
     pub literal: Option<String>,
     pub line: usize,
+    pub column: usize,
+    /// Text of the `///` doc comment lines directly before the token, if any
+    pub doc: Option<String>,
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, lexeme: &str, literal: Option<&str>, line: usize) -> Self {
+    pub fn new(token_type: TokenType, lexeme: &str, literal: Option<&str>, line: usize, column: usize) -> Self {
         Token {
             token_type,
             lexeme: lexeme.to_string(),
             literal: literal.map(|s| s.to_string()),
             line,
+            column,
+            doc: None,
+        }
+    }
+    
+    /// Line and column just past the token's last character
+    pub fn end(&self) -> (usize, usize) {
+        match self.lexeme.rfind('\n') {
+            // Only triple-quoted strings span lines
+            Some(index) => (
+                self.line + self.lexeme.matches('\n').count(),
+                self.lexeme[index + 1..].chars().count() + 1,
+            ),
+            None => (self.line, self.column + self.lexeme.chars().count()),
         }
     }
 }
@@ -77,7 +114,13 @@ pub struct Lexer {
     start: usize,
     current: usize,
     line: usize,
+    line_start: usize,
+    start_line: usize,
+    start_column: usize,
     keywords: HashMap<String, TokenType>,
+    case_insensitive_keywords: bool,
+    /// `///` lines waiting for the next token
+    doc_lines: Vec<String>,
 }
 
 impl Lexer {
@@ -90,6 +133,16 @@ impl Lexer {
         keywords.insert("const".to_string(), TokenType::Const);
         keywords.insert("if".to_string(), TokenType::If);
         keywords.insert("else".to_string(), TokenType::Else);
+        keywords.insert("while".to_string(), TokenType::While);
+        keywords.insert("for".to_string(), TokenType::For);
+        keywords.insert("in".to_string(), TokenType::In);
+        keywords.insert("try".to_string(), TokenType::Try);
+        keywords.insert("catch".to_string(), TokenType::Catch);
+        keywords.insert("call".to_string(), TokenType::Call);
+        keywords.insert("return".to_string(), TokenType::Return);
+        keywords.insert("fn".to_string(), TokenType::Fn);
+        keywords.insert("on_error".to_string(), TokenType::OnError);
+        keywords.insert("prev".to_string(), TokenType::Prev);
         keywords.insert("print".to_string(), TokenType::Print);
         keywords.insert("log".to_string(), TokenType::Log);
         keywords.insert("fetch".to_string(), TokenType::Fetch);
@@ -108,17 +161,31 @@ impl Lexer {
             start: 0,
             current: 0,
             line: 1,
+            line_start: 0,
+            start_line: 1,
+            start_column: 1,
             keywords,
+            case_insensitive_keywords: false,
+            doc_lines: Vec::new(),
         }
     }
     
+    /// Recognize keywords regardless of case, so `WORKFLOW` and `Step` match too
+    pub fn with_case_insensitive_keywords(mut self, enabled: bool) -> Self {
+        self.case_insensitive_keywords = enabled;
+        self
+    }
+    
     pub fn tokenize(&mut self) -> Result<Vec<Token>> {
         while !self.is_at_end() {
             self.start = self.current;
+            self.start_line = self.line;
+            self.start_column = self.current - self.line_start + 1;
             self.scan_token()?;
         }
         
-        self.tokens.push(Token::new(TokenType::Eof, "", None, self.line));
+        let column = self.current - self.line_start + 1;
+        self.tokens.push(Token::new(TokenType::Eof, "", None, self.line, column));
         Ok(self.tokens.clone())
     }
     
@@ -130,9 +197,12 @@ impl Lexer {
             ')' => self.add_token(TokenType::RightParen),
             '{' => self.add_token(TokenType::LeftBrace),
             '}' => self.add_token(TokenType::RightBrace),
+            '[' => self.add_token(TokenType::LeftBracket),
+            ']' => self.add_token(TokenType::RightBracket),
             ':' => self.add_token(TokenType::Colon),
             ';' => self.add_token(TokenType::Semicolon),
             ',' => self.add_token(TokenType::Comma),
+            '@' => self.add_token(TokenType::At),
             '.' => self.add_token(TokenType::Dot),
             '=' => {
                 if self.match_char('=') {
@@ -145,7 +215,21 @@ impl Lexer {
                 if self.match_char('=') {
                     self.add_token(TokenType::NotEqual);
                 } else {
-                    return Err(anyhow!("Unexpected character: !"));
+                    self.add_token(TokenType::Bang);
+                }
+            }
+            '&' => {
+                if self.match_char('&') {
+                    self.add_token(TokenType::AndAnd);
+                } else {
+                    return Err(self.unexpected_character('&'));
+                }
+            }
+            '|' => {
+                if self.match_char('|') {
+                    self.add_token(TokenType::OrOr);
+                } else {
+                    return Err(self.unexpected_character('|'));
                 }
             }
             '<' => {
@@ -163,16 +247,36 @@ impl Lexer {
                 }
             }
             '+' => self.add_token(TokenType::Plus),
+            '-' => self.add_token(TokenType::Minus),
+            '*' => self.add_token(TokenType::Star),
+            '/' => {
+                if self.match_char('/') {
+                    // Line comments produce no tokens, so they are invisible to the parser;
+                    // `///` doc comments ride along on the token that follows them
+                    let is_doc = self.peek() == '/' && self.peek_next() != '/';
+                    while self.peek() != '\n' && !self.is_at_end() {
+                        self.advance();
+                    }
+                    if is_doc {
+                        let text = self.source[self.start + 3..self.current].iter().collect::<String>();
+                        self.doc_lines.push(text.trim().to_string());
+                    }
+                } else {
+                    self.add_token(TokenType::Slash);
+                }
+            }
+            '"' if self.peek() == '"' && self.peek_next() == '"' => self.triple_quoted_string()?,
             '"' => self.string()?,
             '\'' => self.string()?,
-            c if c.is_ascii_digit() => self.number(),
+            c if c.is_ascii_digit() => self.number()?,
             c if c.is_ascii_alphabetic() || c == '_' => self.identifier(),
             c if c.is_whitespace() => {
                 if c == '\n' {
                     self.line += 1;
+                    self.line_start = self.current;
                 }
             }
-            _ => return Err(anyhow!("Unexpected character: {}", c)),
+            _ => return Err(self.unexpected_character(c)),
         }
         
         Ok(())
@@ -184,12 +288,13 @@ impl Lexer {
         while self.peek() != quote && !self.is_at_end() {
             if self.peek() == '\n' {
                 self.line += 1;
+                self.line_start = self.current + 1;
             }
             self.advance();
         }
         
         if self.is_at_end() {
-            return Err(anyhow!("Unterminated string"));
+            return Err(DslError::UnterminatedString { line: self.start_line, column: self.start_column }.into());
         }
         
         // Consume the closing quote
@@ -204,7 +309,58 @@ impl Lexer {
         Ok(())
     }
     
-    fn number(&mut self) {
+    /// `"""..."""` is read verbatim, newlines and quotes included. The only escape
+    /// is `\"""`, which stands for a literal `"""` inside the string.
+    fn triple_quoted_string(&mut self) -> Result<()> {
+        // Consume the rest of the opening quotes
+        self.advance();
+        self.advance();
+        
+        let mut value = String::new();
+        loop {
+            if self.is_at_end() {
+                return Err(DslError::UnterminatedString { line: self.start_line, column: self.start_column }.into());
+            }
+            if self.source[self.current..].starts_with(&['"', '"', '"']) {
+                break;
+            }
+            if self.source[self.current..].starts_with(&['\\', '"', '"', '"']) {
+                self.current += 4;
+                value.push_str("\"\"\"");
+                continue;
+            }
+            
+            let c = self.advance();
+            if c == '\n' {
+                self.line += 1;
+                self.line_start = self.current;
+            }
+            value.push(c);
+        }
+        
+        // Consume the closing quotes
+        self.current += 3;
+        self.add_token_with_literal(TokenType::String, &value);
+        Ok(())
+    }
+    
+    /// Decimal numbers with an optional fraction and exponent (`1.5e-3`), or hex
+    /// integers (`0xFF`). The literal holds the value in decimal for the parser.
+    fn number(&mut self) -> Result<()> {
+        if self.source[self.start] == '0' && matches!(self.peek(), 'x' | 'X') {
+            self.advance(); // consume the "x"
+            let mut value = 0.0;
+            while let Some(digit) = self.peek().to_digit(16) {
+                value = value * 16.0 + digit as f64;
+                self.advance();
+            }
+            if self.current == self.start + 2 {
+                return Err(self.unexpected_character(self.source[self.start + 1]));
+            }
+            self.add_token_with_literal(TokenType::Number, &value.to_string());
+            return Ok(());
+        }
+        
         while self.peek().is_ascii_digit() {
             self.advance();
         }
@@ -218,11 +374,23 @@ impl Lexer {
             }
         }
         
+        // An exponent needs digits, so `2em` stays a number followed by a name
+        if matches!(self.peek(), 'e' | 'E') {
+            let digits_at = if matches!(self.peek_next(), '+' | '-') { self.current + 2 } else { self.current + 1 };
+            if self.source.get(digits_at).is_some_and(char::is_ascii_digit) {
+                self.current = digits_at;
+                while self.peek().is_ascii_digit() {
+                    self.advance();
+                }
+            }
+        }
+        
         let value = self.source[self.start..self.current]
             .iter()
             .collect::<String>();
         
         self.add_token_with_literal(TokenType::Number, &value);
+        Ok(())
     }
     
     fn identifier(&mut self) {
@@ -234,13 +402,20 @@ impl Lexer {
             .iter()
             .collect::<String>();
         
-        let token_type = self.keywords.get(&text)
-            .cloned()
-            .unwrap_or(TokenType::Identifier);
+        let keyword = if self.case_insensitive_keywords {
+            self.keywords.get(&text.to_lowercase())
+        } else {
+            self.keywords.get(&text)
+        };
+        let token_type = keyword.cloned().unwrap_or(TokenType::Identifier);
         
         self.add_token(token_type);
     }
     
+    fn unexpected_character(&self, character: char) -> anyhow::Error {
+        DslError::UnexpectedCharacter { character, line: self.start_line, column: self.start_column }.into()
+    }
+    
     fn advance(&mut self) -> char {
         let c = self.source[self.current];
         self.current += 1;
@@ -280,13 +455,20 @@ impl Lexer {
         let text = self.source[self.start..self.current]
             .iter()
             .collect::<String>();
-        self.tokens.push(Token::new(token_type, &text, None, self.line));
+        self.push_token(Token::new(token_type, &text, None, self.start_line, self.start_column));
     }
     
     fn add_token_with_literal(&mut self, token_type: TokenType, literal: &str) {
         let text = self.source[self.start..self.current]
             .iter()
             .collect::<String>();
-        self.tokens.push(Token::new(token_type, &text, Some(literal), self.line));
+        self.push_token(Token::new(token_type, &text, Some(literal), self.start_line, self.start_column));
+    }
+    
+    fn push_token(&mut self, mut token: Token) {
+        if !self.doc_lines.is_empty() {
+            token.doc = Some(std::mem::take(&mut self.doc_lines).join(" "));
+        }
+        self.tokens.push(token);
     }
 } 
\ No newline at end of file
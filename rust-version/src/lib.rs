@@ -2,11 +2,46 @@ pub mod ast;
 pub mod lexer;
 pub mod parser;
 pub mod executor;
+pub mod analyzer;
+pub mod incremental;
+pub mod value;
+pub mod error;
+pub mod rename;
+pub mod inline;
+pub mod include;
+pub mod semantic;
+pub mod compiled;
+pub mod validate;
+pub mod diff;
+pub mod policy;
+pub mod visit;
+pub mod human;
+pub mod printer;
+pub mod graph;
+pub mod prometheus;
+pub mod symbolic;
+#[cfg(feature = "tokio")]
+pub mod async_executor;
 
 pub use ast::*;
 pub use lexer::*;
 pub use parser::*;
 pub use executor::*;
+pub use analyzer::*;
+pub use incremental::*;
+pub use value::*;
+pub use error::*;
+pub use semantic::*;
+pub use compiled::*;
+pub use diff::*;
+pub use validate::*;
+pub use policy::*;
+pub use visit::*;
+pub use human::*;
+pub use graph::*;
+pub use symbolic::*;
+#[cfg(feature = "tokio")]
+pub use async_executor::*;
 
 use anyhow::Result;
 
@@ -52,6 +87,48 @@ impl WasmDSLExecutor {
         }
     }
     
+    #[wasm_bindgen]
+    pub fn execute_to_json(&self, dsl_code: &str) -> Result<String, JsValue> {
+        console_log!("🦀 Executing DSL code for a report: {}", dsl_code);
+        
+        let report = run_dsl_with_report(dsl_code).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let json = serde_json::to_string(&report).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(json)
+    }
+    
+    /// Run the code, calling `callback` with every `ExecutionEvent` as a JS object
+    /// tagged by its `type`. JS functions can't move into the executor, so the events
+    /// are handed over in order once the run ends, also when it fails.
+    #[wasm_bindgen]
+    pub fn execute_with_events(&self, dsl_code: &str, callback: &js_sys::Function) -> Result<String, JsValue> {
+        console_log!("🦀 Executing DSL code with events: {}", dsl_code);
+        
+        let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let collected = events.clone();
+        let result = parse_dsl(dsl_code).and_then(|ast| {
+            executor::Executor::new()
+                .with_verbose(false)
+                .with_event_handler(move |event| collected.lock().map(|mut events| events.push(event)).unwrap_or_default())
+                .execute(&ast)
+        });
+        for event in events.lock().map(|events| events.clone()).unwrap_or_default() {
+            let event = serde_wasm_bindgen::to_value(&event).map_err(|e| JsValue::from_str(&e.to_string()))?;
+            callback.call1(&JsValue::NULL, &event)?;
+        }
+        let report = result.map_err(|e| JsValue::from_str(&e.to_string()))?;
+        serde_json::to_string(&report).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+    
+    /// Run a program serialized by `parse_to_json`, returning the report as JSON
+    #[wasm_bindgen]
+    pub fn execute_json(&self, json: &str) -> Result<String, JsValue> {
+        console_log!("🦀 Executing program JSON");
+        
+        let report = run_program_json(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let json = serde_json::to_string(&report).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(json)
+    }
+    
     #[wasm_bindgen]
     pub fn parse_to_json(&self, dsl_code: &str) -> Result<String, JsValue> {
         console_log!("🦀 Parsing DSL to JSON: {}", dsl_code);
@@ -61,14 +138,18 @@ impl WasmDSLExecutor {
         Ok(json)
     }
     
+    /// JSON `{"valid": bool, "errors": [message, ...]}` with the parse error, or
+    /// every undefined variable when the code parses
     #[wasm_bindgen]
-    pub fn validate_dsl(&self, dsl_code: &str) -> Result<bool, JsValue> {
+    pub fn validate_dsl(&self, dsl_code: &str) -> Result<String, JsValue> {
         console_log!("🦀 Validating DSL: {}", dsl_code);
         
-        match parse_dsl(dsl_code) {
-            Ok(_) => Ok(true),
-            Err(_) => Ok(false),
-        }
+        let errors: Vec<String> = match parse_dsl(dsl_code) {
+            Ok(ast) => validate_program(&ast).iter().map(|error| error.to_string()).collect(),
+            Err(e) => vec![e.to_string()],
+        };
+        let json = serde_json::json!({ "valid": errors.is_empty(), "errors": errors });
+        Ok(json.to_string())
     }
     
     #[wasm_bindgen]
@@ -79,8 +160,12 @@ impl WasmDSLExecutor {
             "send_email".to_string(),
             "analyze".to_string(),
             "filter".to_string(),
+            "sort".to_string(),
+            "pick".to_string(),
+            "merge".to_string(),
             "transform".to_string(),
             "store".to_string(),
+            "load".to_string(),
             "notify".to_string(),
             "print".to_string(),
             "log".to_string(),
@@ -92,36 +177,30 @@ impl WasmDSLExecutor {
         ]
     }
     
+    /// Graphviz DOT for the control flow of every workflow, one `digraph` each
     #[wasm_bindgen]
-    pub fn generate_human_steps(&self, dsl_code: &str) -> Result<String, JsValue> {
-        console_log!("🦀 Generating human steps for: {}", dsl_code);
+    pub fn workflow_to_dot(&self, dsl_code: &str) -> Result<String, JsValue> {
+        console_log!("🦀 Exporting DOT for: {}", dsl_code);
         
         let ast = parse_dsl(dsl_code).map_err(|e| JsValue::from_str(&e.to_string()))?;
-        let mut steps = Vec::new();
+        Ok(ast.workflows.iter().map(workflow_to_dot).collect::<Vec<_>>().join("\n"))
+    }
+    
+    /// Mermaid flowcharts for every workflow, one `flowchart TD` each
+    #[wasm_bindgen]
+    pub fn workflow_to_mermaid(&self, dsl_code: &str) -> Result<String, JsValue> {
+        console_log!("🦀 Exporting Mermaid for: {}", dsl_code);
         
-        for workflow in &ast.workflows {
-            for step in &workflow.steps {
-                match &step.content {
-                    StepContent::Command(command) => {
-                        let human_desc = match command.name.as_str() {
-                            "input" => format!("Step {}: Collect user input", step.id),
-                            "generate" => format!("Step {}: Generate AI content", step.id),
-                            "output" => format!("Step {}: Export results", step.id),
-                            "fetch" => format!("Step {}: Fetch data from URL", step.id),
-                            "transform" => format!("Step {}: Transform data", step.id),
-                            "validate" => format!("Step {}: Validate input", step.id),
-                            _ => format!("Step {}: Execute {}", step.id, command.name),
-                        };
-                        steps.push(human_desc);
-                    }
-                    StepContent::Conditional(_) => {
-                        steps.push(format!("Step {}: Conditional logic", step.id));
-                    }
-                }
-            }
-        }
+        let ast = parse_dsl(dsl_code).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(ast.workflows.iter().map(workflow_to_mermaid).collect::<Vec<_>>().join("\n"))
+    }
+    
+    #[wasm_bindgen]
+    pub fn generate_human_steps(&self, dsl_code: &str) -> Result<String, JsValue> {
+        console_log!("🦀 Generating human steps for: {}", dsl_code);
         
-        Ok(steps.join("\n"))
+        let ast = parse_dsl(dsl_code).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(human_steps(&ast).join("\n"))
     }
 }
 
@@ -140,6 +219,43 @@ pub fn run_dsl(dsl_code: &str) -> Result<()> {
     Ok(())
 }
 
+/// Parse and execute a DSL program without printing, returning what it produced
+pub fn run_dsl_with_report(dsl_code: &str) -> Result<ExecutionReport> {
+    run_program(&parse_dsl(dsl_code)?)
+}
+
+/// Execute an already parsed program without printing, e.g. one deserialized from JSON
+pub fn run_program(program: &Program) -> Result<ExecutionReport> {
+    executor::Executor::new().with_verbose(false).execute(program)
+}
+
+/// Deserialize a program from the JSON `parse_to_json` produces and execute it
+pub fn run_program_json(json: &str) -> Result<ExecutionReport> {
+    let program: Program = serde_json::from_str(json)
+        .map_err(|e| anyhow::anyhow!("Invalid program JSON: {}", e))?;
+    run_program(&program)
+}
+
+/// What `run_dsl_lenient` ran, along with the errors of the items it had to skip
+#[derive(Debug)]
+pub struct LenientRun {
+    pub report: ExecutionReport,
+    pub parse_errors: Vec<anyhow::Error>,
+}
+
+/// Execute every workflow that parses, skipping broken ones instead of aborting.
+/// Runtime failures are recorded in `report.failed_workflows` the same way; only
+/// a lexer error, which leaves nothing to parse, fails the whole run.
+pub fn run_dsl_lenient(dsl_code: &str) -> Result<LenientRun> {
+    let tokens = lexer::Lexer::new(dsl_code).tokenize()?;
+    let (program, parse_errors) = parser::Parser::new(tokens).parse_partial();
+    let report = executor::Executor::new()
+        .with_verbose(false)
+        .with_continue_workflows_on_error(true)
+        .execute(&program)?;
+    Ok(LenientRun { report, parse_errors })
+}
+
 /// Parse DSL code into AST without execution
 pub fn parse_dsl(dsl_code: &str) -> Result<Program> {
     let tokens = lexer::Lexer::new(dsl_code).tokenize()?;
@@ -147,6 +263,11 @@ pub fn parse_dsl(dsl_code: &str) -> Result<Program> {
     Ok(ast)
 }
 
+/// Parse DSL code into a program that can be executed many times
+pub fn compile_dsl(dsl_code: &str) -> Result<CompiledProgram> {
+    Ok(CompiledProgram::new(parse_dsl(dsl_code)?))
+}
+
 /// Tokenize DSL code
 pub fn tokenize_dsl(dsl_code: &str) -> Result<Vec<Token>> {
     lexer::Lexer::new(dsl_code).tokenize()
@@ -1,12 +1,48 @@
-mod lexer;
-mod parser;
-mod executor;
-mod ast;
 mod test_examples;
 
 use anyhow::Result;
+use std::env;
+use std::fs;
+use std::time::{Duration, Instant};
+use trademinutes_dsl::{executor, lexer, parser};
 
 fn main() -> Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let show_timings = args.iter().any(|arg| arg == "--time");
+    let dry_run = args.iter().any(|arg| arg == "--dry-run");
+    let auto_step_ids = args.iter().any(|arg| arg == "--auto-step-ids");
+    let profile = args.iter().any(|arg| arg == "--profile");
+    
+    // Run a DSL file when one is given instead of the built-in demo
+    if let Some(path) = args.iter().find(|arg| !arg.starts_with("--")) {
+        let dsl_code = fs::read_to_string(path)?;
+        let tokens = lexer::Lexer::new(&dsl_code).tokenize()?;
+        let ast = parser::Parser::new(tokens).with_auto_step_ids(auto_step_ids).parse()?;
+        
+        let mut executor = executor::Executor::new().with_dry_run(dry_run).with_profiling(profile);
+        let started = Instant::now();
+        let report = executor.execute(&ast)?;
+        let total = started.elapsed();
+        
+        if profile {
+            let folded_path = format!("{}.folded", path);
+            fs::write(&folded_path, executor.folded_stacks())?;
+            println!("\n🔥 Folded stacks written to {} (e.g. flamegraph.pl {} > flame.svg)", folded_path, folded_path);
+        }
+        
+        if dry_run {
+            println!("\n🧪 Planned actions:");
+            for action in &report.planned_actions {
+                let arguments: Vec<String> = action.arguments.iter().map(|value| value.to_string()).collect();
+                println!("  Step {}: {}({})", action.step_id, action.command, arguments.join(", "));
+            }
+        }
+        if show_timings {
+            print_timings(&executor, total);
+        }
+        return Ok(());
+    }
+    
     println!("🚀 TradeMinutes DSL Parser (Rust Version)");
     println!("===========================================");
     
@@ -41,14 +77,29 @@ workflow "AI Content Generator" {
     
     // Execute
     let mut executor = executor::Executor::new();
+    let started = Instant::now();
     executor.execute(&ast)?;
+    let total = started.elapsed();
     
     println!("\n✅ Execution completed!");
     
+    if show_timings {
+        print_timings(&executor, total);
+    }
+    
     // Run additional examples
     test_examples::run_examples();
     test_examples::test_tokenization();
     test_examples::test_parsing();
     
     Ok(())
-} 
\ No newline at end of file
+}
+
+fn print_timings(executor: &executor::Executor, total: Duration) {
+    println!("\n⏱️  Step timings:");
+    println!("  {:<20} {:<8} {:>12}", "Workflow", "Step", "Duration");
+    for (workflow, step_id, duration) in executor.step_timings() {
+        println!("  {:<20} {:<8} {:>12}", workflow, step_id, format!("{:.3}ms", duration.as_secs_f64() * 1000.0));
+    }
+    println!("  {:<20} {:<8} {:>12}", "Total", "", format!("{:.3}ms", total.as_secs_f64() * 1000.0));
+}
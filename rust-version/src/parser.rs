@@ -1,51 +1,165 @@
 use anyhow::{anyhow, Result};
 use crate::ast::*;
-use crate::lexer::{Token, TokenType};
+use crate::error::DslError;
+use crate::lexer::{Lexer, Token, TokenType};
+use indexmap::IndexMap;
+
+/// A single top-level item; const blocks produce several declarations
+#[derive(Debug, Clone)]
+pub enum TopLevelItem {
+    Workflow(Workflow),
+    Variables(Vec<VariableDeclaration>),
+    Function(FunctionDeclaration),
+}
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    auto_step_ids: bool,
+    /// Highest step id so far in the workflow being parsed
+    last_step_id: u32,
+    /// Ids handed out to `step:` in the workflow being parsed
+    assigned_step_ids: Vec<u32>,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Parser { tokens, current: 0 }
+        Parser { tokens, current: 0, auto_step_ids: false, last_step_id: 0, assigned_step_ids: Vec::new() }
+    }
+    
+    /// Accept `step: ...` without an id and number it one past the highest id so far
+    /// in its workflow. An explicit id that repeats an assigned one is an error.
+    pub fn with_auto_step_ids(mut self, auto_step_ids: bool) -> Self {
+        self.auto_step_ids = auto_step_ids;
+        self
     }
     
     pub fn parse(&mut self) -> Result<Program> {
         let mut workflows = Vec::new();
         let mut variables = Vec::new();
+        let mut functions = Vec::new();
         
         while !self.is_at_end() {
-            match self.peek().token_type {
-                TokenType::Workflow => {
-                    workflows.push(self.parse_workflow()?);
-                }
-                TokenType::Let | TokenType::Var | TokenType::Const => {
-                    variables.push(self.parse_variable_declaration()?);
-                }
-                _ => {
-                    return Err(anyhow!("Expected workflow or variable declaration"));
+            match self.parse_item()? {
+                TopLevelItem::Workflow(workflow) => workflows.push(workflow),
+                TopLevelItem::Variables(declarations) => variables.extend(declarations),
+                TopLevelItem::Function(function) => functions.push(function),
+            }
+        }
+        
+        let mut program = Program { workflows, variables, functions };
+        program.resolve_includes()?;
+        Ok(program)
+    }
+    
+    /// Parse as much as possible: a workflow or declaration that fails to parse is
+    /// skipped up to the next `workflow` keyword and its error collected, so the
+    /// returned program holds every item that parsed
+    pub fn parse_partial(&mut self) -> (Program, Vec<anyhow::Error>) {
+        let mut program = Program { workflows: Vec::new(), variables: Vec::new(), functions: Vec::new() };
+        let mut errors = Vec::new();
+        
+        while !self.is_at_end() {
+            let start = self.current;
+            match self.parse_item() {
+                Ok(TopLevelItem::Workflow(workflow)) => program.workflows.push(workflow),
+                Ok(TopLevelItem::Variables(declarations)) => program.variables.extend(declarations),
+                Ok(TopLevelItem::Function(function)) => program.functions.push(function),
+                Err(error) => {
+                    errors.push(error);
+                    // `workflow`, `fn` and attributes can't appear inside an item, so they are safe places to resume
+                    self.current = start + 1;
+                    while !self.is_at_end() && ![TokenType::Workflow, TokenType::At, TokenType::Fn].contains(&self.peek().token_type) {
+                        self.advance();
+                    }
                 }
             }
         }
         
-        Ok(Program { workflows, variables })
+        if let Err(error) = program.resolve_includes() {
+            errors.push(error);
+        }
+        (program, errors)
+    }
+    
+    /// Parse the next workflow, variable declaration, const block or function
+    pub fn parse_item(&mut self) -> Result<TopLevelItem> {
+        match self.peek().token_type {
+            TokenType::Workflow | TokenType::At => Ok(TopLevelItem::Workflow(self.parse_workflow()?)),
+            TokenType::Fn => Ok(TopLevelItem::Function(self.parse_function()?)),
+            TokenType::Const if self.check_next(TokenType::LeftBrace) => {
+                Ok(TopLevelItem::Variables(self.parse_const_block()?))
+            }
+            TokenType::Let | TokenType::Var | TokenType::Const => {
+                Ok(TopLevelItem::Variables(vec![self.parse_variable_declaration()?]))
+            }
+            _ => Err(self.error_at_current("Expected workflow, variable declaration or function")),
+        }
+    }
+    
+    /// Whether every token up to `Eof` has been consumed
+    pub fn at_end(&self) -> bool {
+        self.is_at_end()
+    }
+    
+    /// The next token to be consumed
+    pub fn current_token(&self) -> &Token {
+        self.peek()
+    }
+    
+    /// The most recently consumed token
+    pub fn previous_token(&self) -> Option<&Token> {
+        self.current.checked_sub(1).map(|index| &self.tokens[index])
     }
     
     fn parse_workflow(&mut self) -> Result<Workflow> {
+        let mut schedule = None;
+        // Attributes such as `@schedule("0 9 * * *")` come before the workflow they describe
+        while self.match_token(&[TokenType::At]) {
+            if !self.check(TokenType::Identifier) || self.peek().lexeme != "schedule" {
+                return Err(self.error_at_current("Expected workflow attribute 'schedule' after '@'"));
+            }
+            if schedule.is_some() {
+                return Err(self.error_at_current("Duplicate '@schedule' attribute"));
+            }
+            self.advance(); // consume 'schedule'
+            self.consume(TokenType::LeftParen, "Expected '(' after '@schedule'")?;
+            schedule = Some(self.consume_string("Expected cron expression")?);
+            self.consume(TokenType::RightParen, "Expected ')' after cron expression")?;
+        }
+        
         self.consume(TokenType::Workflow, "Expected 'workflow'")?;
         
         let name = self.consume_string("Expected workflow name")?;
         
+        // `includes "Base", "Config"` pulls in the steps of shared workflows
+        let mut includes = Vec::new();
+        if self.check(TokenType::Identifier) && self.peek().lexeme == "includes" {
+            self.advance(); // consume 'includes'
+            loop {
+                includes.push(self.consume_string("Expected name of the included workflow")?);
+                if !self.match_token(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        
         self.consume(TokenType::LeftBrace, "Expected '{' after workflow name")?;
+        self.last_step_id = 0;
+        self.assigned_step_ids.clear();
         
         let mut variables = Vec::new();
         let mut steps = Vec::new();
+        let mut on_error = None;
         while !self.check(TokenType::RightBrace) && !self.is_at_end() {
             // Handle variable declarations inside workflows
             if self.check(TokenType::Let) || self.check(TokenType::Var) || self.check(TokenType::Const) {
                 variables.push(self.parse_variable_declaration()?);
+            } else if self.check(TokenType::OnError) {
+                if on_error.is_some() {
+                    return Err(self.error_at_current("Duplicate 'on_error' handler"));
+                }
+                on_error = Some(self.parse_error_handler()?);
             } else {
                 steps.push(self.parse_step()?);
             }
@@ -53,54 +167,146 @@ impl Parser {
         
         self.consume(TokenType::RightBrace, "Expected '}' after workflow body")?;
         
-        Ok(Workflow { name, variables, steps })
+        Ok(Workflow { name, variables, steps, on_error, schedule, includes })
+    }
+    
+    /// Parse `on_error { ... }` or `on_error(name) { ... }`; the error is bound to `error` by default
+    fn parse_error_handler(&mut self) -> Result<ErrorHandler> {
+        self.consume(TokenType::OnError, "Expected 'on_error'")?;
+        
+        let variable = if self.match_token(&[TokenType::LeftParen]) {
+            let name = self.consume_identifier("Expected error variable name")?;
+            self.consume(TokenType::RightParen, "Expected ')' after error variable name")?;
+            name
+        } else {
+            "error".to_string()
+        };
+        
+        self.consume(TokenType::LeftBrace, "Expected '{' after 'on_error'")?;
+        let mut steps = Vec::new();
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            steps.push(self.parse_step()?);
+        }
+        self.consume(TokenType::RightBrace, "Expected '}' after on_error block")?;
+        
+        Ok(ErrorHandler { variable, steps })
     }
     
     fn parse_step(&mut self) -> Result<Step> {
+        let start = self.position();
+        let line = start.0;
+        let description = self.peek().doc.clone();
+        
+        // Bare `name = value` reassigns an existing variable
+        if self.check(TokenType::Identifier) && self.check_next(TokenType::Equal) {
+            let content = StepContent::Assignment(self.parse_assignment()?);
+            return Ok(Step { id: 0, content, line, span: self.span_from(start), description, guard: None });
+        }
+        if self.check(TokenType::Let) || self.check(TokenType::Var) || self.check(TokenType::Const) {
+            let content = StepContent::Declaration(self.parse_variable_declaration()?);
+            return Ok(Step { id: 0, content, line, span: self.span_from(start), description, guard: None });
+        }
+        if self.check(TokenType::Return) {
+            let content = StepContent::Return(self.parse_return()?);
+            return Ok(Step { id: 0, content, line, span: self.span_from(start), description, guard: None });
+        }
+        
         self.consume(TokenType::Step, "Expected 'step'")?;
         
-        let id = self.consume_number("Expected step number")? as u32;
+        let id = if self.auto_step_ids && self.check(TokenType::Colon) {
+            self.last_step_id += 1;
+            self.assigned_step_ids.push(self.last_step_id);
+            self.last_step_id
+        } else {
+            let id_token = self.peek().clone();
+            let id = self.consume_number("Expected step number")? as u32;
+            if self.assigned_step_ids.contains(&id) {
+                return Err(DslError::UnexpectedToken {
+                    expected: format!("Step {} was already assigned to an auto-numbered step; expected an unused id", id),
+                    found: format!("'{}'", id_token.lexeme),
+                    line: id_token.line,
+                    column: id_token.column,
+                }
+                .into());
+            }
+            self.last_step_id = self.last_step_id.max(id);
+            id
+        };
         
         self.consume(TokenType::Colon, "Expected ':' after step number")?;
         
         let content = if self.check(TokenType::If) {
-            StepContent::Conditional(self.parse_conditional_statement()?)
+            StepContent::Conditional(self.parse_conditional_statement(id)?)
+        } else if self.check(TokenType::While) {
+            StepContent::WhileLoop(self.parse_while_loop()?)
+        } else if self.check(TokenType::For) {
+            StepContent::ForEach(self.parse_for_each()?)
+        } else if self.check(TokenType::Try) {
+            StepContent::TryCatch(self.parse_try_catch()?)
+        } else if self.match_token(&[TokenType::Call]) {
+            let name = self.consume_string("Expected workflow name after 'call'")?;
+            StepContent::CallWorkflow(CallWorkflow { name })
+        } else if self.check(TokenType::Identifier) && self.check_next(TokenType::Equal) {
+            StepContent::Assignment(self.parse_assignment()?)
         } else {
             StepContent::Command(self.parse_command()?)
         };
         
-        Ok(Step { id, content })
+        // `when (condition)` after the step guards it
+        let guard = if self.check(TokenType::Identifier) && self.peek().lexeme == "when" && self.check_next(TokenType::LeftParen) {
+            self.advance(); // consume 'when'
+            self.advance(); // consume '('
+            let condition = self.parse_expression()?;
+            self.consume(TokenType::RightParen, "Expected ')' after guard condition")?;
+            Some(condition)
+        } else {
+            None
+        };
+        
+        Ok(Step { id, content, line, span: self.span_from(start), description, guard })
     }
     
     fn parse_command(&mut self) -> Result<Command> {
-        let name = match self.peek().token_type {
-            TokenType::Identifier => self.consume_identifier("Expected command name")?,
-            TokenType::Print => { self.advance(); "print".to_string() },
-            TokenType::Log => { self.advance(); "log".to_string() },
-            TokenType::Fetch => { self.advance(); "fetch".to_string() },
-            TokenType::SendEmail => { self.advance(); "send_email".to_string() },
-            TokenType::Notify => { self.advance(); "notify".to_string() },
-            TokenType::Input => { self.advance(); "input".to_string() },
-            TokenType::Generate => { self.advance(); "generate".to_string() },
-            TokenType::Output => { self.advance(); "output".to_string() },
-            TokenType::Transform => { self.advance(); "transform".to_string() },
-            TokenType::Validate => { self.advance(); "validate".to_string() },
-            _ => return Err(anyhow!("Expected command name")),
+        let start = self.position();
+        let name = match command_keyword(self.peek().token_type) {
+            Some(name) => {
+                self.advance();
+                name.to_string()
+            }
+            None => self.consume_identifier("Expected command name")?,
         };
         
-        let arguments = if self.check(TokenType::LeftParen) {
-            self.consume(TokenType::LeftParen, "Expected '('")?;
-            let args = self.parse_expression_list()?;
+        let mut arguments = Vec::new();
+        let mut named_arguments = IndexMap::new();
+        if self.match_token(&[TokenType::LeftParen]) {
+            if !self.check(TokenType::RightParen) {
+                loop {
+                    if self.check(TokenType::Identifier) && self.check_next(TokenType::Colon) {
+                        let argument_name = self.peek().lexeme.clone();
+                        if named_arguments.contains_key(&argument_name) {
+                            return Err(self.error_at_current(&format!("Duplicate argument '{}'", argument_name)));
+                        }
+                        self.advance(); // consume the name
+                        self.advance(); // consume ':'
+                        named_arguments.insert(argument_name, self.parse_expression()?);
+                    } else if named_arguments.is_empty() {
+                        arguments.push(self.parse_expression()?);
+                    } else {
+                        return Err(self.error_at_current("Positional arguments must come before named arguments"));
+                    }
+                    
+                    if !self.match_token(&[TokenType::Comma]) {
+                        break;
+                    }
+                }
+            }
             self.consume(TokenType::RightParen, "Expected ')'")?;
-            args
-        } else {
-            Vec::new()
-        };
+        }
         
-        Ok(Command { name, arguments })
+        Ok(Command { name, arguments, named_arguments, span: self.span_from(start) })
     }
     
-    fn parse_conditional_statement(&mut self) -> Result<ConditionalStatement> {
+    fn parse_conditional_statement(&mut self, step_id: u32) -> Result<ConditionalStatement> {
         self.consume(TokenType::If, "Expected 'if'")?;
         
         self.consume(TokenType::LeftParen, "Expected '(' after 'if'")?;
@@ -114,7 +320,15 @@ impl Parser {
         }
         self.consume(TokenType::RightBrace, "Expected '}' after if block")?;
         
-        let else_steps = if self.check(TokenType::Else) {
+        let else_steps = if self.check(TokenType::Else) && self.check_next(TokenType::If) {
+            let start = self.position();
+            self.advance(); // consume 'else'
+            
+            // `else if` becomes a nested conditional that is the only else step
+            let nested = self.parse_conditional_statement(step_id)?;
+            let span = self.span_from(start);
+            Some(vec![Step { id: step_id, content: StepContent::Conditional(nested), line: start.0, span, description: None, guard: None }])
+        } else if self.check(TokenType::Else) {
             self.advance(); // consume 'else'
             self.consume(TokenType::LeftBrace, "Expected '{' after 'else'")?;
             let mut steps = Vec::new();
@@ -134,12 +348,97 @@ impl Parser {
         })
     }
     
+    fn parse_while_loop(&mut self) -> Result<WhileLoop> {
+        self.consume(TokenType::While, "Expected 'while'")?;
+        
+        self.consume(TokenType::LeftParen, "Expected '(' after 'while'")?;
+        let condition = self.parse_expression()?;
+        self.consume(TokenType::RightParen, "Expected ')' after condition")?;
+        
+        self.consume(TokenType::LeftBrace, "Expected '{' after condition")?;
+        let mut body = Vec::new();
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            body.push(self.parse_step()?);
+        }
+        self.consume(TokenType::RightBrace, "Expected '}' after while body")?;
+        
+        Ok(WhileLoop { condition, body })
+    }
+    
+    fn parse_for_each(&mut self) -> Result<ForEach> {
+        self.consume(TokenType::For, "Expected 'for'")?;
+        let var_name = self.consume_identifier("Expected loop variable name after 'for'")?;
+        self.consume(TokenType::In, "Expected 'in' after loop variable")?;
+        let iterable = self.parse_expression()?;
+        
+        self.consume(TokenType::LeftBrace, "Expected '{' after for-each iterable")?;
+        let mut body = Vec::new();
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            body.push(self.parse_step()?);
+        }
+        self.consume(TokenType::RightBrace, "Expected '}' after for-each body")?;
+        
+        Ok(ForEach { var_name, iterable, body })
+    }
+    
+    /// Parse `try { ... } catch { ... }` or `... catch(name) { ... }`; the error is bound to `error` by default
+    fn parse_try_catch(&mut self) -> Result<TryCatch> {
+        self.consume(TokenType::Try, "Expected 'try'")?;
+        
+        self.consume(TokenType::LeftBrace, "Expected '{' after 'try'")?;
+        let mut try_steps = Vec::new();
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            try_steps.push(self.parse_step()?);
+        }
+        self.consume(TokenType::RightBrace, "Expected '}' after try block")?;
+        
+        self.consume(TokenType::Catch, "Expected 'catch' after try block")?;
+        let catch_variable = if self.match_token(&[TokenType::LeftParen]) {
+            let name = self.consume_identifier("Expected error variable name")?;
+            self.consume(TokenType::RightParen, "Expected ')' after error variable name")?;
+            name
+        } else {
+            "error".to_string()
+        };
+        
+        self.consume(TokenType::LeftBrace, "Expected '{' after 'catch'")?;
+        let mut catch_steps = Vec::new();
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            catch_steps.push(self.parse_step()?);
+        }
+        self.consume(TokenType::RightBrace, "Expected '}' after catch block")?;
+        
+        Ok(TryCatch { try_steps, catch_variable, catch_steps })
+    }
+    
+    fn parse_return(&mut self) -> Result<ReturnStatement> {
+        let line = self.consume(TokenType::Return, "Expected 'return'")?.line;
+        
+        // A value has to start on the same line, otherwise `return` stands alone
+        let value = if self.peek().line == line && !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            Some(self.parse_expression()?)
+        } else {
+            None
+        };
+        
+        Ok(ReturnStatement { value })
+    }
+    
+    fn parse_assignment(&mut self) -> Result<Assignment> {
+        let name = self.consume_identifier("Expected variable name")?;
+        self.consume(TokenType::Equal, "Expected '=' after variable name")?;
+        let value = self.parse_expression()?;
+        
+        Ok(Assignment { name, value })
+    }
+    
     fn parse_variable_declaration(&mut self) -> Result<VariableDeclaration> {
+        let start = self.position();
         let keyword = match self.peek().token_type {
             TokenType::Let => "let",
             TokenType::Var => "var",
             TokenType::Const => "const",
-            _ => return Err(anyhow!("Expected variable declaration keyword")),
+            _ => return Err(self.error_at_current("Expected variable declaration keyword")),
         };
         
         self.advance(); // consume keyword
@@ -154,19 +453,122 @@ impl Parser {
             keyword: keyword.to_string(),
             name,
             value,
+            span: self.span_from(start),
         })
     }
     
+    /// Parse `fn name(a, b) = expression`
+    fn parse_function(&mut self) -> Result<FunctionDeclaration> {
+        let start = self.position();
+        self.consume(TokenType::Fn, "Expected 'fn'")?;
+        let name = self.consume_identifier("Expected function name")?;
+        self.consume(TokenType::LeftParen, "Expected '(' after function name")?;
+        
+        let mut parameters: Vec<String> = Vec::new();
+        if !self.check(TokenType::RightParen) {
+            loop {
+                if parameters.contains(&self.peek().lexeme) {
+                    return Err(self.error_at_current(&format!("Duplicate parameter '{}'", self.peek().lexeme)));
+                }
+                parameters.push(self.consume_identifier("Expected parameter name")?);
+                
+                if !self.match_token(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightParen, "Expected ')' after parameters")?;
+        self.consume(TokenType::Equal, "Expected '=' before function body")?;
+        let body = self.parse_expression()?;
+        
+        Ok(FunctionDeclaration { name, parameters, body, span: self.span_from(start) })
+    }
+    
+    /// Parse `const { name = value; ... }` into one const declaration per member
+    fn parse_const_block(&mut self) -> Result<Vec<VariableDeclaration>> {
+        self.consume(TokenType::Const, "Expected 'const'")?;
+        self.consume(TokenType::LeftBrace, "Expected '{' after 'const'")?;
+        
+        let mut declarations = Vec::new();
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            let start = self.position();
+            let name = self.consume_identifier("Expected constant name")?;
+            self.consume(TokenType::Equal, "Expected '=' after constant name")?;
+            let value = self.parse_expression()?;
+            
+            declarations.push(VariableDeclaration {
+                keyword: "const".to_string(),
+                name,
+                value,
+                span: self.span_from(start),
+            });
+            
+            // Members may be separated by semicolons or commas
+            self.match_token(&[TokenType::Semicolon, TokenType::Comma]);
+        }
+        
+        self.consume(TokenType::RightBrace, "Expected '}' after const block")?;
+        
+        Ok(declarations)
+    }
+    
     fn parse_expression(&mut self) -> Result<Expression> {
-        self.parse_binary_expression()
+        self.parse_or_expression()
+    }
+    
+    // Logical operators bind more loosely than comparisons: `||` below `&&`
+    fn parse_or_expression(&mut self) -> Result<Expression> {
+        let mut left = self.parse_and_expression()?;
+        
+        while self.match_token(&[TokenType::OrOr]) {
+            let right = self.parse_and_expression()?;
+            left = Expression::binary(left, "||", right);
+        }
+        
+        Ok(left)
+    }
+    
+    fn parse_and_expression(&mut self) -> Result<Expression> {
+        let mut left = self.parse_binary_expression()?;
+        
+        while self.match_token(&[TokenType::AndAnd]) {
+            let right = self.parse_binary_expression()?;
+            left = Expression::binary(left, "&&", right);
+        }
+        
+        Ok(left)
     }
     
     fn parse_binary_expression(&mut self) -> Result<Expression> {
-        let mut left = self.parse_primary()?;
+        let mut left = self.parse_additive_expression()?;
         
-        while self.match_token(&[TokenType::Plus, TokenType::EqualEqual, TokenType::NotEqual, 
+        while self.match_token(&[TokenType::EqualEqual, TokenType::NotEqual, 
                                TokenType::Greater, TokenType::Less, TokenType::GreaterEqual, TokenType::LessEqual]) {
             let operator = self.previous().lexeme.clone();
+            let right = self.parse_additive_expression()?;
+            left = Expression::binary(left, &operator, right);
+        }
+        
+        Ok(left)
+    }
+    
+    fn parse_additive_expression(&mut self) -> Result<Expression> {
+        let mut left = self.parse_multiplicative_expression()?;
+        
+        while self.match_token(&[TokenType::Plus, TokenType::Minus]) {
+            let operator = self.previous().lexeme.clone();
+            let right = self.parse_multiplicative_expression()?;
+            left = Expression::binary(left, &operator, right);
+        }
+        
+        Ok(left)
+    }
+    
+    fn parse_multiplicative_expression(&mut self) -> Result<Expression> {
+        let mut left = self.parse_primary()?;
+        
+        while self.match_token(&[TokenType::Star, TokenType::Slash]) {
+            let operator = self.previous().lexeme.clone();
             let right = self.parse_primary()?;
             left = Expression::binary(left, &operator, right);
         }
@@ -175,31 +577,96 @@ impl Parser {
     }
     
     fn parse_primary(&mut self) -> Result<Expression> {
+        let start = self.position();
         match self.peek().token_type {
+            TokenType::Bang | TokenType::Minus => {
+                let operator = self.advance().lexeme.clone();
+                let operand = self.parse_primary()?;
+                Ok(Expression::unary(&operator, operand).with_span(self.span_from(start)))
+            }
+            TokenType::LeftBracket => {
+                self.advance(); // consume '['
+                let mut elements = Vec::new();
+                if !self.check(TokenType::RightBracket) {
+                    loop {
+                        elements.push(self.parse_expression()?);
+                        
+                        if !self.match_token(&[TokenType::Comma]) {
+                            break;
+                        }
+                    }
+                }
+                self.consume(TokenType::RightBracket, "Expected ']' after array elements")?;
+                Ok(Expression::array(elements).with_span(self.span_from(start)))
+            }
+            TokenType::LeftParen => {
+                self.advance(); // consume '('
+                let expression = self.parse_expression()?;
+                self.consume(TokenType::RightParen, "Expected ')' after expression")?;
+                // The parentheses belong to the expression's span
+                Ok(expression.with_span(self.span_from(start)))
+            }
             TokenType::String => {
-                let value = self.advance().literal.clone().unwrap_or_default();
-                Ok(Expression::string(&value))
+                let token = self.advance().clone();
+                let value = token.literal.clone().unwrap_or_default();
+                // Triple-quoted strings are verbatim, so they can hold a literal `${`
+                let expression = if value.contains("${") && !token.lexeme.starts_with("\"\"\"") {
+                    Expression::from(ExpressionKind::Interpolation(parse_interpolation(&value, token.line, token.column + 1)?))
+                } else {
+                    Expression::string(&value)
+                };
+                Ok(expression.with_span(self.span_from(start)))
             }
             TokenType::Number => {
-                let value = self.advance().lexeme.parse::<f64>()
-                    .map_err(|_| anyhow!("Invalid number"))?;
-                Ok(Expression::number(value))
+                let token = self.advance();
+                let value = number_value(token, "Invalid number")?;
+                Ok(Expression::number(value).with_span(self.span_from(start)))
+            }
+            // `env("NAME")` and `env("NAME", "default")` read the environment at run time
+            TokenType::Identifier if self.peek().lexeme == "env" && self.check_next(TokenType::LeftParen) => {
+                self.advance(); // consume 'env'
+                self.advance(); // consume '('
+                let name = self.consume_string("Expected environment variable name")?;
+                let default = if self.match_token(&[TokenType::Comma]) {
+                    Some(self.consume_string("Expected default value")?)
+                } else {
+                    None
+                };
+                self.consume(TokenType::RightParen, "Expected ')' after environment variable")?;
+                Ok(Expression::from(ExpressionKind::EnvironmentVariable { name, default }).with_span(self.span_from(start)))
             }
             TokenType::Identifier => {
                 let name = self.advance().lexeme.clone();
+                let expression = Expression::identifier(&name).with_span(self.span_from(start));
+                self.parse_accesses_and_calls(expression, start)
+            }
+            // A command named in an expression is a function value, e.g. `notify("ops")`
+            token_type if self.check_next(TokenType::LeftParen) && command_keyword(token_type).is_some() => {
+                let name = command_keyword(self.advance().token_type).unwrap_or_default();
+                let expression = Expression::identifier(name).with_span(self.span_from(start));
+                self.parse_accesses_and_calls(expression, start)
+            }
+            // `workflow("Name").result` reads what an earlier workflow returned
+            TokenType::Workflow if self.check_next(TokenType::LeftParen) => {
+                self.advance(); // consume 'workflow'
+                self.advance(); // consume '('
+                let name = self.consume_string("Expected workflow name")?;
+                self.consume(TokenType::RightParen, "Expected ')' after workflow name")?;
                 
-                // Check for property access (e.g., step 1.status)
-                if self.check(TokenType::Dot) {
-                    self.advance(); // consume '.'
+                let mut expression = Expression::from(ExpressionKind::WorkflowReference { name }).with_span(self.span_from(start));
+                while self.match_token(&[TokenType::Dot]) {
                     let property = self.consume_identifier("Expected property name")?;
-                    Ok(Expression::property_access(Expression::identifier(&name), &property))
-                } else {
-                    Ok(Expression::identifier(&name))
+                    expression = Expression::property_access(expression, &property).with_span(self.span_from(start));
                 }
+                
+                Ok(expression)
             }
-            TokenType::Step => {
-                self.advance(); // consume 'step'
-                let step_id = self.consume_number("Expected step number")? as u32;
+            TokenType::Step | TokenType::Prev => {
+                let step_id = if self.advance().token_type == TokenType::Step {
+                    Some(self.consume_number("Expected step number")? as u32)
+                } else {
+                    None
+                };
                 
                 let property = if self.check(TokenType::Dot) {
                     self.advance(); // consume '.'
@@ -208,26 +675,47 @@ impl Parser {
                     None
                 };
                 
-                Ok(Expression::step_reference(step_id, property.as_deref()))
+                // Further segments index into the step's data, e.g. `step 1.data.price`
+                let mut expression = match step_id {
+                    Some(step_id) => Expression::step_reference(step_id, property.as_deref()),
+                    None => Expression::previous_step(property.as_deref()),
+                }
+                .with_span(self.span_from(start));
+                while self.match_token(&[TokenType::Dot]) {
+                    let property = self.consume_identifier("Expected property name")?;
+                    expression = Expression::property_access(expression, &property).with_span(self.span_from(start));
+                }
+                
+                Ok(expression)
             }
-            _ => Err(anyhow!("Expected expression")),
+            _ => Err(self.error_at_current("Expected expression")),
         }
     }
     
-    fn parse_expression_list(&mut self) -> Result<Vec<Expression>> {
-        let mut expressions = Vec::new();
-        
-        if !self.check(TokenType::RightParen) {
-            loop {
-                expressions.push(self.parse_expression()?);
-                
-                if !self.match_token(&[TokenType::Comma]) {
-                    break;
+    /// Property accesses and calls after a name, nesting left-to-right: `config.db.host`
+    /// or `add(1)(2)`
+    fn parse_accesses_and_calls(&mut self, mut expression: Expression, start: (usize, usize)) -> Result<Expression> {
+        loop {
+            if self.match_token(&[TokenType::Dot]) {
+                let property = self.consume_identifier("Expected property name")?;
+                expression = Expression::property_access(expression, &property).with_span(self.span_from(start));
+            } else if self.match_token(&[TokenType::LeftParen]) {
+                let mut arguments = Vec::new();
+                if !self.check(TokenType::RightParen) {
+                    loop {
+                        arguments.push(self.parse_expression()?);
+                        
+                        if !self.match_token(&[TokenType::Comma]) {
+                            break;
+                        }
+                    }
                 }
+                self.consume(TokenType::RightParen, "Expected ')' after arguments")?;
+                expression = Expression::call(expression, arguments).with_span(self.span_from(start));
+            } else {
+                return Ok(expression);
             }
         }
-        
-        Ok(expressions)
     }
     
     // Helper methods
@@ -246,9 +734,16 @@ impl Parser {
         }
     }
     
+    fn check_next(&self, token_type: TokenType) -> bool {
+        self.tokens
+            .get(self.current + 1)
+            .map(|token| token.token_type == token_type)
+            .unwrap_or(false)
+    }
+    
     fn match_token(&mut self, types: &[TokenType]) -> bool {
         for token_type in types {
-            if self.check(token_type.clone()) {
+            if self.check(*token_type) {
                 self.advance();
                 return true;
             }
@@ -260,10 +755,26 @@ impl Parser {
         if self.check(token_type) {
             Ok(self.advance())
         } else {
-            Err(anyhow!("{}", message))
+            Err(self.error_at_current(message))
         }
     }
     
+    /// Build an error pointing at the line and column of the current token
+    fn error_at_current(&self, message: &str) -> anyhow::Error {
+        let token = self.peek();
+        let found = match token.token_type {
+            TokenType::Eof => "end of input".to_string(),
+            _ => format!("'{}'", token.lexeme),
+        };
+        DslError::UnexpectedToken {
+            expected: message.to_string(),
+            found,
+            line: token.line,
+            column: token.column,
+        }
+        .into()
+    }
+    
     fn consume_string(&mut self, message: &str) -> Result<String> {
         let token = self.consume(TokenType::String, message)?;
         Ok(token.literal.clone().unwrap_or_default())
@@ -271,8 +782,7 @@ impl Parser {
     
     fn consume_number(&mut self, message: &str) -> Result<f64> {
         let token = self.consume(TokenType::Number, message)?;
-        token.lexeme.parse::<f64>()
-            .map_err(|_| anyhow!("{}", message))
+        number_value(token, message)
     }
     
     fn consume_identifier(&mut self, message: &str) -> Result<String> {
@@ -280,6 +790,18 @@ impl Parser {
         Ok(token.lexeme.clone())
     }
     
+    /// Line and column where the next token starts
+    fn position(&self) -> (usize, usize) {
+        let token = self.peek();
+        (token.line, token.column)
+    }
+    
+    /// From `start` to the end of the most recently consumed token
+    fn span_from(&self, start: (usize, usize)) -> Span {
+        let (end_line, end_col) = self.previous().end();
+        Span::new(start.0, start.1, end_line, end_col)
+    }
+    
     fn peek(&self) -> &Token {
         &self.tokens[self.current]
     }
@@ -291,4 +813,95 @@ impl Parser {
     fn is_at_end(&self) -> bool {
         self.current >= self.tokens.len() || self.peek().token_type == TokenType::Eof
     }
-} 
\ No newline at end of file
+} 
+
+/// The command a keyword token names, for the commands the lexer has keywords for
+fn command_keyword(token_type: TokenType) -> Option<&'static str> {
+    match token_type {
+        TokenType::Print => Some("print"),
+        TokenType::Log => Some("log"),
+        TokenType::Fetch => Some("fetch"),
+        TokenType::SendEmail => Some("send_email"),
+        TokenType::Notify => Some("notify"),
+        TokenType::Input => Some("input"),
+        TokenType::Generate => Some("generate"),
+        TokenType::Output => Some("output"),
+        TokenType::Transform => Some("transform"),
+        TokenType::Validate => Some("validate"),
+        _ => None,
+    }
+}
+
+/// The value of a number token, whose literal is the value in decimal whatever
+/// notation the source used. Literals too large for an `f64` are an error rather
+/// than infinity.
+fn number_value(token: &Token, invalid: &str) -> Result<f64> {
+    let value = token.literal.as_deref().unwrap_or_default().parse::<f64>()
+        .map_err(|_| anyhow!("{}", invalid))?;
+    if value.is_infinite() {
+        return Err(DslError::NumberTooLarge { line: token.line, column: token.column }.into());
+    }
+    Ok(value)
+}
+
+/// Split a string literal's `value`, which starts at `line` and `column`, into text
+/// and the expressions of its `${...}` placeholders
+fn parse_interpolation(value: &str, line: usize, column: usize) -> Result<Vec<InterpolationPart>> {
+    let chars: Vec<char> = value.chars().collect();
+    // Where the character at `index` sits in the source
+    let position_of = |index: usize| {
+        chars[..index].iter().fold((line, column), |(line, column), c| {
+            if *c == '\n' { (line + 1, 1) } else { (line, column + 1) }
+        })
+    };
+    
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    let mut index = 0;
+    while index < chars.len() {
+        if chars[index] != '$' || chars.get(index + 1) != Some(&'{') {
+            literal.push(chars[index]);
+            index += 1;
+            continue;
+        }
+        
+        let (open_line, open_column) = position_of(index);
+        let Some(length) = chars[index + 2..].iter().position(|c| *c == '}') else {
+            return Err(DslError::UnexpectedToken {
+                expected: "Expected '}' to close '${'".to_string(),
+                found: "end of string".to_string(),
+                line: open_line,
+                column: open_column,
+            }
+            .into());
+        };
+        let source: String = chars[index + 2..index + 2 + length].iter().collect();
+        let (inner_line, inner_column) = position_of(index + 2);
+        
+        let mut tokens = Lexer::new(&source).tokenize().map_err(|error| {
+            anyhow!("{} in interpolation at line {}, column {}", error, open_line, open_column)
+        })?;
+        // Point the inner tokens at their place in the program
+        for token in &mut tokens {
+            if token.line == 1 {
+                token.column += inner_column - 1;
+            }
+            token.line += inner_line - 1;
+        }
+        let mut parser = Parser::new(tokens);
+        let expression = parser.parse_expression()?;
+        if !parser.is_at_end() {
+            return Err(parser.error_at_current("Expected '}' after interpolated expression"));
+        }
+        
+        if !literal.is_empty() {
+            parts.push(InterpolationPart::Literal(std::mem::take(&mut literal)));
+        }
+        parts.push(InterpolationPart::Expression(expression));
+        index += length + 3;
+    }
+    if !literal.is_empty() {
+        parts.push(InterpolationPart::Literal(literal));
+    }
+    Ok(parts)
+}
@@ -0,0 +1,115 @@
+use serde::Serialize;
+use std::fmt;
+use std::ops::RangeInclusive;
+use crate::ast::*;
+use crate::value::format_number;
+
+/// Which AI models and temperatures `generate` may use. Literal arguments are
+/// checked by `check_generation_policy`; an executor given the policy with
+/// `Executor::with_generation_policy` fails `generate` steps whose evaluated
+/// arguments break it.
+#[derive(Debug, Clone)]
+pub struct GenerationPolicy {
+    pub allowed_models: Vec<String>,
+    pub temperature_range: RangeInclusive<f64>,
+}
+
+impl GenerationPolicy {
+    pub fn new(allowed_models: &[&str], temperature_range: RangeInclusive<f64>) -> Self {
+        GenerationPolicy {
+            allowed_models: allowed_models.iter().map(|model| model.to_string()).collect(),
+            temperature_range,
+        }
+    }
+
+    /// Why `model` isn't allowed, if it isn't
+    pub fn check_model(&self, model: &str) -> Option<String> {
+        if self.allowed_models.iter().any(|allowed| allowed == model) {
+            None
+        } else {
+            Some(format!("Model '{}' is not allowed (allowed: {})", model, self.allowed_models.join(", ")))
+        }
+    }
+
+    /// Why `temperature` isn't allowed, if it isn't; it may be a number or numeric text
+    pub fn check_temperature(&self, temperature: &str) -> Option<String> {
+        match temperature.trim().parse::<f64>() {
+            Ok(value) if self.temperature_range.contains(&value) => None,
+            Ok(_) => Some(format!(
+                "Temperature {} is outside {}..={}",
+                temperature, self.temperature_range.start(), self.temperature_range.end()
+            )),
+            Err(_) => Some(format!("Temperature '{}' is not a number", temperature)),
+        }
+    }
+}
+
+/// A `generate` step whose literal arguments break the policy
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PolicyViolation {
+    pub step_id: u32,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Step {} (line {}): {}", self.step_id, self.line, self.message)
+    }
+}
+
+/// Check the literal model and temperature of every `generate` command against
+/// `policy`. Arguments computed at run time are left to the executor.
+pub fn check_generation_policy(program: &Program, policy: &GenerationPolicy) -> Vec<PolicyViolation> {
+    let mut violations = Vec::new();
+    for workflow in &program.workflows {
+        check_steps(&workflow.steps, policy, &mut violations);
+        if let Some(handler) = &workflow.on_error {
+            check_steps(&handler.steps, policy, &mut violations);
+        }
+    }
+    violations
+}
+
+fn check_steps(steps: &[Step], policy: &GenerationPolicy, violations: &mut Vec<PolicyViolation>) {
+    for step in steps {
+        match &step.content {
+            StepContent::Command(command) if command.name == "generate" => {
+                let argument = |index: usize, name: &str| {
+                    command.arguments.get(index).or_else(|| command.named_arguments.get(name)).map(|argument| &argument.kind)
+                };
+                let model = match argument(1, "model") {
+                    Some(ExpressionKind::StringLiteral(model)) => policy.check_model(model),
+                    _ => None,
+                };
+                let temperature = match argument(2, "temperature") {
+                    Some(ExpressionKind::StringLiteral(temperature)) => policy.check_temperature(temperature),
+                    Some(ExpressionKind::NumberLiteral(temperature)) => policy.check_temperature(&format_number(*temperature)),
+                    _ => None,
+                };
+                violations.extend(model.into_iter().chain(temperature).map(|message| PolicyViolation {
+                    step_id: step.id,
+                    line: step.line,
+                    message,
+                }));
+            }
+            StepContent::Command(_)
+            | StepContent::CallWorkflow(_)
+            | StepContent::Return(_)
+            | StepContent::Assignment(_)
+            | StepContent::Declaration(_) => {}
+            StepContent::Conditional(conditional) => {
+                check_steps(&conditional.if_steps, policy, violations);
+                if let Some(else_steps) = &conditional.else_steps {
+                    check_steps(else_steps, policy, violations);
+                }
+            }
+            StepContent::WhileLoop(while_loop) => check_steps(&while_loop.body, policy, violations),
+            StepContent::ForEach(for_each) => check_steps(&for_each.body, policy, violations),
+            StepContent::TryCatch(try_catch) => {
+                check_steps(&try_catch.try_steps, policy, violations);
+                check_steps(&try_catch.catch_steps, policy, violations);
+            }
+        }
+    }
+}
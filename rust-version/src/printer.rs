@@ -0,0 +1,143 @@
+use crate::ast::*;
+
+impl Program {
+    /// Source code for the program that parses back to the same AST, indented four
+    /// spaces per block. Included steps were merged in by the parser, so they are
+    /// written out in full and the `includes` clause is left off.
+    pub fn to_dsl_string(&self) -> String {
+        let mut printer = Printer::default();
+        for variable in &self.variables {
+            printer.line(&declaration(variable));
+        }
+        for function in &self.functions {
+            printer.line(&format!("fn {}({}) = {}", function.name, function.parameters.join(", "), function.body));
+        }
+        for (index, workflow) in self.workflows.iter().enumerate() {
+            if index > 0 || !self.variables.is_empty() || !self.functions.is_empty() {
+                printer.out.push('\n');
+            }
+            printer.workflow(workflow);
+        }
+        printer.out
+    }
+}
+
+#[derive(Default)]
+struct Printer {
+    out: String,
+    depth: usize,
+}
+
+impl Printer {
+    fn line(&mut self, text: &str) {
+        self.out.push_str(&"    ".repeat(self.depth));
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    /// `header {`, the steps indented inside, and the closing brace
+    fn block(&mut self, header: &str, steps: &[Step]) {
+        self.line(&format!("{} {{", header));
+        self.depth += 1;
+        steps.iter().for_each(|step| self.step(step));
+        self.depth -= 1;
+        self.line("}");
+    }
+
+    fn workflow(&mut self, workflow: &Workflow) {
+        if let Some(schedule) = &workflow.schedule {
+            self.line(&format!("@schedule({})", quote(schedule)));
+        }
+        self.line(&format!("workflow {} {{", quote(&workflow.name)));
+        self.depth += 1;
+        for variable in &workflow.variables {
+            self.line(&declaration(variable));
+        }
+        workflow.steps.iter().for_each(|step| self.step(step));
+        if let Some(handler) = &workflow.on_error {
+            self.block(&format!("on_error({})", handler.variable), &handler.steps);
+        }
+        self.depth -= 1;
+        self.line("}");
+    }
+
+    fn step(&mut self, step: &Step) {
+        if let Some(description) = &step.description {
+            self.line(&format!("/// {}", description));
+        }
+        let label = match step.id {
+            0 => String::new(),
+            id => format!("step {}: ", id),
+        };
+        match &step.content {
+            StepContent::Command(command) => self.line(&format!("{}{}", label, command_call(command))),
+            StepContent::Conditional(conditional) => self.conditional(&label, step.id, conditional),
+            StepContent::WhileLoop(while_loop) => {
+                self.block(&format!("{}while ({})", label, while_loop.condition), &while_loop.body)
+            }
+            StepContent::ForEach(for_each) => self.block(
+                &format!("{}for {} in {}", label, for_each.var_name, for_each.iterable),
+                &for_each.body,
+            ),
+            StepContent::TryCatch(try_catch) => {
+                self.line(&format!("{}try {{", label));
+                self.depth += 1;
+                try_catch.try_steps.iter().for_each(|step| self.step(step));
+                self.depth -= 1;
+                self.block(&format!("}} catch({})", try_catch.catch_variable), &try_catch.catch_steps);
+            }
+            StepContent::CallWorkflow(call) => self.line(&format!("{}call {}", label, quote(&call.name))),
+            StepContent::Return(return_statement) => match &return_statement.value {
+                Some(value) => self.line(&format!("{}return {}", label, value)),
+                None => self.line(&format!("{}return", label)),
+            },
+            StepContent::Assignment(assignment) => {
+                self.line(&format!("{}{} = {}", label, assignment.name, assignment.value))
+            }
+            StepContent::Declaration(variable) => self.line(&format!("{}{}", label, declaration(variable))),
+        }
+        // The guard follows the step's last line, after the closing brace of a block
+        if let Some(guard) = &step.guard {
+            self.out.pop();
+            self.out.push_str(&format!(" when ({})\n", guard));
+        }
+    }
+
+    /// An `if` with its `else if` chain and final `else`, each closing brace sharing a
+    /// line with the keyword that follows it
+    fn conditional(&mut self, label: &str, id: u32, conditional: &ConditionalStatement) {
+        self.line(&format!("{}if ({}) {{", label, conditional.condition));
+        let mut current = conditional;
+        loop {
+            self.depth += 1;
+            current.if_steps.iter().for_each(|step| self.step(step));
+            self.depth -= 1;
+            match current.else_steps.as_deref() {
+                Some([Step { id: nested_id, content: StepContent::Conditional(nested), .. }]) if *nested_id == id => {
+                    self.line(&format!("}} else if ({}) {{", nested.condition));
+                    current = nested;
+                }
+                Some(else_steps) => {
+                    self.block("} else", else_steps);
+                    return;
+                }
+                None => {
+                    self.line("}");
+                    return;
+                }
+            }
+        }
+    }
+}
+
+fn declaration(variable: &VariableDeclaration) -> String {
+    format!("{} {} = {}", variable.keyword, variable.name, variable.value)
+}
+
+pub(crate) fn command_call(command: &Command) -> String {
+    let arguments: Vec<String> = command.arguments.iter()
+        .map(|argument| argument.to_string())
+        .chain(command.named_arguments.iter().map(|(name, value)| format!("{}: {}", name, value)))
+        .collect();
+    format!("{}({})", command.name, arguments.join(", "))
+}
@@ -0,0 +1,65 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use crate::executor::ExecutionReport;
+
+impl ExecutionReport {
+    /// The run's metrics in the Prometheus text format: step runs and failed step
+    /// runs per workflow, with loop iterations counted one by one, the run's duration
+    /// and its peak memory use
+    pub fn to_prometheus(&self) -> String {
+        let mut runs: BTreeMap<&str, (usize, usize)> = self.workflows.iter().map(|name| (name.as_str(), (0, 0))).collect();
+        for timing in &self.timings {
+            let (total, failures) = runs.entry(timing.workflow.as_str()).or_default();
+            *total += 1;
+            if !timing.success {
+                *failures += 1;
+            }
+        }
+        
+        let mut text = String::new();
+        let mut family = |name: &str, kind: &str, help: &str, samples: Vec<(String, String)>| {
+            text.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n", name, help, name, kind));
+            for (labels, value) in samples {
+                text.push_str(&format!("{}{} {}\n", name, labels, value));
+            }
+        };
+        let per_workflow = |count: fn(&(usize, usize)) -> usize| {
+            runs.iter()
+                .map(|(workflow, counts)| (format!("{{workflow=\"{}\"}}", label_value(workflow)), count(counts).to_string()))
+                .collect()
+        };
+        family("tmflow_steps_total", "counter", "Step runs, each loop iteration counted", per_workflow(|counts| counts.0));
+        family("tmflow_step_failures_total", "counter", "Step runs that failed", per_workflow(|counts| counts.1));
+        family(
+            "tmflow_run_duration_seconds", "gauge", "Wall-clock time of the run",
+            vec![(String::new(), self.total_duration.as_secs_f64().to_string())],
+        );
+        family(
+            "tmflow_peak_values", "gauge", "Most values held at once",
+            vec![(String::new(), self.metrics.peak_values.to_string())],
+        );
+        family(
+            "tmflow_peak_bytes", "gauge", "Most bytes held at once",
+            vec![(String::new(), self.metrics.peak_bytes.to_string())],
+        );
+        text
+    }
+    
+    /// Write `to_prometheus` to `path` for the node exporter's textfile collector.
+    /// The text goes to a temporary file that is then renamed over `path`, so a
+    /// scrape never sees half a file.
+    pub fn write_prometheus(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let partial = path.with_extension("prom.tmp");
+        fs::write(&partial, self.to_prometheus())?;
+        fs::rename(&partial, path)?;
+        Ok(())
+    }
+}
+
+/// A label value with backslashes, quotes and newlines escaped
+fn label_value(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
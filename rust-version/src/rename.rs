@@ -0,0 +1,305 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+use crate::ast::*;
+use crate::lexer::{Lexer, TokenType};
+
+impl Program {
+    /// Rename a variable and every reference to it. The program-level binding is
+    /// renamed when there is one, otherwise the workflow-level bindings are; inner
+    /// declarations that shadow it keep their name along with their references.
+    pub fn rename_variable(&mut self, old: &str, new: &str) -> Result<()> {
+        if old == new {
+            return Ok(());
+        }
+
+        let tokens = Lexer::new(new).tokenize()?;
+        if tokens.len() != 2 || tokens[0].token_type != TokenType::Identifier || ["true", "false", "null"].contains(&new) {
+            return Err(anyhow!("'{}' is not a valid variable name", new));
+        }
+
+        let global = self.variables.iter().any(|variable| variable.name == old);
+        let declared = global || self.workflows
+            .iter()
+            .any(|workflow| workflow.variables.iter().any(|variable| variable.name == old));
+        if !declared {
+            return Err(anyhow!("Variable '{}' is not declared", old));
+        }
+
+        let mut names = HashSet::new();
+        collect_names(self, &mut names);
+        if names.contains(new) {
+            return Err(anyhow!("Cannot rename '{}' to '{}': '{}' is already used", old, new, new));
+        }
+        let mut renamer = Renamer { old, new, scopes: vec![None] };
+
+        for variable in &mut self.variables {
+            renamer.declaration(variable, true);
+        }
+        // Function bodies see every program-level variable, unless a parameter shadows it
+        for function in &mut self.functions {
+            renamer.scopes.push(None);
+            for parameter in &function.parameters {
+                renamer.bind(parameter, false);
+            }
+            renamer.expression(&mut function.body);
+            renamer.scopes.pop();
+        }
+        for workflow in &mut self.workflows {
+            renamer.scopes.push(None);
+            for variable in &mut workflow.variables {
+                renamer.declaration(variable, !global);
+            }
+            renamer.steps(&mut workflow.steps);
+            if let Some(handler) = &mut workflow.on_error {
+                renamer.scopes.push(None);
+                renamer.bind(&handler.variable, false);
+                renamer.steps(&mut handler.steps);
+                renamer.scopes.pop();
+            }
+            renamer.scopes.pop();
+        }
+
+        Ok(())
+    }
+}
+
+/// Walks the program with a scope stack; each scope records whether it binds the
+/// old name and, if so, whether that binding is the one being renamed
+struct Renamer<'a> {
+    old: &'a str,
+    new: &'a str,
+    scopes: Vec<Option<bool>>,
+}
+
+impl Renamer<'_> {
+    fn declaration(&mut self, variable: &mut VariableDeclaration, is_target: bool) {
+        // The initializer still sees the outer binding
+        self.expression(&mut variable.value);
+        if variable.name == self.old {
+            self.bind(self.old, is_target);
+            if is_target {
+                variable.name = self.new.to_string();
+            }
+        }
+    }
+
+    fn bind(&mut self, name: &str, is_target: bool) {
+        if name == self.old {
+            if let Some(scope) = self.scopes.last_mut() {
+                *scope = Some(is_target);
+            }
+        }
+    }
+
+    /// Whether `old` currently resolves to the binding being renamed
+    fn target_visible(&self) -> bool {
+        self.scopes.iter().rev().find_map(|scope| *scope).unwrap_or(false)
+    }
+
+    fn block(&mut self, steps: &mut [Step]) {
+        self.scopes.push(None);
+        self.steps(steps);
+        self.scopes.pop();
+    }
+
+    fn steps(&mut self, steps: &mut [Step]) {
+        for step in steps {
+            if let Some(guard) = &mut step.guard {
+                self.expression(guard);
+            }
+            match &mut step.content {
+                StepContent::Command(command) => {
+                    for argument in command.arguments.iter_mut().chain(command.named_arguments.values_mut()) {
+                        self.expression(argument);
+                    }
+                }
+                StepContent::Conditional(conditional) => {
+                    self.expression(&mut conditional.condition);
+                    self.block(&mut conditional.if_steps);
+                    if let Some(else_steps) = &mut conditional.else_steps {
+                        self.block(else_steps);
+                    }
+                }
+                StepContent::WhileLoop(while_loop) => {
+                    self.expression(&mut while_loop.condition);
+                    self.block(&mut while_loop.body);
+                }
+                StepContent::ForEach(for_each) => {
+                    self.expression(&mut for_each.iterable);
+                    self.scopes.push(None);
+                    self.bind(&for_each.var_name, false);
+                    self.steps(&mut for_each.body);
+                    self.scopes.pop();
+                }
+                StepContent::TryCatch(try_catch) => {
+                    self.block(&mut try_catch.try_steps);
+                    self.scopes.push(None);
+                    self.bind(&try_catch.catch_variable, false);
+                    self.steps(&mut try_catch.catch_steps);
+                    self.scopes.pop();
+                }
+                StepContent::CallWorkflow(_) => {}
+                StepContent::Return(return_statement) => {
+                    if let Some(value) = &mut return_statement.value {
+                        self.expression(value);
+                    }
+                }
+                StepContent::Assignment(assignment) => {
+                    self.expression(&mut assignment.value);
+                    if assignment.name == self.old && self.target_visible() {
+                        assignment.name = self.new.to_string();
+                    }
+                }
+                StepContent::Declaration(variable) => self.declaration(variable, false),
+            }
+        }
+    }
+
+    fn expression(&mut self, expression: &mut Expression) {
+        match &mut expression.kind {
+            ExpressionKind::Identifier(name) => {
+                if name == self.old && self.target_visible() {
+                    *name = self.new.to_string();
+                }
+            }
+            ExpressionKind::ArrayLiteral(elements) => {
+                for element in elements {
+                    self.expression(element);
+                }
+            }
+            ExpressionKind::BinaryExpression { left, right, .. } => {
+                self.expression(left);
+                self.expression(right);
+            }
+            ExpressionKind::UnaryExpression { operand, .. } => self.expression(operand),
+            ExpressionKind::PropertyAccess { object, .. } => self.expression(object),
+            ExpressionKind::Interpolation(parts) => {
+                for expression in parts.iter_mut().filter_map(InterpolationPart::expression_mut) {
+                    self.expression(expression);
+                }
+            }
+            ExpressionKind::Call { callee, arguments } => {
+                self.expression(callee);
+                for argument in arguments {
+                    self.expression(argument);
+                }
+            }
+            ExpressionKind::StringLiteral(_)
+            | ExpressionKind::NumberLiteral(_)
+            | ExpressionKind::StepReference { .. }
+            | ExpressionKind::PreviousStep { .. }
+            | ExpressionKind::WorkflowReference { .. }
+            | ExpressionKind::EnvironmentVariable { .. } => {}
+        }
+    }
+}
+
+/// Every variable name declared, bound or referenced anywhere in the program
+fn collect_names(program: &Program, names: &mut HashSet<String>) {
+    for variable in &program.variables {
+        names.insert(variable.name.clone());
+        collect_expression_names(&variable.value, names);
+    }
+    for function in &program.functions {
+        names.insert(function.name.clone());
+        names.extend(function.parameters.iter().cloned());
+        collect_expression_names(&function.body, names);
+    }
+    for workflow in &program.workflows {
+        for variable in &workflow.variables {
+            names.insert(variable.name.clone());
+            collect_expression_names(&variable.value, names);
+        }
+        collect_step_names(&workflow.steps, names);
+        if let Some(handler) = &workflow.on_error {
+            names.insert(handler.variable.clone());
+            collect_step_names(&handler.steps, names);
+        }
+    }
+}
+
+fn collect_step_names(steps: &[Step], names: &mut HashSet<String>) {
+    for step in steps {
+        if let Some(guard) = &step.guard {
+            collect_expression_names(guard, names);
+        }
+        match &step.content {
+            StepContent::Command(command) => {
+                for argument in command.arguments.iter().chain(command.named_arguments.values()) {
+                    collect_expression_names(argument, names);
+                }
+            }
+            StepContent::Conditional(conditional) => {
+                collect_expression_names(&conditional.condition, names);
+                collect_step_names(&conditional.if_steps, names);
+                if let Some(else_steps) = &conditional.else_steps {
+                    collect_step_names(else_steps, names);
+                }
+            }
+            StepContent::WhileLoop(while_loop) => {
+                collect_expression_names(&while_loop.condition, names);
+                collect_step_names(&while_loop.body, names);
+            }
+            StepContent::ForEach(for_each) => {
+                names.insert(for_each.var_name.clone());
+                collect_expression_names(&for_each.iterable, names);
+                collect_step_names(&for_each.body, names);
+            }
+            StepContent::TryCatch(try_catch) => {
+                names.insert(try_catch.catch_variable.clone());
+                collect_step_names(&try_catch.try_steps, names);
+                collect_step_names(&try_catch.catch_steps, names);
+            }
+            StepContent::CallWorkflow(_) => {}
+            StepContent::Return(return_statement) => {
+                if let Some(value) = &return_statement.value {
+                    collect_expression_names(value, names);
+                }
+            }
+            StepContent::Assignment(assignment) => {
+                names.insert(assignment.name.clone());
+                collect_expression_names(&assignment.value, names);
+            }
+            StepContent::Declaration(variable) => {
+                names.insert(variable.name.clone());
+                collect_expression_names(&variable.value, names);
+            }
+        }
+    }
+}
+
+fn collect_expression_names(expression: &Expression, names: &mut HashSet<String>) {
+    match &expression.kind {
+        ExpressionKind::Identifier(name) => {
+            names.insert(name.clone());
+        }
+        ExpressionKind::ArrayLiteral(elements) => {
+            for element in elements {
+                collect_expression_names(element, names);
+            }
+        }
+        ExpressionKind::BinaryExpression { left, right, .. } => {
+            collect_expression_names(left, names);
+            collect_expression_names(right, names);
+        }
+        ExpressionKind::UnaryExpression { operand, .. } => collect_expression_names(operand, names),
+        ExpressionKind::PropertyAccess { object, .. } => collect_expression_names(object, names),
+        ExpressionKind::Interpolation(parts) => {
+            for expression in parts.iter().filter_map(InterpolationPart::expression) {
+                collect_expression_names(expression, names);
+            }
+        }
+        ExpressionKind::Call { callee, arguments } => {
+            collect_expression_names(callee, names);
+            for argument in arguments {
+                collect_expression_names(argument, names);
+            }
+        }
+        ExpressionKind::StringLiteral(_)
+        | ExpressionKind::NumberLiteral(_)
+        | ExpressionKind::StepReference { .. }
+        | ExpressionKind::PreviousStep { .. }
+        | ExpressionKind::WorkflowReference { .. }
+        | ExpressionKind::EnvironmentVariable { .. } => {}
+    }
+}
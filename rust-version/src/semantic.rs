@@ -0,0 +1,82 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::ops::Range;
+use crate::incremental::{line_starts, token_offset};
+use crate::lexer::{Lexer, Token, TokenType};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum SemanticCategory {
+    Keyword,
+    String,
+    Number,
+    Command,
+    Variable,
+    Property,
+    Operator,
+}
+
+/// A highlighted range of the source; `range` is in characters, `line` and `column` are 1-based
+#[derive(Debug, Clone, Serialize)]
+pub struct SemanticToken {
+    pub range: Range<usize>,
+    pub line: usize,
+    pub column: usize,
+    pub category: SemanticCategory,
+}
+
+/// Categorize every token worth highlighting. Punctuation is left out, and identifiers
+/// are told apart by position: `step 1: name(...)` is a command, `a.name` a property.
+pub fn semantic_tokens(source: &str) -> Result<Vec<SemanticToken>> {
+    let tokens = Lexer::new(source).tokenize()?;
+    let line_starts = line_starts(source);
+
+    let tokens = tokens
+        .iter()
+        .enumerate()
+        .filter_map(|(index, token)| {
+            let previous = index.checked_sub(1).map(|previous| tokens[previous].token_type);
+            let next = tokens.get(index + 1).map(|next| next.token_type);
+            let category = categorize(token, previous, next)?;
+            let start = token_offset(&line_starts, token);
+            Some(SemanticToken {
+                range: start..start + token.lexeme.chars().count(),
+                line: token.line,
+                column: token.column,
+                category,
+            })
+        })
+        .collect();
+
+    Ok(tokens)
+}
+
+fn categorize(token: &Token, previous: Option<TokenType>, next: Option<TokenType>) -> Option<SemanticCategory> {
+    use TokenType::*;
+
+    let category = match token.token_type {
+        Workflow | Step | Let | Var | Const | If | Else | While | For | In | Try | Catch | Call | Return | Fn | OnError | Prev => SemanticCategory::Keyword,
+        Print | Log | Fetch | SendEmail | Notify | Input | Generate | Output | Transform | Validate => {
+            SemanticCategory::Command
+        }
+        String => SemanticCategory::String,
+        Number => SemanticCategory::Number,
+        Identifier => match (previous, next) {
+            // Property access, or the name of a named command argument
+            (Some(Dot), _) | (Some(LeftParen | Comma), Some(Colon)) => SemanticCategory::Property,
+            // Workflow attributes such as `@schedule`
+            (Some(At), _) => SemanticCategory::Keyword,
+            (Some(Colon), next) if next != Some(Equal) => SemanticCategory::Command,
+            _ if matches!(token.lexeme.as_str(), "true" | "false" | "null") => SemanticCategory::Keyword,
+            // The `env("NAME")` built-in
+            (_, Some(LeftParen)) if token.lexeme == "env" || token.lexeme == "when" => SemanticCategory::Keyword,
+            // A function declared with `fn` or called in an expression
+            (Some(Fn), _) | (_, Some(LeftParen)) => SemanticCategory::Command,
+            _ => SemanticCategory::Variable,
+        },
+        Plus | Minus | Star | Slash | Equal | EqualEqual | NotEqual | Greater | Less | GreaterEqual | LessEqual
+        | AndAnd | OrOr | Bang => SemanticCategory::Operator,
+        Dot | LeftParen | RightParen | LeftBrace | RightBrace | LeftBracket | RightBracket | Colon
+        | Semicolon | Comma | At | Eof => return None,
+    };
+    Some(category)
+}
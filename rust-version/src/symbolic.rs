@@ -0,0 +1,256 @@
+use indexmap::IndexMap;
+use std::collections::{HashMap, HashSet};
+use crate::analyzer::Diagnostic;
+use crate::ast::*;
+use crate::executor::Executor;
+use crate::value::Value;
+use crate::visit::{walk_expression, Visitor};
+
+/// Runtime errors every run is bound to hit, found by following the branches whose
+/// conditions only involve literals and the literal values variables were given.
+/// Anything read from a step, `prev`, another workflow or `env` is unknown, and code
+/// that only runs depending on an unknown value, inside a loop past its first
+/// iteration or inside `try` is not reported. Best effort: no diagnostics doesn't
+/// mean the program can't fail.
+pub fn dry_analyze(program: &Program) -> Vec<Diagnostic> {
+    let mut analysis = DryRun {
+        scopes: vec![HashMap::new()],
+        functions: program.functions.iter().map(|function| function.name.clone()).collect(),
+        certain: true,
+        stopped: false,
+        diagnostics: Vec::new(),
+    };
+    for variable in &program.variables {
+        analysis.declaration(variable.span.start_line, variable);
+    }
+    // A failing program-level declaration stops the run before any workflow
+    if analysis.stopped {
+        return analysis.diagnostics;
+    }
+    for workflow in &program.workflows {
+        analysis.stopped = false;
+        analysis.scopes.push(HashMap::new());
+        for variable in &workflow.variables {
+            analysis.declaration(variable.span.start_line, variable);
+        }
+        analysis.steps(&workflow.steps);
+        if let Some(handler) = &workflow.on_error {
+            analysis.stopped = false;
+            analysis.uncertain(|analysis| analysis.block_with(&handler.variable, None, &handler.steps));
+        }
+        analysis.scopes.pop();
+    }
+    analysis.diagnostics
+}
+
+/// A variable as far as the analysis can tell; `value` is `None` once it depends on run time
+#[derive(Debug, Clone)]
+struct Binding {
+    value: Option<Value>,
+    constant: bool,
+}
+
+struct DryRun {
+    /// Innermost scope last; the first scope holds program-level variables
+    scopes: Vec<HashMap<String, Binding>>,
+    /// Names of the program's `fn` declarations, whose calls are left to run time
+    functions: HashSet<String>,
+    /// Whether the steps being analyzed run on every run that gets this far
+    certain: bool,
+    /// Set once a certain error or `return` has ended the workflow
+    stopped: bool,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl DryRun {
+    fn steps(&mut self, steps: &[Step]) {
+        for step in steps {
+            if self.stopped {
+                return;
+            }
+            self.step(step);
+        }
+    }
+
+    fn step(&mut self, step: &Step) {
+        if let Some(guard) = &step.guard {
+            match self.evaluate(step.line, guard) {
+                Some(value) if !value.is_truthy() => return,
+                Some(_) => {}
+                None => return self.uncertain(|analysis| analysis.content(step)),
+            }
+        }
+        self.content(step);
+    }
+
+    fn content(&mut self, step: &Step) {
+        let line = step.line;
+        match &step.content {
+            StepContent::Command(command) => {
+                for argument in command.arguments.iter().chain(command.named_arguments.values()) {
+                    self.evaluate(line, argument);
+                }
+            }
+            StepContent::Conditional(conditional) => self.conditional(line, conditional),
+            StepContent::WhileLoop(while_loop) => {
+                let condition = self.evaluate(line, &while_loop.condition);
+                if condition.as_ref().is_some_and(Value::is_truthy) {
+                    self.block(&while_loop.body);
+                }
+                // Later iterations see whatever the earlier ones assigned
+                if condition.is_none_or(|condition| condition.is_truthy()) {
+                    self.uncertain(|analysis| analysis.block(&while_loop.body));
+                }
+            }
+            StepContent::ForEach(for_each) => match self.evaluate(line, &for_each.iterable) {
+                Some(Value::Array(items)) if items.is_empty() => {}
+                Some(Value::Array(items)) => {
+                    self.block_with(&for_each.var_name, items.first().cloned(), &for_each.body);
+                    self.uncertain(|analysis| analysis.block_with(&for_each.var_name, None, &for_each.body));
+                }
+                _ => self.uncertain(|analysis| analysis.block_with(&for_each.var_name, None, &for_each.body)),
+            },
+            // Errors inside `try` are handled by its `catch`
+            StepContent::TryCatch(try_catch) => self.uncertain(|analysis| {
+                analysis.block(&try_catch.try_steps);
+                analysis.block_with(&try_catch.catch_variable, None, &try_catch.catch_steps);
+            }),
+            // The called workflow may assign any program-level variable
+            StepContent::CallWorkflow(_) => {
+                for binding in self.scopes[0].values_mut().filter(|binding| !binding.constant) {
+                    binding.value = None;
+                }
+            }
+            StepContent::Return(return_statement) => {
+                if let Some(value) = &return_statement.value {
+                    self.evaluate(line, value);
+                }
+                if self.certain {
+                    self.stopped = true;
+                }
+            }
+            StepContent::Assignment(assignment) => self.assignment(line, assignment),
+            StepContent::Declaration(variable) => self.declaration(line, variable),
+        }
+    }
+
+    fn conditional(&mut self, line: usize, conditional: &ConditionalStatement) {
+        match self.evaluate(line, &conditional.condition) {
+            Some(value) if value.is_truthy() => self.block(&conditional.if_steps),
+            Some(_) => {
+                if let Some(else_steps) = &conditional.else_steps {
+                    self.block(else_steps);
+                }
+            }
+            None => self.uncertain(|analysis| {
+                analysis.block(&conditional.if_steps);
+                if let Some(else_steps) = &conditional.else_steps {
+                    analysis.block(else_steps);
+                }
+            }),
+        }
+    }
+
+    fn block(&mut self, steps: &[Step]) {
+        self.scopes.push(HashMap::new());
+        self.steps(steps);
+        self.scopes.pop();
+    }
+
+    /// A block with `name` bound inside it, like a loop variable
+    fn block_with(&mut self, name: &str, value: Option<Value>, steps: &[Step]) {
+        self.scopes.push(HashMap::from([(name.to_string(), Binding { value, constant: false })]));
+        self.steps(steps);
+        self.scopes.pop();
+    }
+
+    /// Analyze code that may or may not run: nothing in it is reported, and what it
+    /// assigns is unknown afterwards
+    fn uncertain(&mut self, analyze: impl FnOnce(&mut Self)) {
+        let (certain, stopped) = (self.certain, self.stopped);
+        self.certain = false;
+        analyze(self);
+        self.certain = certain;
+        self.stopped = stopped;
+    }
+
+    fn declaration(&mut self, line: usize, variable: &VariableDeclaration) {
+        let value = self.evaluate(line, &variable.value).filter(|_| self.certain);
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(variable.name.clone(), Binding { value, constant: variable.keyword == "const" });
+        }
+    }
+
+    fn assignment(&mut self, line: usize, assignment: &Assignment) {
+        let value = self.evaluate(line, &assignment.value);
+        let certain = self.certain;
+        match self.scopes.iter_mut().rev().find_map(|scope| scope.get_mut(&assignment.name)) {
+            Some(binding) if binding.constant => {
+                self.error(line, format!("Cannot assign to constant: {}", assignment.name))
+            }
+            Some(binding) => binding.value = value.filter(|_| certain),
+            None => self.error(line, format!("Cannot assign to undeclared variable: {}", assignment.name)),
+        }
+    }
+
+    /// The value of `expression` when it only involves literals and known variables,
+    /// reporting the error it is certain to raise
+    fn evaluate(&mut self, line: usize, expression: &Expression) -> Option<Value> {
+        let mut dynamic = Dynamic { scopes: &self.scopes, functions: &self.functions, found: false };
+        dynamic.visit_expression(expression);
+        if dynamic.found {
+            return None;
+        }
+        // Inner bindings come later, replacing the outer ones they shadow
+        let mut bindings = IndexMap::new();
+        for scope in &self.scopes {
+            for (name, binding) in scope {
+                if let Some(value) = &binding.value {
+                    bindings.shift_remove(name);
+                    bindings.insert(name.clone(), value.clone());
+                }
+            }
+        }
+        match Executor::evaluate_literal(expression, &bindings) {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.error(line, error.to_string());
+                None
+            }
+        }
+    }
+
+    fn error(&mut self, line: usize, message: String) {
+        if self.certain {
+            self.diagnostics.push(Diagnostic::error(format!("Line {}: {}", line, message)));
+            self.stopped = true;
+        }
+    }
+}
+
+/// Whether an expression reads anything only known at run time
+struct Dynamic<'a> {
+    scopes: &'a [HashMap<String, Binding>],
+    functions: &'a HashSet<String>,
+    found: bool,
+}
+
+impl Visitor for Dynamic<'_> {
+    fn visit_expression(&mut self, expression: &Expression) {
+        match &expression.kind {
+            ExpressionKind::StepReference { .. }
+            | ExpressionKind::PreviousStep { .. }
+            | ExpressionKind::WorkflowReference { .. }
+            | ExpressionKind::EnvironmentVariable { .. }
+            | ExpressionKind::Call { .. } => self.found = true,
+            // Undeclared names aren't dynamic: reading them is certain to fail
+            ExpressionKind::Identifier(name) => {
+                let binding = self.scopes.iter().rev().find_map(|scope| scope.get(name));
+                if binding.is_some_and(|binding| binding.value.is_none()) || (binding.is_none() && self.functions.contains(name)) {
+                    self.found = true;
+                }
+            }
+            _ => walk_expression(self, expression),
+        }
+    }
+}
@@ -1,7 +1,14 @@
-use crate::lexer::Lexer;
-use crate::parser::Parser;
-use crate::executor::Executor;
+use trademinutes_dsl::lexer::Lexer;
+use trademinutes_dsl::parser::Parser;
+use trademinutes_dsl::executor::Executor;
+use trademinutes_dsl::{
+    analyze_program, check_generation_policy, compile_dsl, dry_analyze, human_steps, workflow_to_dot, workflow_to_mermaid, run_dsl_lenient, run_dsl_with_report,
+    run_program_json, semantic_tokens, validate_program, walk_command,
+    Change, Command, DslError, ErrorPolicy, Expression, ExecutionEvent, ExecutionReport, ExpressionKind, GenerationPolicy, InputProvider, InputRequest, Program, SemanticCategory, Span,
+    RandomSource, SeededRandom, StepContent, StepResult, Value, Visitor, VisitorMut,
+};
 use anyhow::Result;
+use std::collections::BTreeMap;
 
 pub fn run_examples() {
     println!("🧪 Running TradeMinutes DSL Examples");
@@ -122,6 +129,4006 @@ workflow "AI Content Generator" {
         Ok(_) => println!("✅ Example 5 executed successfully"),
         Err(e) => println!("❌ Example 5 failed: {}", e),
     }
+    
+    // Example 6: Numeric addition vs concatenation
+    let example6 = r#"
+workflow "ArithmeticExample" {
+    let total = 2 + 3
+    let label = "x" + 1
+    
+    step 1: print(total)
+    step 2: print("a" + "b")
+    step 3: print(label)
+}
+"#;
+    
+    println!("\n📝 Example 6: Numeric Addition");
+    println!("{}", example6);
+    
+    match run_dsl_with_report(example6) {
+        Ok(report) => {
+            // `print` keeps what it printed; concatenating 2 and 3 would have printed "23"
+            let data: Vec<String> = (1..=3)
+                .filter_map(|id| report.step("ArithmeticExample", id).map(|result| result.data.to_display_string()))
+                .collect();
+            if data == ["5", "ab", "x1"] {
+                println!("✅ Example 6 added numbers and concatenated strings: {:?}", data)
+            } else {
+                println!("❌ Example 6 unexpected results: {:?}", data)
+            }
+        }
+        Err(e) => println!("❌ Example 6 failed: {}", e),
+    }
+    
+    // Example 7: Polling with a while loop
+    let example7 = r#"
+workflow "PollingExample" {
+    step 1: fetch("https://api.com/job-status")
+    
+    step 2: while (step 1.status != 200) {
+        step 3: sleep(100)
+        step 4: fetch("https://api.com/job-status")
+    }
+    step 5: print("Job finished with status " + step 1.status)
+}
+"#;
+    
+    println!("\n📝 Example 7: While Loop");
+    println!("{}", example7);
+    
+    match run_dsl_example(example7) {
+        Ok(_) => println!("✅ Example 7 executed successfully"),
+        Err(e) => println!("❌ Example 7 failed: {}", e),
+    }
+    
+    // Example 8: Else-if chains
+    let example8 = r#"
+workflow "StatusRangeExample" {
+    step 1: fetch("https://api.com/status")
+    
+    step 2: if (step 1.status >= 500) {
+        step 3: print("Server error")
+    } else if (step 1.status >= 400) {
+        step 4: print("Client error")
+    } else {
+        step 5: print("Success")
+    }
+}
+"#;
+    
+    println!("\n📝 Example 8: Else-If Chains");
+    println!("{}", example8);
+    
+    match run_dsl_example(example8) {
+        Ok(_) => println!("✅ Example 8 executed successfully"),
+        Err(e) => println!("❌ Example 8 failed: {}", e),
+    }
+    
+    // Example 9: Counting loop with reassignment
+    let example9 = r#"
+workflow "CounterExample" {
+    var count = 0
+    
+    step 1: while (count < 3) {
+        step 2: print("Iteration " + count)
+        count = count + 1
+    }
+    step 3: print("Final count: " + count)
+}
+"#;
+    
+    println!("\n📝 Example 9: Variable Assignment");
+    println!("{}", example9);
+    
+    match run_dsl_example(example9) {
+        Ok(_) => println!("✅ Example 9 executed successfully"),
+        Err(e) => println!("❌ Example 9 failed: {}", e),
+    }
+    
+    // Example 10: Overflow is an error rather than a silent wrap
+    let example10 = r#"
+workflow "OverflowExample" {
+    let big = 9223372036854775807
+    
+    step 1: print("Doubled: " + big * 2)
+}
+"#;
+    
+    println!("\n📝 Example 10: Integer Overflow");
+    println!("{}", example10);
+    
+    match run_dsl_example(example10) {
+        Ok(_) => println!("❌ Example 10 should have reported an overflow"),
+        Err(e) => println!("✅ Example 10 rejected the overflow: {}", e),
+    }
+    // Numbers are f64, so the limit is 2^53 - 1: in-range operands whose result leaves it
+    // overflow, while an operand already past it is reported as outside the safe range
+    let overflows = [
+        ("9007199254740991 * 2", "Integer overflow in 9007199254740991 * 2"),
+        ("-9007199254740991 - 1", "Integer overflow in -9007199254740991 - 1"),
+        ("10000000000000000 + 0", "10000000000000000 is outside the safe integer range of ±9007199254740991"),
+    ];
+    for (expression, expected) in overflows {
+        let source = format!("workflow \"OverflowExample\" {{\n    step 1: print({})\n}}", expression);
+        match run_dsl_example(&source) {
+            Err(e) if e.to_string().contains(expected) => println!("✅ Example 10 rejected {}: {}", expression, e),
+            other => println!("❌ Example 10 expected '{}' for {}, got {:?}", expected, expression, other.err()),
+        }
+    }
+    match run_dsl_example("workflow \"OverflowExample\" {\n    step 1: print(9007199254740990 + 1)\n}") {
+        Ok(_) => println!("✅ Example 10 allowed a result of exactly 9007199254740991"),
+        Err(e) => println!("❌ Example 10 rejected an in-range sum: {}", e),
+    }
+    
+    // Example 11: For-each over a list of URLs
+    let example11 = r#"
+workflow "ForEachExample" {
+    var fetched = 0
+    
+    step 1: for url in ["https://api.example.com/a", "https://api.example.com/b", "https://api.example.com/c"] {
+        step 2: fetch(url)
+        fetched = fetched + 1
+    }
+    step 3: if (fetched == 3) {
+        step 4: print("Fetched all " + fetched + " URLs")
+    } else {
+        step 5: print("Expected 3 fetches, got " + fetched)
+    }
+}
+"#;
+    
+    println!("\n📝 Example 11: For-Each Loop");
+    println!("{}", example11);
+    
+    match run_dsl_example(example11) {
+        Ok(_) => println!("✅ Example 11 executed successfully"),
+        Err(e) => println!("❌ Example 11 failed: {}", e),
+    }
+    
+    // Example 12: Comments around and inside workflows
+    let example12 = r#"// Header comment before anything else
+// spanning two lines
+workflow "CommentedExample" {
+    // Comments can sit between steps
+    step 1: print("Comments are ignored") // and after them
+}
+// Trailing comment after the last workflow"#;
+    
+    println!("\n📝 Example 12: Comments");
+    println!("{}", example12);
+    
+    match parse_dsl_example(example12) {
+        Ok(ast) if ast.workflows.len() == 1 && ast.workflows[0].steps.len() == 1 => {
+            match run_dsl_example(example12) {
+                Ok(_) => println!("✅ Example 12 executed successfully"),
+                Err(e) => println!("❌ Example 12 failed: {}", e),
+            }
+        }
+        Ok(ast) => println!("❌ Example 12 parsed unexpectedly: {:?}", ast),
+        Err(e) => println!("❌ Example 12 failed: {}", e),
+    }
+    
+    // Example 13: Serializing values to strings
+    let example13 = r#"
+workflow "ExportExample" {
+    step 1: set(null, "name", "Ada")
+    step 2: set(step 1.data, "role", "admin, owner")
+    step 3: set(null, "name", "Lin")
+    step 4: to_json(step 2.data)
+    step 5: to_csv([step 2.data, step 3.data])
+    step 6: to_yaml(step 2.data)
+    step 7: if (step 4.data == '{"name":"Ada","role":"admin, owner"}') {
+        step 8: print("JSON export matches")
+    }
+    step 9: print(step 5.data)
+}
+"#;
+    
+    println!("\n📝 Example 13: Export Commands");
+    println!("{}", example13);
+    
+    match run_dsl_example(example13) {
+        Ok(_) => println!("✅ Example 13 executed successfully"),
+        Err(e) => println!("❌ Example 13 failed: {}", e),
+    }
+    
+    // Example 14: Collecting results instead of printing them
+    let example14 = r#"
+let greeting = "Hello"
+
+workflow "ReportExample" {
+    step 1: print(greeting + " report")
+    step 2: fetch("https://api.example.com/report")
+}
+"#;
+    
+    println!("\n📝 Example 14: Execution Report");
+    println!("{}", example14);
+    
+    match run_dsl_with_report(example14) {
+        Ok(report) => {
+            let step1 = report.step("ReportExample", 1).map(|result| result.data.to_display_string());
+            let step2 = report.step("ReportExample", 2).map(|result| result.status);
+            if report.workflows == ["ReportExample"]
+                && report.steps["ReportExample"].len() == 2
+                && step1.as_deref() == Some("Hello report")
+                && step2 == Some(200)
+                && report.variables.get("greeting").map(|value| value.to_display_string()).as_deref() == Some("Hello")
+            {
+                println!("✅ Example 14 report: {}", serde_json::to_string(&report).unwrap_or_default());
+            } else {
+                println!("❌ Example 14 report has unexpected contents: {:?}", report);
+            }
+        }
+        Err(e) => println!("❌ Example 14 failed: {}", e),
+    }
+    
+    // Example 15: Dry run records side effects instead of performing them
+    let example15 = r#"
+workflow "DryRunExample" {
+    step 1: fetch("https://api.example.com/orders")
+    step 2: send_email("ops@example.com", "Orders synced")
+    step 3: print("Print still echoes in a dry run")
+}
+"#;
+    
+    println!("\n📝 Example 15: Dry Run");
+    println!("{}", example15);
+    
+    let dry_run = Lexer::new(example15).tokenize()
+        .and_then(|tokens| Parser::new(tokens).parse())
+        .and_then(|ast| Executor::new_dry_run().execute(&ast));
+    match dry_run {
+        Ok(report) => {
+            let planned: Vec<&str> = report.planned_actions.iter().map(|action| action.command.as_str()).collect();
+            let fetched = report.step("DryRunExample", 1).map(|result| result.data.to_display_string());
+            if planned == ["fetch", "send_email"] && fetched.as_deref() == Some("null") {
+                println!("✅ Example 15 planned {} actions without performing them", planned.len());
+            } else {
+                println!("❌ Example 15 unexpected dry run: {:?}", report.planned_actions);
+            }
+        }
+        Err(e) => println!("❌ Example 15 failed: {}", e),
+    }
+    
+    // Example 16: Referring to the previous step
+    let example16 = r#"
+workflow "PrevExample" {
+    step 1: fetch("https://api.example.com/prices")
+    step 2: print(prev.data)
+    step 3: print("Printed: " + prev.data)
+}
+"#;
+    
+    println!("\n📝 Example 16: Previous Step Reference");
+    println!("{}", example16);
+    
+    match run_dsl_with_report(example16) {
+        Ok(report) if report.steps["PrevExample"][&3].data.to_display_string()
+            == format!("Printed: {}", report.steps["PrevExample"][&1].data) => {
+            println!("✅ Example 16 executed successfully");
+        }
+        Ok(report) => println!("❌ Example 16 unexpected results: {:?}", report.steps),
+        Err(e) => println!("❌ Example 16 failed: {}", e),
+    }
+    
+    match run_dsl_example(r#"workflow "PrevFirst" { step 1: print(prev.data) }"#) {
+        Ok(_) => println!("❌ Example 16 should reject 'prev' in the first step"),
+        Err(e) => println!("✅ Example 16 rejected 'prev' in the first step: {}", e),
+    }
+    
+    // Example 17: Static analysis flags a const shadowed inside a branch
+    let example17 = r#"
+workflow "ConstShadowExample" {
+    const limit = 10
+    
+    step 1: if (limit > 5) {
+        const limit = 20
+        step 2: print("Limit is " + limit)
+    }
+}
+"#;
+    
+    println!("\n📝 Example 17: Const Shadowing Lint");
+    println!("{}", example17);
+    
+    match parse_dsl_example(example17) {
+        Ok(ast) => {
+            let diagnostics = analyze_program(&ast);
+            if diagnostics.iter().any(|diagnostic| diagnostic.message == "Constant 'limit' is shadowed in a nested scope") {
+                println!("✅ Example 17 warned: {}", diagnostics[0].message);
+            } else {
+                println!("❌ Example 17 missing const shadowing warning: {:?}", diagnostics);
+            }
+        }
+        Err(e) => println!("❌ Example 17 failed: {}", e),
+    }
+    
+    // Example 18: Structured errors carry the source location
+    println!("\n📝 Example 18: Structured Errors");
+    
+    let cases = [
+        ("workflow \"Broken\" {\n    step 1 print(\"missing colon\")\n}", "unexpected token"),
+        ("workflow \"Broken\" {\n    step 1: print(\"never closed)\n}", "unterminated string"),
+        ("workflow \"Broken\" {\n    step 1: print(missing)\n}", "undefined variable"),
+    ];
+    for (dsl_code, kind) in cases {
+        let error = match run_dsl_example(dsl_code) {
+            Ok(_) => {
+                println!("❌ Example 18 expected an {} error", kind);
+                continue;
+            }
+            Err(e) => e,
+        };
+        match (error.downcast_ref::<DslError>(), kind) {
+            (Some(DslError::UnexpectedToken { line: 2, column: 12, .. }), "unexpected token")
+            | (Some(DslError::UnterminatedString { line: 2, column: 19 }), "unterminated string")
+            | (Some(DslError::UndefinedVariable { .. }), "undefined variable") => {
+                println!("✅ Example 18 {} error: {}", kind, error);
+            }
+            (other, _) => println!("❌ Example 18 expected an {} error, got {:?}", kind, other),
+        }
+    }
+    
+    // Example 19: Triple-quoted strings with an escaped triple quote
+    let example19 = r#"
+workflow "TripleQuoteExample" {
+    step 1: generate("""Explain Python docstrings, which look like \"""this\""".
+Keep "quotes" and 'apostrophes' as they are.""")
+}
+"#;
+    
+    println!("\n📝 Example 19: Triple-Quoted Strings");
+    println!("{}", example19);
+    
+    match run_dsl_with_report(example19) {
+        Ok(report) => {
+            let prompt = match &report.steps["TripleQuoteExample"][&1].data {
+                Value::Object(fields) => fields.get("content").map(Value::to_display_string).unwrap_or_default(),
+                other => other.to_display_string(),
+            };
+            if prompt.contains("look like \"\"\"this\"\"\".\nKeep \"quotes\"") {
+                println!("✅ Example 19 kept the embedded triple quotes: {}", prompt);
+            } else {
+                println!("❌ Example 19 unexpected prompt: {}", prompt);
+            }
+        }
+        Err(e) => println!("❌ Example 19 failed: {}", e),
+    }
+    
+    // Example 20: AI commands and conditionals through the library crate
+    let example20 = r#"
+workflow "LibraryExample" {
+    let topic = "market trends"
+    
+    step 1: input("topic", "text", "Enter a topic")
+    step 2: validate(step 1, "required")
+    step 3: if (step 2.valid) {
+        step 4: generate("Summarize " + topic, "mistral-small-latest", "0.5")
+        step 5: output(step 4, "pdf", "Summary")
+    } else {
+        step 6: print("Invalid input")
+    }
+}
+"#;
+    
+    println!("\n📝 Example 20: AI Commands From The Library");
+    println!("{}", example20);
+    
+    match run_dsl_with_report(example20) {
+        Ok(report) if report.step("LibraryExample", 5).is_some() && report.step("LibraryExample", 6).is_none() => {
+            println!("✅ Example 20 ran the AI commands in the taken branch");
+        }
+        Ok(report) => println!("❌ Example 20 ran unexpected steps: {:?}", report.steps.keys().collect::<Vec<_>>()),
+        Err(e) => println!("❌ Example 20 failed: {}", e),
+    }
+    
+    // Example 21: Renaming a variable across a program
+    let example21 = r#"
+let base_url = "https://api.example.com"
+
+workflow "RenameExample" {
+    step 1: fetch(base_url + "/prices")
+    step 2: if (step 1.success) {
+        let base_url = "https://mirror.example.com"
+        step 3: fetch(base_url + "/prices")
+    }
+    step 4: print("Fetched from " + base_url)
+}
+"#;
+    
+    println!("\n📝 Example 21: Rename Variable");
+    println!("{}", example21);
+    
+    let renamed = parse_dsl_example(example21).and_then(|mut ast| {
+        ast.rename_variable("base_url", "api_root")?;
+        Ok(ast)
+    });
+    match renamed {
+        Ok(ast) => {
+            let json = serde_json::to_string(&ast).unwrap_or_default();
+            // The program binding and its two references are renamed, the shadowing block keeps its own
+            if json.matches("api_root").count() == 3 && json.matches("base_url").count() == 2 {
+                println!("✅ Example 21 renamed every reference to the program-level base_url");
+            } else {
+                println!("❌ Example 21 unexpected rename result: {}", json);
+            }
+        }
+        Err(e) => println!("❌ Example 21 failed: {}", e),
+    }
+    
+    // Example 22: Workflow variables shadow program-level ones
+    let example22 = r#"
+const region = "us-east"
+
+workflow "RegionalExample" {
+    let region = "eu-west"
+    
+    step 1: print("Deploying to " + region)
+}
+
+workflow "DefaultExample" {
+    step 2: print("Deploying to " + region)
+}
+"#;
+    
+    println!("\n📝 Example 22: Workflow-Local Variables");
+    println!("{}", example22);
+    
+    match run_dsl_with_report(example22) {
+        Ok(report) if report.steps["RegionalExample"][&1].data.to_display_string() == "Deploying to eu-west"
+            && report.steps["DefaultExample"][&2].data.to_display_string() == "Deploying to us-east" => {
+            println!("✅ Example 22 used the workflow-local region and then the global one");
+        }
+        Ok(report) => println!("❌ Example 22 unexpected results: {:?}", report.steps),
+        Err(e) => println!("❌ Example 22 failed: {}", e),
+    }
+    
+    // Example 23: Logical operators as values
+    let example23 = r#"
+let cached = "yes"
+let ready = cached || never_defined
+let both = cached && 0
+
+workflow "LogicalValueExample" {
+    step 1: print("ready=" + ready + " both=" + both)
+}
+"#;
+    
+    println!("\n📝 Example 23: Logical Values");
+    println!("{}", example23);
+    
+    // `never_defined` would be an error if `||` evaluated its right side
+    match run_dsl_with_report(example23) {
+        Ok(report) if report.variables.get("ready") == Some(&Value::Bool(true))
+            && report.variables.get("both") == Some(&Value::Bool(false)) => {
+            println!("✅ Example 23 bound booleans with short-circuiting");
+        }
+        Ok(report) => println!("❌ Example 23 unexpected variables: {:?}", report.variables),
+        Err(e) => println!("❌ Example 23 failed: {}", e),
+    }
+    
+    // Example 24: Each workflow has its own scope
+    let example24 = r#"
+workflow "First" {
+    let x = "first"
+    step 1: print(x)
+}
+
+workflow "Second" {
+    let x = "second"
+    step 2: print(x)
+    step 3: if (x == "second") {
+        const x = "inner"
+        step 4: print(x)
+    }
+    step 5: print(x)
+}
+"#;
+    
+    println!("\n📝 Example 24: Workflow Scopes");
+    println!("{}", example24);
+    
+    match run_dsl_with_report(example24) {
+        Ok(report) => {
+            let printed: Vec<String> = [("First", 1), ("Second", 2), ("Second", 4), ("Second", 5)]
+                .iter()
+                .map(|(workflow, id)| report.steps[*workflow][id].data.to_display_string())
+                .collect();
+            if printed == ["first", "second", "inner", "second"] && !report.variables.contains_key("x") {
+                println!("✅ Example 24 kept each workflow's x to itself");
+            } else {
+                println!("❌ Example 24 unexpected values: {:?}", printed);
+            }
+        }
+        Err(e) => println!("❌ Example 24 failed: {}", e),
+    }
+    
+    match run_dsl_with_report(&format!("{}\nworkflow \"Third\" {{ step 6: print(x) }}", example24)) {
+        Ok(_) => println!("❌ Example 24 leaked x into a later workflow"),
+        Err(e) => println!("✅ Example 24 later workflow cannot see x: {}", e),
+    }
+    
+    // Example 26: Flattening workflow calls
+    let example26 = r#"
+workflow "Main" {
+    step 1: print("start")
+    step 2: run("Middle")
+    step 3: print("done")
+}
+
+workflow "Middle" {
+    step 1: fetch("https://api.example.com/orders")
+    step 2: run("Leaf", step 1.data)
+}
+
+workflow "Leaf" {
+    let payload = "nothing"
+    
+    step 1: log(payload)
+    step 2: notify("Logged " + step 1.data)
+}
+"#;
+    
+    println!("\n📝 Example 26: Inline Workflow Calls");
+    println!("{}", example26);
+    
+    match parse_dsl_example(example26).and_then(|ast| ast.inline_workflow_calls()) {
+        Ok(flat) => {
+            let sequence: Vec<String> = flat.workflows[0].steps
+                .iter()
+                .map(|step| match &step.content {
+                    StepContent::Command(command) => format!("{}:{}", step.id, command.name),
+                    StepContent::Declaration(variable) => format!("{}:let {} = {}", step.id, variable.name, variable.value),
+                    _ => format!("{}:?", step.id),
+                })
+                .collect();
+            let expected = ["1:print", "4:fetch", "0:let payload = step 4.data", "5:log", "6:notify", "3:print"];
+            let notify_argument = match &flat.workflows[0].steps[4].content {
+                StepContent::Command(command) => command.arguments[0].to_string(),
+                _ => String::new(),
+            };
+            if sequence == expected && notify_argument == "\"Logged \" + step 5.data" {
+                println!("✅ Example 26 flattened to {:?}", sequence);
+            } else {
+                println!("❌ Example 26 unexpected flat steps: {:?}, {}", sequence, notify_argument);
+            }
+        }
+        Err(e) => println!("❌ Example 26 failed: {}", e),
+    }
+    
+    let recursive = r#"
+workflow "Ping" { step 1: run("Pong") }
+workflow "Pong" { step 1: run("Ping") }
+"#;
+    match parse_dsl_example(recursive).and_then(|ast| ast.inline_workflow_calls()) {
+        Ok(_) => println!("❌ Example 26 should reject recursive calls"),
+        Err(e) => println!("✅ Example 26 rejected recursion: {}", e),
+    }
+    
+    // Example 27: Semantic tokens for editor highlighting
+    let example27 = "workflow \"Highlight\" {\n    step 1: fetch(x)\n    step 2: summarize(step 1.data)\n}";
+    
+    println!("\n📝 Example 27: Semantic Tokens");
+    println!("{}", example27);
+    
+    match semantic_tokens(example27) {
+        Ok(tokens) => {
+            let category_of = |text: &str| {
+                tokens.iter()
+                    .find(|token| example27.chars().skip(token.range.start).take(token.range.len()).collect::<String>() == text)
+                    .map(|token| token.category)
+            };
+            if category_of("fetch") == Some(SemanticCategory::Command)
+                && category_of("x") == Some(SemanticCategory::Variable)
+                && category_of("summarize") == Some(SemanticCategory::Command)
+                && category_of("data") == Some(SemanticCategory::Property)
+            {
+                println!("✅ Example 27 categorized {} tokens", tokens.len());
+            } else {
+                println!("❌ Example 27 unexpected categories: {:?}", tokens);
+            }
+        }
+        Err(e) => println!("❌ Example 27 failed: {}", e),
+    }
+    
+    // Example 28: Named command arguments
+    let example28 = r#"
+workflow "NamedArgumentsExample" {
+    step 1: output("report", file: "Q3 Summary")
+    step 2: output(file: "Q4 Summary", data: "forecast", format: "csv")
+}
+"#;
+    
+    println!("\n📝 Example 28: Named Arguments");
+    println!("{}", example28);
+    
+    match run_dsl_with_report(example28) {
+        Ok(report) => {
+            let first = report.steps["NamedArgumentsExample"][&1].data.to_display_string();
+            let second = report.steps["NamedArgumentsExample"][&2].data.to_display_string();
+            if first == r#"{"exported":"report","format":"text","file":"Q3 Summary"}"#
+                && second == r#"{"exported":"forecast","format":"csv","file":"Q4 Summary"}"#
+            {
+                println!("✅ Example 28 mixed positional and named arguments");
+            } else {
+                println!("❌ Example 28 unexpected outputs: {} / {}", first, second);
+            }
+        }
+        Err(e) => println!("❌ Example 28 failed: {}", e),
+    }
+    
+    match run_dsl_example(r#"workflow "Typo" { step 1: output("report", fil: "Q3") }"#) {
+        Ok(_) => println!("❌ Example 28 should reject unknown argument names"),
+        Err(e) => println!("✅ Example 28 rejected an unknown argument: {}", e),
+    }
+    
+    // Example 29: validate enforces its rules
+    println!("\n📝 Example 29: Validation Rules");
+    
+    let cases = [
+        ("\"Ada\"", "required", true),
+        ("\"  \"", "required", false),
+        ("\"ada@example.com\"", "email", true),
+        ("\"ada@example\"", "email", false),
+        ("\"42.5\"", "number", true),
+        ("\"forty\"", "number", false),
+        ("18", "min:18", true),
+        ("17", "min:18", false),
+        ("100", "max:100", true),
+        ("101", "max:100", false),
+    ];
+    for (input, rule, expected) in cases {
+        let code = format!(r#"workflow "Validate" {{ step 1: validate({}, "{}") }}"#, input, rule);
+        match run_dsl_with_report(&code) {
+            Ok(report) => {
+                let step = &report.steps["Validate"][&1];
+                let valid = matches!(&step.data, Value::Object(fields) if fields.get("valid") == Some(&Value::Bool(expected)));
+                if step.success == expected && valid {
+                    println!("✅ Example 29 {} with {} -> {}", rule, input, if expected { "valid" } else { "invalid" });
+                } else {
+                    println!("❌ Example 29 {} with {} gave success={} data={}", rule, input, step.success, step.data);
+                }
+            }
+            Err(e) => println!("❌ Example 29 {} with {} failed: {}", rule, input, e),
+        }
+    }
+    
+    let halting = r#"
+workflow "Signup" {
+    step 1: validate("not-an-email", "email")
+    step 2: print("should not run")
+}
+"#;
+    let halted = Lexer::new(halting).tokenize()
+        .and_then(|tokens| Parser::new(tokens).parse())
+        .and_then(|ast| Executor::new().with_verbose(false).with_halt_on_invalid(true).execute(&ast));
+    match halted {
+        Ok(_) => println!("❌ Example 29 should halt on a failed validation"),
+        Err(e) => println!("✅ Example 29 halted the workflow: {}", e),
+    }
+    
+    // Example 30: Step metadata
+    let example30 = r#"
+workflow "MetadataExample" {
+    step 1: fetch("https://api.example.com/quotes")
+    step 2: print(step 1.meta.url)
+    step 3: print(step 1.meta.missing)
+}
+"#;
+    
+    println!("\n📝 Example 30: Step Metadata");
+    println!("{}", example30);
+    
+    match run_dsl_with_report(example30) {
+        Ok(report) => {
+            let url = report.steps["MetadataExample"][&1].metadata.get("url").map(Value::to_display_string);
+            let printed = report.steps["MetadataExample"][&2].data.to_display_string();
+            let missing = report.steps["MetadataExample"][&3].data.to_display_string();
+            if url.as_deref() == Some("https://api.example.com/quotes") && printed == "https://api.example.com/quotes" && missing == "null" {
+                println!("✅ Example 30 read fetch metadata through step 1.meta");
+            } else {
+                println!("❌ Example 30 unexpected metadata: {:?} / {} / {}", url, printed, missing);
+            }
+        }
+        Err(e) => println!("❌ Example 30 failed: {}", e),
+    }
+    
+    // Example 31: Independent workflows keep running after one fails
+    let example31 = r#"
+workflow "Broken" {
+    step 1: print(missing_variable)
+}
+
+workflow "Independent" {
+    step 2: print("still running")
+}
+"#;
+    
+    println!("\n📝 Example 31: Continue Workflows On Error");
+    println!("{}", example31);
+    
+    let batch = Lexer::new(example31).tokenize()
+        .and_then(|tokens| Parser::new(tokens).parse())
+        .and_then(|ast| Executor::new().with_continue_workflows_on_error(true).execute(&ast));
+    match batch {
+        Ok(report) => {
+            let failed: Vec<&str> = report.failed_workflows.iter().map(|failure| failure.workflow.as_str()).collect();
+            if failed == ["Broken"] && report.step("Independent", 2).map(|result| result.data.to_display_string()).as_deref() == Some("still running") {
+                println!("✅ Example 31 recorded the failed workflow and ran the next one");
+            } else {
+                println!("❌ Example 31 unexpected report: failed {:?}, steps {:?}", failed, report.steps.keys().collect::<Vec<_>>());
+            }
+        }
+        Err(e) => println!("❌ Example 31 should not abort the program: {}", e),
+    }
+    
+    match run_dsl_example(example31) {
+        Ok(_) => println!("❌ Example 31 should stop at the first failure by default"),
+        Err(_) => println!("✅ Example 31 stops at the first failed workflow by default"),
+    }
+    
+    // Example 32: Error policy for failed steps
+    let example32 = r#"
+workflow "Signup" {
+    step 1: validate("not-an-email", "email")
+    step 2: send_email("not-an-email", "Welcome")
+}
+
+workflow "Cleanup" {
+    step 3: print("cleanup ran")
+}
+"#;
+    
+    println!("\n📝 Example 32: Error Policy");
+    println!("{}", example32);
+    
+    let run_with_policy = |policy: ErrorPolicy| Lexer::new(example32).tokenize()
+        .and_then(|tokens| Parser::new(tokens).parse())
+        .and_then(|ast| Executor::new().with_verbose(false).with_error_policy(policy).execute(&ast));
+    
+    match run_with_policy(ErrorPolicy::Continue) {
+        Ok(report) if report.step("Signup", 2).is_some() => println!("✅ Example 32 Continue ran the step after the failure"),
+        Ok(_) => println!("❌ Example 32 Continue skipped step 2"),
+        Err(e) => println!("❌ Example 32 Continue failed: {}", e),
+    }
+    match run_with_policy(ErrorPolicy::HaltWorkflow) {
+        Ok(report) if report.step("Signup", 2).is_none() && report.step("Cleanup", 3).is_some() && report.failed_workflows.len() == 1 => {
+            println!("✅ Example 32 HaltWorkflow skipped step 2 and still ran the next workflow")
+        }
+        Ok(report) => println!("❌ Example 32 HaltWorkflow ran steps {:?}", report.steps.keys().collect::<Vec<_>>()),
+        Err(e) => println!("❌ Example 32 HaltWorkflow should not abort the program: {}", e),
+    }
+    match run_with_policy(ErrorPolicy::HaltProgram) {
+        Err(e) if matches!(e.downcast_ref::<DslError>(), Some(DslError::StepFailed { step_id: 1, .. })) => {
+            println!("✅ Example 32 HaltProgram stopped at the failed step: {}", e)
+        }
+        Err(e) => println!("❌ Example 32 HaltProgram gave an unexpected error: {}", e),
+        Ok(_) => println!("❌ Example 32 HaltProgram should stop the program"),
+    }
+    
+    // Example 33: try/catch
+    let example33 = r#"
+workflow "Resilient" {
+    step 1: try {
+        step 2: validate("", "required")
+        step 3: print("unreachable")
+    } catch {
+        step 4: print("Fallback: " + error)
+    }
+    step 5: try {
+        step 6: print(undefined_name)
+    } catch (problem) {
+        step 7: print(problem)
+    }
+    step 8: try {
+        step 9: print("fine")
+    } catch {
+        step 10: print("not needed")
+    }
+}
+"#;
+    
+    println!("\n📝 Example 33: Try/Catch");
+    println!("{}", example33);
+    
+    match run_dsl_with_report(example33) {
+        Ok(report) => {
+            let fallback = report.step("Resilient", 4).map(|result| result.data.to_display_string()).unwrap_or_default();
+            let caught = report.step("Resilient", 7).map(|result| result.data.to_display_string()).unwrap_or_default();
+            if fallback == "Fallback: Step 2 failed: Validation failed: value is required"
+                && caught == "Step 6 (line 10): Undefined variable: undefined_name"
+                && report.step("Resilient", 3).is_none()
+                && report.step("Resilient", 9).is_some()
+                && report.step("Resilient", 10).is_none()
+            {
+                println!("✅ Example 33 ran catch blocks for a failed step and an evaluation error");
+            } else {
+                println!("❌ Example 33 unexpected results: {:?} / {:?} / steps {:?}", fallback, caught, report.steps.keys().collect::<Vec<_>>());
+            }
+        }
+        Err(e) => println!("❌ Example 33 failed: {}", e),
+    }
+    
+    // Example 34: Semantic version comparison
+    println!("\n📝 Example 34: Version Compare");
+    
+    let cases = [
+        ("1.10.0", "1.9.0", 1.0),
+        ("1.9.0", "1.10.0", -1.0),
+        ("v2.0", "2.0.0", 0.0),
+        ("1.0.0-alpha", "1.0.0", -1.0),
+        ("1.0.0-alpha.2", "1.0.0-alpha.10", -1.0),
+        ("1.0.0+build.5", "1.0.0", 0.0),
+    ];
+    for (left, right, expected) in cases {
+        let code = format!(r#"workflow "Versions" {{ step 1: version_compare("{}", "{}") }}"#, left, right);
+        match run_dsl_with_report(&code) {
+            Ok(report) if report.steps["Versions"][&1].data == Value::Number(expected) => {
+                println!("✅ Example 34 version_compare({}, {}) = {}", left, right, expected)
+            }
+            Ok(report) => println!("❌ Example 34 version_compare({}, {}) gave {}", left, right, report.steps["Versions"][&1].data),
+            Err(e) => println!("❌ Example 34 failed: {}", e),
+        }
+    }
+    
+    match run_dsl_with_report(r#"workflow "Versions" { step 1: version_compare("1.x", "1.0") }"#) {
+        Ok(report) if !report.steps["Versions"][&1].success => println!("✅ Example 34 rejected an invalid version: {}", report.steps["Versions"][&1].message),
+        Ok(_) => println!("❌ Example 34 should reject an invalid version"),
+        Err(e) => println!("❌ Example 34 failed: {}", e),
+    }
+    
+    // Example 35: Cached constants across runs of a compiled program
+    let example35 = r#"
+const window = 60 * 60 * 24 * 7 - 3600 + 2 * 3 * 5 * 7
+let greeting = "hello"
+const banner = greeting + " world"
+
+workflow "Batch" {
+    step 1: print(window)
+    step 2: print(banner)
+}
+"#;
+    
+    println!("\n📝 Example 35: Compiled Constants");
+    println!("{}", example35);
+    
+    match compile_dsl(example35) {
+        Ok(compiled) => {
+            // One executor for every run, the way a service reuses it
+            let mut executor = Executor::new().with_verbose(false);
+            let first = compiled.execute(&mut executor);
+            let second = compiled.execute(&mut executor);
+            match (first, second) {
+                (Ok(first), Ok(second)) => {
+                    let outputs = |report: &ExecutionReport| (
+                        report.steps["Batch"][&1].data.to_display_string(),
+                        report.steps["Batch"][&2].data.to_display_string(),
+                    );
+                    let expected = ("601410".to_string(), "hello world".to_string());
+                    if outputs(&first) == expected && outputs(&second) == expected
+                        && compiled.constant_evaluations() == 2
+                        && compiled.constants().keys().collect::<Vec<_>>() == ["window"]
+                    {
+                        println!("✅ Example 35 folded 'window' once and evaluated 'banner' per run");
+                    } else {
+                        println!(
+                            "❌ Example 35 unexpected results: {:?} / {:?}, {} evaluations",
+                            outputs(&first), outputs(&second), compiled.constant_evaluations()
+                        );
+                    }
+                }
+                (Err(e), _) | (_, Err(e)) => println!("❌ Example 35 failed: {}", e),
+            }
+        }
+        Err(e) => println!("❌ Example 35 failed to compile: {}", e),
+    }
+    
+    // Example 36: Step id validation
+    println!("\n📝 Example 36: Step Id Validation");
+    
+    let valid = r#"
+workflow "Checks" {
+    step 1: fetch("https://api.com/status")
+    step 2: if (step 1.status == 200) {
+        step 3: print(step 1.data)
+    } else if (step 1.status == 404) {
+        step 4: print("missing")
+    } else {
+        step 5: print("error")
+    }
+}
+"#;
+    let duplicate = r#"workflow "Twice" { step 1: print("a") step 1: print("b") }"#;
+    let forward = r#"workflow "Ahead" { step 1: print(step 2.data) step 2: print("later") }"#;
+    let undefined = r#"workflow "Missing" { step 1: print(step 99.data) }"#;
+    
+    match parse_dsl_example(valid).and_then(|program| program.validate()) {
+        Ok(()) => println!("✅ Example 36 accepted unique ids and earlier references"),
+        Err(e) => println!("❌ Example 36 rejected a valid program: {}", e),
+    }
+    for (code, expected) in [
+        (duplicate, DslError::DuplicateStepId { step_id: 1, workflow: "Twice".to_string() }),
+        (forward, DslError::UndefinedStepReference { step_id: 2, workflow: "Ahead".to_string() }),
+        (undefined, DslError::UndefinedStepReference { step_id: 99, workflow: "Missing".to_string() }),
+    ] {
+        match parse_dsl_example(code).and_then(|program| program.validate()) {
+            Err(e) if e.downcast_ref::<DslError>() == Some(&expected) => println!("✅ Example 36 reported: {}", e),
+            Err(e) => println!("❌ Example 36 unexpected error: {}", e),
+            Ok(()) => println!("❌ Example 36 should reject: {}", code),
+        }
+    }
+    
+    // Example 37: Structured program diff
+    let before = r#"
+const base_url = "https://api.com"
+
+workflow "Report" {
+    step 1: fetch(base_url + "/sales")
+    step 2: print(step 1.data)
+}
+"#;
+    let after = r#"
+const base_url = "https://api.com"
+
+workflow "Report" {
+    step 1: fetch(base_url + "/sales")
+    step 2: print(step 1.data)
+    step 3: notify("Report ready")
+}
+"#;
+    
+    println!("\n📝 Example 37: Program Diff");
+    
+    match (parse_dsl_example(before), parse_dsl_example(after)) {
+        (Ok(old), Ok(new)) => {
+            let changes = Program::diff(&old, &new);
+            if changes == [Change::StepAdded { workflow: "Report".to_string(), step_id: 3 }] {
+                println!("✅ Example 37 found a single change: {}", changes[0]);
+            } else {
+                println!("❌ Example 37 unexpected changes: {:?}", changes);
+            }
+            if Program::diff(&old, &old).is_empty() {
+                println!("✅ Example 37 found no changes between identical programs");
+            } else {
+                println!("❌ Example 37 reported changes between identical programs");
+            }
+        }
+        (Err(e), _) | (_, Err(e)) => println!("❌ Example 37 failed to parse: {}", e),
+    }
+    
+    // Example 38: Undefined variables found before execution
+    let example38 = r#"
+const base_url = "https://api.com"
+
+workflow "Lint" {
+    let total = 0
+    step 1: print(base_url + endpoint)
+    step 2: for item in [1, 2] {
+        total = total + item
+    }
+    step 3: print(item)
+}
+"#;
+    
+    println!("\n📝 Example 38: Undefined Variable Lint");
+    println!("{}", example38);
+    
+    match parse_dsl_example(example38) {
+        Ok(program) => {
+            let errors = validate_program(&program);
+            let expected = [
+                DslError::UndefinedVariable { name: "endpoint".to_string() },
+                DslError::UndefinedVariable { name: "item".to_string() },
+            ];
+            if errors == expected {
+                println!("✅ Example 38 found the undefined variables without running anything");
+            } else {
+                println!("❌ Example 38 unexpected errors: {:?}", errors);
+            }
+        }
+        Err(e) => println!("❌ Example 38 failed to parse: {}", e),
+    }
+    
+    // Example 39: Generation policy
+    let example39 = r#"
+workflow "Governed" {
+    let chosen = "gpt-unapproved"
+    step 1: generate("Summarize the quarter", "mistral-small-latest", "0.3")
+    step 2: generate("Write a poem", "gpt-unapproved", "0.5")
+    step 3: generate("Brainstorm", model: "mistral-small-latest", temperature: 1.5)
+    step 4: generate("Draft", chosen)
+}
+"#;
+    
+    println!("\n📝 Example 39: Generation Policy");
+    println!("{}", example39);
+    
+    let policy = GenerationPolicy::new(&["mistral-small-latest", "mistral-large-latest"], 0.0..=1.0);
+    match parse_dsl_example(example39) {
+        Ok(program) => {
+            let violations = check_generation_policy(&program, &policy);
+            let flagged: Vec<(u32, usize)> = violations.iter().map(|violation| (violation.step_id, violation.line)).collect();
+            if flagged == [(2, 5), (3, 6)] {
+                println!("✅ Example 39 flagged the disallowed literals: {}", violations[0]);
+            } else {
+                println!("❌ Example 39 unexpected violations: {:?}", violations);
+            }
+            
+            match Executor::new().with_verbose(false).with_generation_policy(policy).execute(&program) {
+                Ok(report) if report.steps["Governed"][&1].success && !report.steps["Governed"][&4].success => {
+                    println!("✅ Example 39 failed the step with a disallowed model at run time: {}", report.steps["Governed"][&4].message)
+                }
+                Ok(report) => println!("❌ Example 39 unexpected run: step 4 {}", report.steps["Governed"][&4].message),
+                Err(e) => println!("❌ Example 39 failed: {}", e),
+            }
+        }
+        Err(e) => println!("❌ Example 39 failed to parse: {}", e),
+    }
+    
+    // Example 40: Runtime errors name their step and line
+    let example40 = r#"
+workflow "Located" {
+    step 1: print("fine")
+    step 2: if (step 1.success) {
+        step 3: print("total: " + totl)
+    }
+}
+"#;
+    
+    println!("\n📝 Example 40: Runtime Error Locations");
+    println!("{}", example40);
+    
+    match run_dsl_with_report(example40) {
+        Ok(_) => println!("❌ Example 40 should fail on the misspelled variable"),
+        Err(e) if e.to_string() == "Step 3 (line 5): Undefined variable: totl"
+            && matches!(e.downcast_ref::<DslError>(), Some(DslError::UndefinedVariable { .. })) =>
+        {
+            println!("✅ Example 40 located the error: {}", e)
+        }
+        Err(e) => println!("❌ Example 40 unexpected error: {}", e),
+    }
+    
+    // Example 41: Execute a program from its JSON AST
+    let example41 = r#"
+const greeting = "Hello"
+
+workflow "RoundTrip" {
+    step 1: print(greeting + " from JSON")
+    step 2: if (step 1.success) {
+        step 3: to_json([1, 2, 3])
+    }
+}
+"#;
+    
+    println!("\n📝 Example 41: JSON Round Trip");
+    println!("{}", example41);
+    
+    let round_trip = parse_dsl_example(example41)
+        .and_then(|program| Ok(serde_json::to_string(&program)?))
+        .and_then(|json| run_program_json(&json));
+    match round_trip {
+        Ok(report) if report.steps["RoundTrip"][&1].data.to_display_string() == "Hello from JSON"
+            && report.steps["RoundTrip"][&3].data.to_display_string() == "[1,2,3]" =>
+        {
+            println!("✅ Example 41 executed the program deserialized from JSON")
+        }
+        Ok(report) => println!("❌ Example 41 unexpected steps: {:?}", report.steps),
+        Err(e) => println!("❌ Example 41 failed: {}", e),
+    }
+    
+    match run_program_json(r#"{"workflows": [{"name": "Broken"}"#) {
+        Ok(_) => println!("❌ Example 41 should reject malformed JSON"),
+        Err(e) => println!("✅ Example 41 rejected malformed JSON: {}", e),
+    }
+    
+    // Example 42: Run the workflows that parse and report the broken ones
+    let example42 = r#"
+workflow "Broken" {
+    step 1 print("missing colon")
+}
+
+workflow "Healthy" {
+    step 2: print("still runs")
+}
+"#;
+    
+    println!("\n📝 Example 42: Lenient Execution");
+    println!("{}", example42);
+    
+    match run_dsl_lenient(example42) {
+        Ok(run) => {
+            let ran = run.report.workflows.clone();
+            let located = run.parse_errors
+                .iter()
+                .any(|error| matches!(error.downcast_ref::<DslError>(), Some(DslError::UnexpectedToken { line: 3, .. })));
+            if ran == ["Healthy"] && run.parse_errors.len() == 1 && located && run.report.step("Healthy", 2).is_some() {
+                println!("✅ Example 42 ran 'Healthy' and reported: {}", run.parse_errors[0]);
+            } else {
+                println!("❌ Example 42 unexpected run: {:?} / {:?}", ran, run.parse_errors);
+            }
+        }
+        Err(e) => println!("❌ Example 42 failed: {}", e),
+    }
+    
+    // Example 43: AST visitors
+    let example43 = r#"
+workflow "Visited" {
+    step 1: fetch("https://api.com/a")
+    step 2: if (step 1.success) {
+        step 3: log("ok")
+        step 4: for item in [1, 2] {
+            step 5: log(item)
+        }
+    } else {
+        step 6: notify("failed")
+    }
+    on_error {
+        step 7: log(error)
+    }
+}
+"#;
+    
+    println!("\n📝 Example 43: AST Visitors");
+    println!("{}", example43);
+    
+    match parse_dsl_example(example43) {
+        Ok(mut program) => {
+            let mut counter = CommandCounter::default();
+            counter.visit_program(&program);
+            let expected: BTreeMap<String, usize> = [("fetch", 1), ("log", 3), ("notify", 1)]
+                .into_iter()
+                .map(|(name, count)| (name.to_string(), count))
+                .collect();
+            if counter.counts == expected {
+                println!("✅ Example 43 counted commands by name: {:?}", counter.counts);
+            } else {
+                println!("❌ Example 43 unexpected counts: {:?}", counter.counts);
+            }
+            
+            CommandRenamer { from: "log", to: "print" }.visit_program_mut(&mut program);
+            let mut counter = CommandCounter::default();
+            counter.visit_program(&program);
+            if counter.counts.get("print") == Some(&3) && !counter.counts.contains_key("log") {
+                println!("✅ Example 43 rewrote every log command into print");
+            } else {
+                println!("❌ Example 43 rewrite left: {:?}", counter.counts);
+            }
+        }
+        Err(e) => println!("❌ Example 43 failed to parse: {}", e),
+    }
+    
+    // Example 44: Step results inside loops belong to their iteration
+    let example44 = r#"
+workflow "PerIteration" {
+    step 1: for symbol in ["AAPL", "MSFT", "NVDA"] {
+        step 2: print("quote for " + symbol)
+        step 3: print(step 2.data)
+    }
+}
+"#;
+    
+    println!("\n📝 Example 44: Per-Iteration Step Results");
+    println!("{}", example44);
+    
+    match run_dsl_with_report(example44) {
+        Ok(report) => {
+            let seen: Vec<String> = report.loop_results
+                .get("PerIteration")
+                .and_then(|results| results.get(&3))
+                .map(|results| results.iter().map(|result| result.data.to_display_string()).collect())
+                .unwrap_or_default();
+            if seen == ["quote for AAPL", "quote for MSFT", "quote for NVDA"] {
+                println!("✅ Example 44 each iteration read its own step 2: {:?}", seen);
+            } else {
+                println!("❌ Example 44 unexpected iterations: {:?}", seen);
+            }
+        }
+        Err(e) => println!("❌ Example 44 failed: {}", e),
+    }
+    
+    // Reading a body step before it has run in the current iteration doesn't see the previous one
+    let stale = r#"
+workflow "Stale" {
+    let count = 0
+    step 1: while (count < 2) {
+        count = count + 1
+        step 2: if (count > 1) {
+            step 3: print(step 4.data)
+        }
+        step 4: print(count)
+    }
+}
+"#;
+    match run_dsl_with_report(stale) {
+        Err(e) if matches!(e.downcast_ref::<DslError>(), Some(DslError::StepNotFound { step_id: 4 })) => {
+            println!("✅ Example 44 kept the previous iteration's step 4 out of reach: {}", e)
+        }
+        Err(e) => println!("❌ Example 44 unexpected error: {}", e),
+        Ok(_) => println!("❌ Example 44 should not read step 4 from the previous iteration"),
+    }
+    
+    // Example 45: Snapshots of a running executor, read from another thread
+    let example45 = r#"
+workflow "Snapshot" {
+    let symbol = "AAPL"
+    step 1: print("quote for " + symbol)
+    step 2: sleep(300)
+    step 3: print("done")
+}
+"#;
+    
+    println!("\n📝 Example 45: Executor Snapshots");
+    println!("{}", example45);
+    
+    match Lexer::new(example45).tokenize().and_then(|tokens| Parser::new(tokens).parse()) {
+        Ok(ast) => {
+            let mut executor = Executor::new().with_verbose(false);
+            let handle = executor.snapshot_handle();
+            let watcher = std::thread::spawn(move || {
+                // Poll until the executor is inside the sleep
+                for _ in 0..200 {
+                    let snapshot = handle.snapshot();
+                    if snapshot.current_step == Some(2) {
+                        return Some(snapshot);
+                    }
+                    std::thread::sleep(std::time::Duration::from_millis(5));
+                }
+                None
+            });
+            let run = executor.execute(&ast);
+            match (run, watcher.join().ok().flatten()) {
+                (Ok(_), Some(snapshot))
+                    if snapshot.workflow.as_deref() == Some("Snapshot")
+                        && snapshot.step_results.get("Snapshot").is_some_and(|results| results.keys().eq([1].iter()))
+                        && snapshot.variables.get("symbol") == Some(&Value::String("AAPL".to_string())) =>
+                {
+                    println!("✅ Example 45 snapshot mid-run: {}", serde_json::to_string(&snapshot).unwrap_or_default())
+                }
+                (Ok(_), Some(snapshot)) => println!("❌ Example 45 unexpected snapshot: {:?}", snapshot),
+                (Ok(_), None) => println!("❌ Example 45 never saw step 2 running"),
+                (Err(e), _) => println!("❌ Example 45 failed: {}", e),
+            }
+        }
+        Err(e) => println!("❌ Example 45 failed to parse: {}", e),
+    }
+    
+    // Example 46: Source spans on AST nodes
+    let example46 = r#"
+const price = 10
+workflow "Spans" {
+    step 1: print("total: " + price * 2)
+}
+"#;
+    
+    println!("\n📝 Example 46: Source Spans");
+    println!("{}", example46);
+    
+    match Lexer::new(example46).tokenize().and_then(|tokens| Parser::new(tokens).parse()) {
+        Ok(ast) => {
+            let step = &ast.workflows[0].steps[0];
+            let spans = match &step.content {
+                StepContent::Command(command) => match &command.arguments[0].kind {
+                    ExpressionKind::BinaryExpression { right, .. } => Some((
+                        ast.variables[0].span,
+                        step.span,
+                        command.span,
+                        command.arguments[0].span,
+                        right.span,
+                    )),
+                    _ => None,
+                },
+                _ => None,
+            };
+            match spans {
+                Some((declaration, step, command, argument, product))
+                    if declaration == Span::new(2, 1, 2, 17)
+                        && step == Span::new(4, 5, 4, 41)
+                        && command == Span::new(4, 13, 4, 41)
+                        && argument == Span::new(4, 19, 4, 40)
+                        && product == Span::new(4, 31, 4, 40) =>
+                {
+                    println!("✅ Example 46 `price * 2` spans {:?}", product)
+                }
+                other => println!("❌ Example 46 unexpected spans: {:?}", other),
+            }
+        }
+        Err(e) => println!("❌ Example 46 failed to parse: {}", e),
+    }
+    
+    // Example 47: Schedule attributes for host schedulers
+    let example47 = r#"
+@schedule("0 9 * * *")
+workflow "MorningReport" {
+    step 1: print("Good morning")
+}
+"#;
+    
+    println!("\n📝 Example 47: Workflow Schedules");
+    println!("{}", example47);
+    
+    match parse_dsl_example(example47) {
+        Ok(ast) if ast.workflows[0].schedule.as_deref() == Some("0 9 * * *") => {
+            println!("✅ Example 47 parsed schedule {:?}", ast.workflows[0].schedule);
+            #[cfg(feature = "cron")]
+            {
+                let diagnostics = analyze_program(&ast);
+                if diagnostics.is_empty() {
+                    println!("✅ Example 47 valid cron has no diagnostics");
+                } else {
+                    println!("❌ Example 47 valid cron reported: {:?}", diagnostics);
+                }
+            }
+        }
+        Ok(ast) => println!("❌ Example 47 unexpected schedule: {:?}", ast.workflows[0].schedule),
+        Err(e) => println!("❌ Example 47 failed: {}", e),
+    }
+    
+    #[cfg(feature = "cron")]
+    match parse_dsl_example("@schedule(\"0 25 * * *\")\nworkflow \"Late\" {\n    step 1: print(\"never\")\n}") {
+        Ok(ast) => match analyze_program(&ast).first() {
+            Some(diagnostic) if diagnostic.message.starts_with("Workflow 'Late' has an invalid schedule '0 25 * * *'") => {
+                println!("✅ Example 47 rejected hour 25: {}", diagnostic.message)
+            }
+            other => println!("❌ Example 47 expected an invalid schedule error, got {:?}", other),
+        },
+        Err(e) => println!("❌ Example 47 failed: {}", e),
+    }
+    
+    // Example 48: Calling one workflow from another
+    let example48 = r#"
+const greeting = "hello"
+workflow "Main" {
+    let local = "main only"
+    step 10: print("main starting")
+    step 11: call "Helper"
+    step 12: print(prev.data)
+}
+workflow "Helper" {
+    step 1: print(greeting + " from helper")
+}
+"#;
+    
+    println!("\n📝 Example 48: Workflow Calls");
+    println!("{}", example48);
+    
+    match run_dsl_with_report(example48) {
+        Ok(report) => {
+            let call = report.step("Main", 11).map(|result| Value::Object(result.metadata.clone()).to_json());
+            let helper = report.step("Helper", 1).map(|result| result.data.to_display_string());
+            match (call.as_deref(), helper.as_deref()) {
+                (Some(r#"{"workflow":"Helper","steps":[1]}"#), Some("hello from helper")) => {
+                    println!("✅ Example 48 Main called Helper: {}", call.unwrap_or_default())
+                }
+                other => println!("❌ Example 48 unexpected results: {:?}", other),
+            }
+        }
+        Err(e) => println!("❌ Example 48 failed: {}", e),
+    }
+    
+    // The callee sees program-level bindings only, not the caller's
+    let isolated = r#"
+workflow "Caller" {
+    let secret = 42
+    step 1: call "Peeker"
+}
+workflow "Peeker" {
+    step 2: print(secret)
+}
+"#;
+    match run_dsl_with_report(isolated) {
+        Err(e) if e.to_string().contains("Undefined variable: secret") => {
+            println!("✅ Example 48 kept the caller's variables from the callee: {}", e)
+        }
+        other => println!("❌ Example 48 callee should not see 'secret': {:?}", other.map(|report| report.steps.len())),
+    }
+    
+    let recursive = r#"
+workflow "Ping" {
+    step 1: call "Pong"
+}
+workflow "Pong" {
+    step 2: call "Ping"
+}
+"#;
+    match run_dsl_with_report(recursive) {
+        Err(e) if e.to_string().contains("Recursive workflow call: Ping -> Pong -> Ping") => {
+            println!("✅ Example 48 stopped the cycle: {}", e)
+        }
+        other => println!("❌ Example 48 recursion not detected: {:?}", other.map(|report| report.steps.len())),
+    }
+    
+    // Example 49: Duration assertions for performance SLAs
+    let example49 = r#"
+workflow "Sla" {
+    step 1: sleep(120)
+    step 2: assert_duration(step 1, 50)
+    step 3: assert_duration(step 1, ms: 5000)
+}
+"#;
+    
+    println!("\n📝 Example 49: Duration Assertions");
+    println!("{}", example49);
+    
+    match run_dsl_with_report(example49) {
+        Ok(report) => {
+            let success = |id: u32| report.step("Sla", id).map(|result| result.success);
+            match (report.step("Sla", 2), success(3)) {
+                (Some(slow), Some(true)) if !slow.success && slow.message.contains("over the 50ms limit") => {
+                    println!("✅ Example 49 flagged the slow step: {}", slow.message)
+                }
+                other => println!("❌ Example 49 unexpected assertions: {:?}", other),
+            }
+        }
+        Err(e) => println!("❌ Example 49 failed: {}", e),
+    }
+    
+    match run_dsl_with_report("workflow \"Early\" {\n    step 1: assert_duration(step 2, 100)\n    step 2: print(\"later\")\n}") {
+        Err(e) if matches!(e.downcast_ref::<DslError>(), Some(DslError::StepNotFound { step_id: 2 })) => {
+            println!("✅ Example 49 rejected a step that hasn't run: {}", e)
+        }
+        other => println!("❌ Example 49 should reject step 2: {:?}", other.map(|report| report.steps.len())),
+    }
+    
+    // Example 50: Return values handed back to the call site
+    let example50 = r#"
+workflow "Main" {
+    step 1: call "Quote"
+    step 2: print("quoted " + step 1.data)
+}
+workflow "Quote" {
+    step 10: print(150)
+    step 11: print(step 10.data * 2)
+    return step 11.data
+    step 12: print("never runs")
+}
+"#;
+    
+    println!("\n📝 Example 50: Return Values");
+    println!("{}", example50);
+    
+    match run_dsl_with_report(example50) {
+        Ok(report) => {
+            let quoted = report.step("Main", 2).map(|result| result.data.to_display_string());
+            let returned = report.return_values.get("Quote").map(Value::to_display_string);
+            match (quoted.as_deref(), returned.as_deref(), report.step("Quote", 12).is_some()) {
+                (Some("quoted 300"), Some("300"), false) => {
+                    println!("✅ Example 50 Quote returned 300 and skipped step 12")
+                }
+                other => println!("❌ Example 50 unexpected results: {:?}", other),
+            }
+        }
+        Err(e) => println!("❌ Example 50 failed: {}", e),
+    }
+    
+    // Example 51: A multi-line raw prompt keeps later line numbers right
+    let example51 = r#"
+workflow "RawPrompt" {
+    let ticker = "AAPL"
+    step 1: generate("""You are an analyst.
+Summarize "today's" moves, don't escape anything, for:
+""" + ticker, "mistral-small-latest", "0.3")
+    step 2: print(step 1.data.content)
+}
+"#;
+    
+    println!("\n📝 Example 51: Multi-Line Raw Prompts");
+    println!("{}", example51);
+    
+    match parse_dsl_example(example51) {
+        Ok(ast) => {
+            let steps = &ast.workflows[0].steps;
+            let lines = (steps[0].line, steps[0].span.end_line, steps[1].line);
+            match (lines, run_dsl_with_report(example51)) {
+                ((4, 6, 7), Ok(report)) => {
+                    let prompt = report.steps["RawPrompt"][&2].data.to_display_string();
+                    if prompt == "Generated content for: You are an analyst.\nSummarize \"today's\" moves, don't escape anything, for:\nAAPL" {
+                        println!("✅ Example 51 step 2 is on line 7 and the prompt is verbatim: {:?}", prompt);
+                    } else {
+                        println!("❌ Example 51 unexpected prompt: {:?}", prompt);
+                    }
+                }
+                (lines, Ok(_)) => println!("❌ Example 51 unexpected lines: {:?}", lines),
+                (_, Err(e)) => println!("❌ Example 51 failed: {}", e),
+            }
+        }
+        Err(e) => println!("❌ Example 51 failed to parse: {}", e),
+    }
+    
+    // Example 52: Peak memory of a run
+    let example52 = r#"
+workflow "Grow" {
+    let table = null
+    let i = 0
+    step 1: while (i < 200) {
+        step 2: set(table, "key" + i, "value " + i)
+        table = step 2.data
+        i = i + 1
+    }
+}
+"#;
+    
+    println!("\n📝 Example 52: Peak Memory Metrics");
+    println!("{}", example52);
+    
+    let trivial = "workflow \"Tiny\" {\n    step 1: print(\"hi\")\n}";
+    match (run_dsl_with_report(example52), run_dsl_with_report(trivial)) {
+        (Ok(grow), Ok(tiny)) if grow.metrics.peak_values > 100 * tiny.metrics.peak_values
+            && grow.metrics.peak_bytes > tiny.metrics.peak_bytes =>
+        {
+            println!("✅ Example 52 growing table peaked at {:?}, trivial run at {:?}", grow.metrics, tiny.metrics)
+        }
+        (Ok(grow), Ok(tiny)) => println!("❌ Example 52 peaks too close: {:?} vs {:?}", grow.metrics, tiny.metrics),
+        (Err(e), _) | (_, Err(e)) => println!("❌ Example 52 failed: {}", e),
+    }
+    
+    // Example 53: String interpolation
+    let example53 = r#"
+workflow "Urls" {
+    let base = "https://api.example.com"
+    let id = 42
+    step 1: print(id)
+    step 2: print("${base}/users/${id}")
+    step 3: print("user ${step 1.data + 1} of ${base}")
+    step 4: print("""raw ${base}""")
+}
+"#;
+    
+    println!("\n📝 Example 53: String Interpolation");
+    println!("{}", example53);
+    
+    match run_dsl_with_report(example53) {
+        Ok(report) => {
+            let printed: Vec<String> = [2, 3, 4]
+                .iter()
+                .map(|id| report.step("Urls", *id).map(|result| result.data.to_display_string()).unwrap_or_default())
+                .collect();
+            let expected = ["https://api.example.com/users/42", "user 43 of https://api.example.com", "raw ${base}"];
+            if printed == expected {
+                println!("✅ Example 53 interpolated {:?}", printed);
+            } else {
+                println!("❌ Example 53 unexpected strings: {:?}", printed);
+            }
+        }
+        Err(e) => println!("❌ Example 53 failed: {}", e),
+    }
+    
+    let unbalanced = "workflow \"Bad\" {\n    step 1: print(\"id ${oops\")\n}";
+    match parse_dsl_example(unbalanced) {
+        Err(e) if e.downcast_ref::<DslError>().is_some_and(|e| matches!(e, DslError::UnexpectedToken { line: 2, column: 23, .. })) => {
+            println!("✅ Example 53 rejected an unbalanced placeholder: {}", e)
+        }
+        Err(e) => println!("❌ Example 53 wrong error for an unbalanced placeholder: {}", e),
+        Ok(_) => println!("❌ Example 53 accepted an unbalanced placeholder"),
+    }
+    
+    // Example 54: Store and load across workflows
+    let example54 = r#"
+workflow "Save" {
+    step 1: fetch("https://api.example.com/quotes")
+    step 2: store("quote", step 1.data)
+    step 3: store("count", 3)
+}
+workflow "Restore" {
+    step 10: load("quote")
+    step 11: load("count")
+    step 12: load("missing")
+}
+"#;
+    
+    println!("\n📝 Example 54: Store And Load");
+    println!("{}", example54);
+    
+    match run_dsl_with_report(example54) {
+        Ok(report) => {
+            let data = |workflow: &str, id: u32| report.step(workflow, id).map(|result| result.data.clone());
+            let missing = report.step("Restore", 12).map(|result| (result.success, result.status));
+            if data("Restore", 10).is_some() && data("Restore", 10) == data("Save", 1) && data("Restore", 11) == Some(Value::Number(3.0))
+                && missing == Some((false, 404)) && report.store.len() == 2
+            {
+                println!("✅ Example 54 round-tripped the fetched data and count, and a missing key failed");
+            } else {
+                println!("❌ Example 54 unexpected results: {:?} {:?} {:?}", data("Restore", 10), data("Restore", 11), missing);
+            }
+        }
+        Err(e) => println!("❌ Example 54 failed: {}", e),
+    }
+    
+    // Example 55: Workflows including a shared preamble
+    let example55 = r#"
+workflow "Auth" {
+    let token = "secret-token"
+    step 1: log("authenticating")
+}
+workflow "Report" includes "Auth" {
+    let region = "eu"
+    step 2: print(token + " " + region)
+}
+"#;
+    
+    println!("\n📝 Example 55: Includes");
+    println!("{}", example55);
+    
+    match parse_dsl_example(example55) {
+        Ok(ast) => {
+            let report = &ast.workflows[1];
+            let variables: Vec<&str> = report.variables.iter().map(|variable| variable.name.as_str()).collect();
+            let ids: Vec<u32> = report.steps.iter().map(|step| step.id).collect();
+            let printed = run_dsl_with_report(example55)
+                .ok()
+                .and_then(|run| run.step("Report", 2).map(|result| result.data.to_display_string()));
+            match (variables.as_slice(), ids.as_slice(), printed.as_deref()) {
+                (["token", "region"], [1, 2], Some("secret-token eu")) => {
+                    println!("✅ Example 55 Report gained Auth's variable and step ahead of its own")
+                }
+                other => println!("❌ Example 55 unexpected merge: {:?}", other),
+            }
+        }
+        Err(e) => println!("❌ Example 55 failed to parse: {}", e),
+    }
+    
+    let clashing = "workflow \"Auth\" {\n    step 1: log(\"a\")\n}\nworkflow \"Report\" includes \"Auth\" {\n    step 1: print(\"b\")\n}";
+    match parse_dsl_example(clashing) {
+        Err(e) if e.to_string().contains("Step 1 of workflow 'Report' conflicts") => {
+            println!("✅ Example 55 rejected a clashing step id: {}", e)
+        }
+        other => println!("❌ Example 55 expected a step id conflict, got {:?}", other.map(|_| ())),
+    }
+    
+    // Example 56: Auto-numbered steps
+    let example56 = r#"
+workflow "Numbered" {
+    step: print("first")
+    step: print("second")
+    step 10: print("explicit")
+    step: if (true) {
+        step: print("nested")
+    }
+}
+"#;
+    
+    println!("\n📝 Example 56: Auto-Numbered Steps");
+    println!("{}", example56);
+    
+    let parse_auto = |source: &str| Lexer::new(source).tokenize().and_then(|tokens| Parser::new(tokens).with_auto_step_ids(true).parse());
+    match parse_auto(example56) {
+        Ok(ast) => {
+            let steps = &ast.workflows[0].steps;
+            let ids: Vec<u32> = steps.iter().map(|step| step.id).collect();
+            let nested = match &steps[3].content {
+                StepContent::Conditional(conditional) => conditional.if_steps.first().map(|step| step.id),
+                _ => None,
+            };
+            if ids == [1, 2, 10, 11] && nested == Some(12) {
+                println!("✅ Example 56 assigned ids {:?} and {:?} to the nested step", ids, nested);
+            } else {
+                println!("❌ Example 56 unexpected ids {:?}, nested {:?}", ids, nested);
+            }
+        }
+        Err(e) => println!("❌ Example 56 failed to parse: {}", e),
+    }
+    
+    let colliding = "workflow \"Clash\" {\n    step: print(\"a\")\n    step 1: print(\"b\")\n}";
+    match (parse_auto(colliding), parse_dsl_example(example56)) {
+        (Err(clash), Err(_)) => println!("✅ Example 56 rejected a reused id ({}) and needs the mode enabled", clash),
+        (clash, plain) => println!("❌ Example 56 expected both to fail: {:?} {:?}", clash.map(|_| ()), plain.map(|_| ())),
+    }
+    
+    // Example 57: filter, summarize and analyze
+    let example57 = r#"
+workflow "Stats" {
+    let prices = [12, 5, 30, 8]
+    step 1: filter(prices, "> 10")
+    step 2: summarize(prices)
+    step 3: analyze(prices)
+    step 4: set(null, "name", "widget")
+    step 5: set(step 4.data, "price", 25)
+    step 6: filter([step 5.data, step 4.data], "price >= 20")
+    step 7: analyze(["a"])
+}
+"#;
+    
+    println!("\n📝 Example 57: Filter, Summarize And Analyze");
+    println!("{}", example57);
+    
+    match run_dsl_with_report(example57) {
+        Ok(report) => {
+            let data = |id: u32| report.step("Stats", id).map(|result| result.data.to_json()).unwrap_or_default();
+            let bad_input_failed = report.step("Stats", 7).is_some_and(|result| !result.success);
+            let expected = [
+                "[12,30]".to_string(),
+                r#"{"type":"array","length":4}"#.to_string(),
+                r#"{"count":4,"sum":55,"min":5,"max":30,"mean":13.75,"median":10}"#.to_string(),
+                r#"[{"name":"widget","price":25}]"#.to_string(),
+            ];
+            let actual = [data(1), data(2), data(3), data(6)];
+            if actual == expected && bad_input_failed {
+                println!("✅ Example 57 filtered, summarized and analyzed the prices");
+            } else {
+                println!("❌ Example 57 unexpected results: {:?}, step 7 failed: {}", actual, bad_input_failed);
+            }
+        }
+        Err(e) => println!("❌ Example 57 failed: {}", e),
+    }
+    
+    // Example 59: Returning early from inside a branch or loop
+    let example59 = r#"
+workflow "Guard" {
+    step 1: validate("", "required")
+    step 2: if (!step 1.success) {
+        step 3: log("input missing, stopping")
+        return "missing input"
+    }
+    step 4: print("never runs")
+}
+workflow "Search" {
+    step 10: for n in [1, 2, 3, 4] {
+        step 11: print(n)
+        step 13: if (n == 2) {
+            return n * 10
+        }
+    }
+    step 12: print("never runs either")
+}
+"#;
+    
+    println!("\n📝 Example 59: Early Return");
+    println!("{}", example59);
+    
+    match run_dsl_with_report(example59) {
+        Ok(report) => {
+            let returned: Vec<String> = report.return_values.values().map(Value::to_display_string).collect();
+            let iterations = report.loop_results.get("Search").and_then(|results| results.get(&11)).map(Vec::len);
+            let skipped = report.step("Guard", 4).is_none() && report.step("Search", 12).is_none();
+            if returned == ["missing input", "20"] && iterations == Some(2) && skipped {
+                println!("✅ Example 59 returned {:?} and skipped the steps after each return", returned);
+            } else {
+                println!("❌ Example 59 unexpected: returned {:?}, iterations {:?}, skipped {}", returned, iterations, skipped);
+            }
+        }
+        Err(e) => println!("❌ Example 59 failed: {}", e),
+    }
+    
+    // Example 60: Restricting the hosts fetch may reach
+    let example60 = r#"
+workflow "Allowed" {
+    step 1: fetch("https://api.example.com/prices?symbol=AAPL")
+}
+workflow "Denied" {
+    step 2: fetch("https://evil.com/steal")
+}
+"#;
+    
+    println!("\n📝 Example 60: Fetch Allow-List");
+    println!("{}", example60);
+    
+    let run = |dry_run: bool| {
+        parse_dsl_example(example60).and_then(|ast| {
+            Executor::new()
+                .with_verbose(false)
+                .with_fetch_allowlist(&["api.example.com", "*.trusted.org"])
+                .with_continue_workflows_on_error(true)
+                .with_dry_run(dry_run)
+                .execute(&ast)
+        })
+    };
+    match run(false) {
+        Ok(report) => {
+            let allowed = report.step("Allowed", 1).is_some_and(|result| result.success);
+            let denied = report.failed_workflows.iter().find(|failure| failure.workflow == "Denied");
+            match denied {
+                Some(failure) if allowed && report.step("Denied", 2).is_none() && failure.error.contains("'evil.com' is not allowed") => {
+                    println!("✅ Example 60 fetched from api.example.com and refused evil.com: {}", failure.error)
+                }
+                other => println!("❌ Example 60 unexpected: allowed {}, denied {:?}", allowed, other),
+            }
+        }
+        Err(e) => println!("❌ Example 60 failed: {}", e),
+    }
+    // A dry run only plans the allowed fetch and refuses the other one just the same
+    match run(true) {
+        Ok(report) => {
+            let planned: Vec<String> = report.planned_actions.iter().filter_map(|action| action.arguments.first().map(Value::to_string)).collect();
+            let denied = report.failed_workflows.iter().find(|failure| failure.workflow == "Denied");
+            match denied {
+                Some(failure) if planned == ["https://api.example.com/prices?symbol=AAPL"] && failure.error.contains("'evil.com' is not allowed") => {
+                    println!("✅ Example 60 dry run planned {:?} and refused evil.com", planned)
+                }
+                other => println!("❌ Example 60 unexpected dry run: planned {:?}, denied {:?}", planned, other),
+            }
+        }
+        Err(e) => println!("❌ Example 60 dry run failed: {}", e),
+    }
+    
+    // Example 61: Retrying failed commands
+    let example61 = r#"
+workflow "Retries" {
+    step 1: load("token", retries: 2)
+    step 2: print("first try", retries: 3)
+}
+"#;
+    
+    println!("\n📝 Example 61: Retries");
+    println!("{}", example61);
+    
+    let retried = parse_dsl_example(example61).and_then(|ast| {
+        Executor::new().with_verbose(false).with_retry_backoff(std::time::Duration::from_millis(1)).execute(&ast)
+    });
+    match retried {
+        Ok(report) => {
+            let messages: Vec<&str> = [1, 2]
+                .iter()
+                .map(|id| report.step("Retries", *id).map_or("", |result| result.message.as_str()))
+                .collect();
+            match messages.as_slice() {
+                [load, print] if load.ends_with("(after 3 attempts)") && !print.contains("attempts") => {
+                    println!("✅ Example 61 gave up on load after 3 attempts and ran print once: {:?}", messages)
+                }
+                other => println!("❌ Example 61 unexpected messages: {:?}", other),
+            }
+        }
+        Err(e) => println!("❌ Example 61 failed: {}", e),
+    }
+    
+    // Example 62: Input validated as it is collected
+    let example62 = r#"
+workflow "Signup" {
+    step 1: input("name", "text", "Your name", required: true)
+    step 2: input("age", "number", "Enter age", required: true, min: 0, max: 150)
+}
+"#;
+    
+    println!("\n📝 Example 62: Validated Input");
+    println!("{}", example62);
+    
+    let answered = parse_dsl_example(example62).and_then(|ast| {
+        Executor::new().with_verbose(false).with_input_provider(CannedAnswers).execute(&ast)
+    });
+    match answered {
+        Ok(report) => {
+            let name = report.step("Signup", 1).map(|result| (result.success, result.data.to_json()));
+            let age = report.step("Signup", 2).map(|result| (result.success, result.status, result.message.clone()));
+            match (name, age) {
+                (Some((true, name)), Some((false, 422, message))) if name.contains(r#""value":"Ann""#) => {
+                    println!("✅ Example 62 accepted the name and rejected the age: {}", message)
+                }
+                other => println!("❌ Example 62 unexpected results: {:?}", other),
+            }
+        }
+        Err(e) => println!("❌ Example 62 failed: {}", e),
+    }
+    
+    // Example 63: Scientific and hex number notation
+    let example63 = r#"
+workflow "Notation" {
+    step 1: print(1e3 == 1000)
+    step 2: print(2.5e-2)
+    step 3: print(0x10 == 16)
+    step 4: print(0xFF + 1E+2)
+}
+"#;
+    
+    println!("\n📝 Example 63: Number Notation");
+    println!("{}", example63);
+    
+    match run_dsl_with_report(example63) {
+        Ok(report) => {
+            let printed: Vec<String> = (1..=4)
+                .map(|id| report.step("Notation", id).map(|result| result.data.to_display_string()).unwrap_or_default())
+                .collect();
+            let bad_hex = parse_dsl_example("workflow \"Bad\" {\n    step 1: print(0x)\n}").is_err();
+            if printed == ["true", "0.025", "true", "355"] && bad_hex {
+                println!("✅ Example 63 read the literals as {:?} and rejected a bare 0x", printed);
+            } else {
+                println!("❌ Example 63 unexpected values {:?}, bare 0x rejected: {}", printed, bad_hex);
+            }
+        }
+        Err(e) => println!("❌ Example 63 failed: {}", e),
+    }
+    
+    // Example 65: Step timings and the slowest steps
+    let example65 = r#"
+workflow "Timed" {
+    step 1: sleep(30)
+    step 2: print("quick")
+    step 3: sleep(5)
+}
+"#;
+    
+    println!("\n📝 Example 65: Step Timings");
+    println!("{}", example65);
+    
+    match run_dsl_with_report(example65) {
+        Ok(report) => {
+            let ids: Vec<u32> = report.timings.iter().map(|timing| timing.step_id).collect();
+            let sequential = report.timings.windows(2).all(|pair| pair[0].started + pair[0].duration <= pair[1].started);
+            let within_total = report.timings.iter().all(|timing| timing.started + timing.duration <= report.total_duration);
+            let slowest: Vec<u32> = report.slowest_steps(2).iter().map(|timing| timing.step_id).collect();
+            if ids == [1, 2, 3] && sequential && within_total && slowest == [1, 3] {
+                println!("✅ Example 65 timed {:?} in {:?}, slowest {:?}", ids, report.total_duration, slowest);
+            } else {
+                println!("❌ Example 65 unexpected timings {:?} (total {:?}), slowest {:?}", report.timings, report.total_duration, slowest);
+            }
+        }
+        Err(e) => println!("❌ Example 65 failed: {}", e),
+    }
+    
+    // Example 66: Reading an earlier workflow's result
+    let example66 = r#"
+workflow "Prices" {
+    step 1: analyze([10, 20, 30])
+    return step 1.data.mean
+}
+workflow "Report" {
+    step 2: print("average price " + workflow("Prices").result)
+}
+"#;
+    
+    println!("\n📝 Example 66: Workflow Results");
+    println!("{}", example66);
+    
+    let too_early = "workflow \"B\" {\n    step 1: print(workflow(\"A\").result)\n}\nworkflow \"A\" {\n    return 1\n}";
+    match (run_dsl_with_report(example66), run_dsl_with_report(too_early)) {
+        (Ok(report), Err(early)) => match report.step("Report", 2).map(|result| result.data.to_display_string()) {
+            Some(printed) if printed == "average price 20" && early.to_string().contains("'A' has not finished") => {
+                println!("✅ Example 66 Report read {:?}; reading ahead failed: {}", printed, early)
+            }
+            other => println!("❌ Example 66 unexpected: {:?}, {}", other, early),
+        },
+        (Ok(_), Ok(_)) => println!("❌ Example 66 read a workflow that hadn't run yet"),
+        (Err(e), _) => println!("❌ Example 66 failed: {}", e),
+    }
+    
+    // Example 67: Folded stacks for flamegraphs
+    let example67 = r#"
+workflow "Profiled" {
+    step 1: print("start")
+    step 2: if (true) {
+        step 3: sleep(5)
+    }
+}
+"#;
+    
+    println!("\n📝 Example 67: Profiling");
+    println!("{}", example67);
+    
+    let mut profiler = Executor::new().with_verbose(false).with_profiling(true);
+    match parse_dsl_example(example67).and_then(|ast| profiler.execute(&ast)) {
+        Ok(_) => {
+            let folded = profiler.folded_stacks();
+            let nested = folded
+                .lines()
+                .find_map(|line| line.strip_prefix("Profiled;step 2 if;step 3 sleep "))
+                .and_then(|micros| micros.parse::<u64>().ok());
+            match nested {
+                Some(micros) if micros >= 5000 && folded.lines().any(|line| line.starts_with("Profiled;step 1 print ")) => {
+                    println!("✅ Example 67 folded stacks:\n{}", folded.trim_end())
+                }
+                _ => println!("❌ Example 67 unexpected folded stacks:\n{}", folded),
+            }
+        }
+        Err(e) => println!("❌ Example 67 failed: {}", e),
+    }
+    
+    // Example 69: A custom command registered on the executor
+    let example69 = r#"
+workflow "Custom" {
+    step 1: double(21)
+    step 2: double(step 1.data)
+    step 3: double("many")
+}
+"#;
+    
+    println!("\n📝 Example 69: Custom Commands");
+    println!("{}", example69);
+    
+    let mut executor = Executor::new().with_verbose(false);
+    executor.register_command("double", |arguments: &[Value]| -> Result<StepResult> {
+        Ok(match arguments.first().and_then(Value::as_number) {
+            Some(number) => StepResult::new(true, Value::Number(number * 2.0), 200, "Doubled".to_string()),
+            None => StepResult::new(false, Value::Null, 400, "double expects a number".to_string()),
+        })
+    });
+    match parse_dsl_example(example69).and_then(|ast| executor.execute(&ast)) {
+        Ok(report) if report.steps["Custom"][&2].data == Value::Number(84.0) && !report.steps["Custom"][&3].success => {
+            println!("✅ Example 69 doubled 21 twice to {} and rejected text: {}", report.steps["Custom"][&2].data, report.steps["Custom"][&3].message)
+        }
+        Ok(report) => println!("❌ Example 69 unexpected results: {:?}", report.steps),
+        Err(e) => println!("❌ Example 69 failed: {}", e),
+    }
+    
+    // Example 70: Sorting numbers, and objects by a field
+    let example70 = r#"
+workflow "Sorting" {
+    step 1: set(null, "symbol", "MSFT")
+    step 2: set(step 1.data, "price", 410)
+    step 3: set(null, "symbol", "AAPL")
+    step 4: set(step 3.data, "price", 190)
+    step 5: set(null, "symbol", "AMZN")
+    step 6: set(step 5.data, "price", 190)
+    step 7: sort([10, 2, 33, 4.5])
+    step 8: sort([step 2.data, step 6.data, step 4.data], "price")
+    step 9: sort([step 2.data, step 6.data, step 4.data], key: "symbol")
+    step 10: sort([3, "three"])
+}
+"#;
+    
+    println!("\n📝 Example 70: Sort");
+    println!("{}", example70);
+    
+    match parse_dsl_example(example70).and_then(|ast| Executor::new().with_verbose(false).execute(&ast)) {
+        Ok(report) => {
+            let symbols = |step: u32| match &report.steps["Sorting"][&step].data {
+                Value::Array(items) => items
+                    .iter()
+                    .filter_map(|item| match item {
+                        Value::Object(fields) => fields.get("symbol").map(Value::to_display_string),
+                        _ => None,
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+            let numbers = report.steps["Sorting"][&7].data.to_string();
+            // Equal prices keep their original order
+            if numbers == "[2,4.5,10,33]" && symbols(8) == ["AMZN", "AAPL", "MSFT"] && symbols(9) == ["AAPL", "AMZN", "MSFT"]
+                && !report.steps["Sorting"][&10].success
+            {
+                println!("✅ Example 70 sorted {} and trades by price, and refused a mixed array: {}", numbers, report.steps["Sorting"][&10].message)
+            } else {
+                println!("❌ Example 70 unexpected results: {:?}", report.steps)
+            }
+        }
+        Err(e) => println!("❌ Example 70 failed: {}", e),
+    }
+    
+    // Example 71: Step ids reused across workflows
+    let example71 = r#"
+workflow "Morning" {
+    step 1: print("open")
+    step 2: print("morning " + step 1.data)
+}
+workflow "Evening" {
+    step 1: print("close")
+    step 2: print("evening " + step 1.data)
+}
+"#;
+    
+    println!("\n📝 Example 71: Per-Workflow Step Results");
+    println!("{}", example71);
+    
+    let leaking = "workflow \"A\" {\n    step 1: print(1)\n}\nworkflow \"B\" {\n    step 2: print(step 1.data)\n}";
+    match (run_dsl_with_report(example71), parse_dsl_example(leaking).map(|ast| ast.validate())) {
+        (Ok(report), Ok(Err(leak))) => {
+            let printed = |workflow: &str| report.step(workflow, 2).map(|result| result.data.to_display_string());
+            match (printed("Morning"), printed("Evening")) {
+                (Some(morning), Some(evening)) if morning == "morning open" && evening == "evening close" => {
+                    println!("✅ Example 71 kept both step 1 results apart: {:?} / {:?}; {}", morning, evening, leak)
+                }
+                other => println!("❌ Example 71 unexpected results: {:?}", other),
+            }
+        }
+        (Ok(_), other) => println!("❌ Example 71 let workflow B read A's step 1: {:?}", other.map(|_| ())),
+        (Err(e), _) => println!("❌ Example 71 failed: {}", e),
+    }
+    
+    // Example 72: Reading environment variables
+    std::env::set_var("TMFLOW_EXAMPLE_REGION", "eu-west");
+    std::env::remove_var("TMFLOW_EXAMPLE_UNSET");
+    let example72 = r#"
+workflow "Deploy" {
+    step 1: print("region " + env("TMFLOW_EXAMPLE_REGION"))
+    step 2: print(env("TMFLOW_EXAMPLE_UNSET", "default-tier"))
+}
+"#;
+    
+    println!("\n📝 Example 72: Environment Variables");
+    println!("{}", example72);
+    
+    let unset = "workflow \"Deploy\" {\n    step 1: print(env(\"TMFLOW_EXAMPLE_UNSET\"))\n}";
+    match (run_dsl_with_report(example72), run_dsl_with_report(unset)) {
+        (Ok(report), Err(error)) => {
+            let printed = |id: u32| report.step("Deploy", id).map(|result| result.data.to_display_string());
+            match (printed(1), printed(2)) {
+                (Some(region), Some(tier)) if region == "region eu-west" && tier == "default-tier"
+                    && error.to_string().contains("'TMFLOW_EXAMPLE_UNSET' is not set") => {
+                    println!("✅ Example 72 read {:?} and {:?}; unset: {}", region, tier, error)
+                }
+                other => println!("❌ Example 72 unexpected results: {:?} / {}", other, error),
+            }
+        }
+        (Ok(_), Ok(_)) => println!("❌ Example 72 read an unset variable without a default"),
+        (Err(e), _) => println!("❌ Example 72 failed: {}", e),
+    }
+    
+    // Example 73: Number literals too large for a float
+    let example73 = format!("workflow \"Huge\" {{\n    step 1: print({})\n}}", "9".repeat(400));
+    
+    println!("\n📝 Example 73: Oversized Number Literals");
+    println!("workflow \"Huge\" {{ step 1: print(<400 nines>) }}");
+    
+    match parse_dsl_example(&example73) {
+        Err(e) if e.to_string().contains("Number literal too large at line 2") => {
+            println!("✅ Example 73 rejected the 400-digit literal: {}", e)
+        }
+        Err(e) => println!("❌ Example 73 failed with the wrong error: {}", e),
+        Ok(program) => println!("❌ Example 73 parsed an infinite number: {:?}", program.workflows[0].steps[0].content),
+    }
+    
+    // Example 74: Doc comments as human-readable step descriptions
+    let example74 = r#"
+workflow "Onboarding" {
+    /// Look up the new customer's account in the CRM
+    step 1: fetch("https://crm.example.com/accounts/42")
+    // An ordinary comment stays out of the description
+    step 2: summarize(step 1.data)
+}
+"#;
+    
+    println!("\n📝 Example 74: Documented Steps");
+    println!("{}", example74);
+    
+    match parse_dsl_example(example74) {
+        Ok(program) => {
+            let steps = human_steps(&program);
+            if steps == ["Step 1: Look up the new customer's account in the CRM", "Step 2: Summarize step 1.data"] {
+                println!("✅ Example 74 described the steps as {:?}", steps)
+            } else {
+                println!("❌ Example 74 unexpected human steps: {:?}", steps)
+            }
+        }
+        Err(e) => println!("❌ Example 74 failed: {}", e),
+    }
+    
+    // Example 75: Random picks from a seeded generator
+    let example75 = r#"
+workflow "Experiment" {
+    step 1: pick(["control", "variant-a", "variant-b"])
+    step 2: pick([])
+}
+"#;
+    
+    println!("\n📝 Example 75: Seeded Random Picks");
+    println!("{}", example75);
+    
+    let expected = ["control", "variant-a", "variant-b"][SeededRandom::new(42).next_index(3)];
+    let picked = |seed| parse_dsl_example(example75).and_then(|ast| {
+        Executor::new().with_verbose(false).with_random_source(SeededRandom::new(seed)).execute(&ast)
+    });
+    match (picked(42), picked(42)) {
+        (Ok(first), Ok(second)) => {
+            let choice = |report: &ExecutionReport| report.step("Experiment", 1).map(|result| result.data.to_display_string());
+            let empty = first.step("Experiment", 2).map(|result| (result.success, result.message.clone()));
+            match (choice(&first), choice(&second), empty) {
+                (Some(a), Some(b), Some((false, message))) if a == expected && b == expected => {
+                    println!("✅ Example 75 picked {:?} with seed 42 both times; empty: {}", a, message)
+                }
+                other => println!("❌ Example 75 unexpected picks: {:?}", other),
+            }
+        }
+        (Err(e), _) | (_, Err(e)) => println!("❌ Example 75 failed: {}", e),
+    }
+    
+    // Example 76: Human-readable steps for branches
+    let example76 = r#"
+workflow "Triage" {
+    step 1: fetch("https://api.example.com/tickets")
+    step 2: if (step 1.status == 200) {
+        step 3: filter(step 1.data, "priority > 2")
+        step 4: if (step 3.data) {
+            step 5: notify("on-call")
+        }
+    } else {
+        step 6: print("ticket service is down")
+    }
+}
+"#;
+    
+    println!("\n📝 Example 76: Describing Conditionals");
+    println!("{}", example76);
+    
+    let expected = [
+        "Step 1: Fetch data from https://api.example.com/tickets",
+        "Step 2: If step 1.status == 200",
+        "  Step 3: Filter step 1.data by priority > 2",
+        "  Step 4: If step 3.data",
+        "    Step 5: Execute notify",
+        "Otherwise",
+        "  Step 6: Execute print",
+    ];
+    match parse_dsl_example(example76) {
+        Ok(program) => {
+            let steps = human_steps(&program);
+            if steps == expected {
+                println!("✅ Example 76 described both branches:\n{}", steps.join("\n"))
+            } else {
+                println!("❌ Example 76 unexpected human steps: {:#?}", steps)
+            }
+        }
+        Err(e) => println!("❌ Example 76 failed: {}", e),
+    }
+    
+    // Example 77: Reading a step before it runs
+    let example77 = r#"
+workflow "Report" {
+    step 1: print("draft " + step 2.data)
+    step 2: print("final")
+    step 3: print(step 2.data)
+}
+"#;
+    
+    println!("\n📝 Example 77: Forward Step References");
+    println!("{}", example77);
+    
+    match parse_dsl_example(example77) {
+        Ok(program) => {
+            let warnings: Vec<String> = analyze_program(&program)
+                .into_iter()
+                .filter(|diagnostic| diagnostic.message.contains("comes later in the same block"))
+                .map(|diagnostic| diagnostic.message)
+                .collect();
+            if warnings == ["Line 3: step 2 is read before it runs, as it comes later in the same block"] {
+                println!("✅ Example 77 warned about the forward reference only: {}", warnings[0])
+            } else {
+                println!("❌ Example 77 unexpected warnings: {:?}", warnings)
+            }
+        }
+        Err(e) => println!("❌ Example 77 failed: {}", e),
+    }
+    
+    // Example 78: Printing a program back to source
+    let example78 = r#"
+const threshold = 100
+@schedule("0 9 * * 1-5")
+workflow "ComplexExample" {
+    let base_url = "https://trading-api.com"
+    /// Pull the latest market data
+    step 1: fetch(base_url + "/market-data", retries: 2)
+    step 2: if (step 1.status == 200 && !(step 1.data.price < 0)) {
+        step 3: print("Market data received successfully")
+        step 4: if (step 1.data.price > threshold) {
+            step 5: send_email("trader@company.com", 'Price is "high"')
+        } else if (step 1.data.price > threshold * 0.5) {
+            step 6: notify("Price is ${step 1.data.price}")
+        } else {
+            step 7: print("Price is normal: " + step 1.data.price)
+        }
+    } else {
+        step 8: notify("Failed to fetch market data")
+    }
+    step 9: for symbol in ["AAPL", "MSFT"] {
+        step 10: print(symbol)
+    }
+    step 11: try {
+        step 12: fetch(env("QUOTES_URL", "https://quotes.example.com"))
+    } catch(reason) {
+        step 13: log(reason)
+    }
+    on_error(problem) {
+        step 14: notify(problem)
+    }
+}
+"#;
+    
+    println!("\n📝 Example 78: Round-Tripping Source");
+    println!("{}", example78);
+    
+    // Positions differ between the two sources, so they are left out of the comparison
+    fn without_positions(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(fields) => {
+                fields.retain(|key, _| key != "span" && key != "line");
+                fields.values_mut().for_each(without_positions);
+            }
+            serde_json::Value::Array(items) => items.iter_mut().for_each(without_positions),
+            _ => {}
+        }
+    }
+    let round_trip = parse_dsl_example(example78).and_then(|original| {
+        let source = original.to_dsl_string();
+        let reparsed = parse_dsl_example(&source)?;
+        let mut shapes = [serde_json::to_value(&original)?, serde_json::to_value(&reparsed)?];
+        shapes.iter_mut().for_each(without_positions);
+        Ok((source, shapes[0] == shapes[1], reparsed.to_dsl_string()))
+    });
+    match round_trip {
+        Ok((source, true, reprinted)) if reprinted == source => {
+            println!("✅ Example 78 printed source that parses back to the same AST:\n{}", source)
+        }
+        Ok((source, _, _)) => println!("❌ Example 78 printed source with a different AST:\n{}", source),
+        Err(e) => println!("❌ Example 78 failed: {}", e),
+    }
+    
+    // Example 79: Control flow as a Graphviz graph
+    let example79 = r#"
+workflow "Approval" {
+    step 1: fetch("https://api.example.com/request")
+    step 2: if (step 1.data.amount > 1000) {
+        step 3: notify("needs review")
+    } else {
+        step 4: print("auto-approved")
+    }
+    step 5: print("done")
+}
+"#;
+    
+    println!("\n📝 Example 79: DOT Export");
+    println!("{}", example79);
+    
+    match parse_dsl_example(example79) {
+        Ok(program) => {
+            let dot = workflow_to_dot(&program.workflows[0]);
+            let expected = [
+                "digraph \"Approval\" {",
+                r#"    step2 [label="Step 2: if (step 1.data.amount > 1000)", shape=diamond];"#,
+                "    step1 -> step2;",
+                r#"    step2 -> step3 [label="true"];"#,
+                r#"    step2 -> step4 [label="false"];"#,
+                "    step3 -> step5;",
+                "    step4 -> step5;",
+                "    step1 -> step2 [style=dashed];",
+            ];
+            let missing: Vec<&str> = expected.iter().copied().filter(|line| !dot.lines().any(|actual| actual == *line)).collect();
+            if missing.is_empty() {
+                println!("✅ Example 79 exported the branches and dependencies:\n{}", dot)
+            } else {
+                println!("❌ Example 79 is missing {:?} in:\n{}", missing, dot)
+            }
+        }
+        Err(e) => println!("❌ Example 79 failed: {}", e),
+    }
+    
+    // Example 80: Metrics for Prometheus
+    let example80 = r#"
+workflow "Nightly" {
+    step 1: print("start")
+    step 2: sort([3, "b"])
+    step 3: for n in [1, 2] {
+        step 4: print(n)
+    }
+}
+"#;
+    
+    println!("\n📝 Example 80: Prometheus Metrics");
+    println!("{}", example80);
+    
+    let path = std::env::temp_dir().join(format!("tmflow-example-{}.prom", std::process::id()));
+    let written = run_dsl_with_report(example80).and_then(|report| {
+        report.write_prometheus(&path)?;
+        Ok((report.to_prometheus(), std::fs::read_to_string(&path)?))
+    });
+    let _ = std::fs::remove_file(&path);
+    match written {
+        Ok((text, file)) => {
+            let has_duration = text.lines().any(|line| line.starts_with("tmflow_run_duration_seconds "));
+            if text.contains("tmflow_steps_total{workflow=\"Nightly\"} 4\n")
+                && text.contains("tmflow_step_failures_total{workflow=\"Nightly\"} 1\n")
+                && text.contains("# TYPE tmflow_steps_total counter\n")
+                && has_duration
+                && file == text
+            {
+                println!("✅ Example 80 exported the run's metrics:\n{}", text)
+            } else {
+                println!("❌ Example 80 unexpected metrics:\n{}", text)
+            }
+        }
+        Err(e) => println!("❌ Example 80 failed: {}", e),
+    }
+    
+    // Example 81: Control flow as a Mermaid flowchart
+    println!("\n📝 Example 81: Mermaid Export");
+    println!("{}", example79);
+    
+    match parse_dsl_example(example79) {
+        Ok(program) => {
+            let mermaid = workflow_to_mermaid(&program.workflows[0]);
+            let expected = [
+                "flowchart TD",
+                r#"    step1["Step 1: fetch https://api.example.com/request"]"#,
+                r#"    step2{"Step 2: if (step 1.data.amount > 1000)"}"#,
+                r#"    step3["Step 3: notify needs review"]"#,
+                "    step1 --> step2",
+                "    step2 -->|true| step3",
+                "    step2 -->|false| step4",
+                "    step3 --> step5",
+                "    step4 --> step5",
+            ];
+            let lines: Vec<&str> = mermaid.lines().collect();
+            let missing: Vec<&str> = expected.iter().copied().filter(|line| !lines.contains(line)).collect();
+            if missing.is_empty() && lines.first() == Some(&"flowchart TD") {
+                println!("✅ Example 81 exported the flowchart:\n{}", mermaid)
+            } else {
+                println!("❌ Example 81 is missing {:?} in:\n{}", missing, mermaid)
+            }
+        }
+        Err(e) => println!("❌ Example 81 failed: {}", e),
+    }
+    
+    // Example 82: Merging objects
+    let example82 = r#"
+workflow "Payload" {
+    step 1: set(null, "region", "eu")
+    step 2: set(step 1.data, "retries", 1)
+    step 3: set(null, "retries", 5)
+    step 4: merge(step 2.data, step 3.data)
+    step 5: merge(step 2.data, "not an object")
+}
+"#;
+    
+    println!("\n📝 Example 82: Merging Objects");
+    println!("{}", example82);
+    
+    match run_dsl_with_report(example82) {
+        Ok(report) => {
+            let merged = report.step("Payload", 4).map(|result| result.data.to_json());
+            let rejected = report.step("Payload", 5).map(|result| (result.success, result.message.clone()));
+            match (merged, rejected) {
+                (Some(merged), Some((false, message))) if merged == r#"{"region":"eu","retries":5}"# => {
+                    println!("✅ Example 82 let the later value win: {}; {}", merged, message)
+                }
+                other => println!("❌ Example 82 unexpected results: {:?}", other),
+            }
+        }
+        Err(e) => println!("❌ Example 82 failed: {}", e),
+    }
+    
+    // Example 83: Streaming execution events
+    let example83 = r#"
+workflow "Main" {
+    step 1: print("hello")
+    step 2: print(step 1.data + " again")
+}
+workflow "Broken" {
+    step 1: print(missing)
+}
+"#;
+    
+    println!("\n📝 Example 83: Execution Events");
+    println!("{}", example83);
+    
+    let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let collected = events.clone();
+    let run = parse_dsl_example(example83).and_then(|ast| {
+        Executor::new()
+            .with_verbose(false)
+            .with_continue_workflows_on_error(true)
+            .with_event_handler(move |event| {
+                let summary = match event {
+                    ExecutionEvent::WorkflowStarted { workflow } => format!("start {}", workflow),
+                    ExecutionEvent::StepStarted { workflow, step_id } => format!("step {} {}", workflow, step_id),
+                    ExecutionEvent::StepFinished { workflow, step_id, result } => {
+                        format!("done {} {} {}", workflow, step_id, result.data.to_display_string())
+                    }
+                    ExecutionEvent::Error { workflow, message } => format!("error {} {}", workflow, message),
+                };
+                if let Ok(mut events) = collected.lock() {
+                    events.push(summary);
+                }
+            })
+            .execute(&ast)
+    });
+    let events = events.lock().map(|events| events.clone()).unwrap_or_default();
+    let expected = [
+        "start Main",
+        "step Main 1",
+        "done Main 1 hello",
+        "step Main 2",
+        "done Main 2 hello again",
+        "start Broken",
+        "step Broken 1",
+        "error Broken Step 1 (line 7): Undefined variable: missing",
+    ];
+    match run {
+        Ok(_) if events == expected => println!("✅ Example 83 streamed {} events: {:?}", events.len(), events),
+        Ok(_) => println!("❌ Example 83 unexpected events: {:?}", events),
+        Err(e) => println!("❌ Example 83 failed: {}", e),
+    }
+    
+    // Example 84: Guarded steps
+    let example84 = r#"
+workflow "Alerts" {
+    step 1: set(null, "errors", 0)
+    step 2: print("errors found") when (step 1.data.errors > 0)
+    step 3: print("all clear") when (step 1.data.errors == 0)
+}
+"#;
+    
+    println!("\n📝 Example 84: Guarded Steps");
+    println!("{}", example84);
+    
+    match run_dsl_with_report(example84) {
+        Ok(report) => {
+            let skipped = report.step("Alerts", 2).map(|result| (result.data.clone(), result.metadata.get("skipped").cloned()));
+            let ran = report.step("Alerts", 3).map(|result| result.data.to_display_string());
+            match (skipped, ran) {
+                (Some((Value::Null, Some(Value::Bool(true)))), Some(ran)) if ran == "all clear" => {
+                    println!("✅ Example 84 skipped step 2 and ran step 3: {}", ran)
+                }
+                other => println!("❌ Example 84 unexpected results: {:?}", other),
+            }
+        }
+        Err(e) => println!("❌ Example 84 failed: {}", e),
+    }
+    
+    // Example 85: Execution limits
+    let example85 = r#"
+workflow "Spin" {
+    var count = 0
+    step 1: while (count < 50) {
+        count = count + 1
+    }
+}
+workflow "Outer" {
+    step 1: call "Middle"
+}
+workflow "Middle" {
+    step 1: call "Inner"
+}
+workflow "Inner" {
+    step 1: print("deep")
+}
+"#;
+    
+    println!("\n📝 Example 85: Execution Limits");
+    println!("{}", example85);
+    
+    let limit = |error: &anyhow::Error| match error.downcast_ref::<DslError>() {
+        Some(DslError::LimitExceeded { limit, max }) => Some((limit.clone(), *max)),
+        _ => None,
+    };
+    let steps = parse_dsl_example(example85)
+        .and_then(|ast| Executor::new().with_verbose(false).with_max_steps(10).execute(&ast));
+    let calls = parse_dsl_example(example85)
+        .and_then(|ast| Executor::new().with_verbose(false).with_max_call_depth(1).execute(&ast));
+    match (&steps, &calls) {
+        (Err(steps), Err(calls))
+            if limit(steps) == Some(("steps executed".to_string(), 10))
+                && limit(calls) == Some(("nested workflow calls".to_string(), 1)) =>
+        {
+            println!("✅ Example 85 stopped at both limits: {}; {}", steps, calls)
+        }
+        other => println!("❌ Example 85 unexpected results: {:?}", other),
+    }
+    
+    // Example 86: Division
+    let example86 = r#"
+workflow "Split" {
+    step 1: print(10 / 4)
+    step 2: print(7 - 6 / 3 * 2)
+    step 3: print(5 / 0)
+}
+"#;
+    
+    println!("\n📝 Example 86: Division");
+    println!("{}", example86);
+    
+    match parse_dsl_example(example86) {
+        Ok(ast) => {
+            // `/` binds like `*` and prints back without losing its grouping
+            let printed = Expression::binary(Expression::number(8.0), "/", Expression::binary(Expression::number(4.0), "/", Expression::number(2.0))).to_string();
+            let run = Executor::new().with_verbose(false).with_continue_workflows_on_error(true).execute(&ast);
+            let values = |id| run.as_ref().ok().and_then(|report| report.step("Split", id)).map(|result| result.data.to_display_string());
+            let error = run.as_ref().ok().and_then(|report| report.failed_workflows.first()).map(|failure| failure.error.clone());
+            match (values(1), values(2), error) {
+                (Some(quotient), Some(mixed), Some(error))
+                    if quotient == "2.5" && mixed == "3" && error.contains("Division by zero in 5 / 0") && printed == "8 / (4 / 2)" =>
+                {
+                    println!("✅ Example 86 divided: {}, {}, {}; {}", quotient, mixed, printed, error)
+                }
+                other => println!("❌ Example 86 unexpected results: {:?} {:?}", other, run.as_ref().err()),
+            }
+        }
+        Err(e) => println!("❌ Example 86 failed: {}", e),
+    }
+    
+    // Example 87: Symbolic dry analysis
+    let example87 = r#"
+workflow "Reachable" {
+    const debug = true
+    step 1: if (debug) {
+        step 2: print(1 / 0)
+    }
+}
+workflow "Unreachable" {
+    const debug = false
+    step 1: if (debug) {
+        step 2: print(1 / 0)
+    }
+    step 3: print(10 / 4)
+}
+workflow "Dynamic" {
+    step 1: set(null, "count", 0)
+    step 2: if (step 1.data.count == 0) {
+        step 3: print(1 / 0)
+    }
+}
+"#;
+    
+    println!("\n📝 Example 87: Dry Analysis");
+    println!("{}", example87);
+    
+    match parse_dsl_example(example87) {
+        Ok(ast) => {
+            let messages: Vec<String> = dry_analyze(&ast).into_iter().map(|diagnostic| diagnostic.message).collect();
+            if messages == ["Line 5: Division by zero in 1 / 0"] {
+                println!("✅ Example 87 flagged only the reachable division: {:?}", messages)
+            } else {
+                println!("❌ Example 87 unexpected diagnostics: {:?}", messages)
+            }
+        }
+        Err(e) => println!("❌ Example 87 failed: {}", e),
+    }
+    
+    // Example 88: Running one executor twice
+    let example88 = r#"
+const base = 10
+var runs = 0
+workflow "Again" {
+    runs = runs + 1
+    step 1: print(base + runs)
+}
+"#;
+    
+    println!("\n📝 Example 88: Reused Executor");
+    println!("{}", example88);
+    
+    match parse_dsl_example(example88) {
+        Ok(ast) => {
+            let mut executor = Executor::new().with_verbose(false);
+            let first = executor.execute(&ast);
+            let second = executor.execute(&ast);
+            let summary = |report: &ExecutionReport| (
+                report.step("Again", 1).map(|result| result.data.to_display_string()),
+                report.timings.len(),
+                report.variables.get("runs").cloned(),
+            );
+            match (&first, &second) {
+                (Ok(first), Ok(second))
+                    if summary(first) == summary(second) && summary(second) == (Some("11".to_string()), 1, Some(Value::Number(1.0))) =>
+                {
+                    println!("✅ Example 88 started the second run from scratch: {:?}", summary(second))
+                }
+                (Ok(first), Ok(second)) => println!("❌ Example 88 runs differ: {:?} / {:?}", summary(first), summary(second)),
+                (Err(e), _) | (_, Err(e)) => println!("❌ Example 88 failed: {}", e),
+            }
+        }
+        Err(e) => println!("❌ Example 88 failed: {}", e),
+    }
+    
+    // Example 89: Const block members are protected
+    let example89 = r#"
+const { base_url = "https://api.example.com"; timeout = 30; }
+workflow "Tune" {
+    step 1: print(base_url)
+    timeout = 60
+}
+"#;
+    
+    println!("\n📝 Example 89: Const Block Protection");
+    println!("{}", example89);
+    
+    match parse_dsl_example(example89) {
+        Ok(ast) => {
+            let declared: Vec<(&str, &str)> = ast.variables.iter().map(|variable| (variable.keyword.as_str(), variable.name.as_str())).collect();
+            let run = Executor::new().with_verbose(false).execute(&ast);
+            match run {
+                Err(e) if declared == [("const", "base_url"), ("const", "timeout")] && e.to_string().contains("Cannot assign to constant: timeout") => {
+                    println!("✅ Example 89 rejected the assignment: {}", e)
+                }
+                Err(e) => println!("❌ Example 89 unexpected error: {} ({:?})", e, declared),
+                Ok(_) => println!("❌ Example 89 should reject assigning to a const block member"),
+            }
+        }
+        Err(e) => println!("❌ Example 89 failed: {}", e),
+    }
+    
+    // Example 92: The binary's --time table
+    let example92 = r#"
+workflow "Sleepy" {
+    step 1: sleep(30)
+    step 2: print("awake")
+}
+"#;
+    
+    println!("\n📝 Example 92: Timing Flag");
+    println!("{}", example92);
+    
+    let path = std::env::temp_dir().join(format!("tmflow-time-{}.tm", std::process::id()));
+    let output = std::fs::write(&path, example92)
+        .and_then(|()| std::env::current_exe())
+        .and_then(|binary| std::process::Command::new(binary).arg(&path).arg("--time").output());
+    let _ = std::fs::remove_file(&path);
+    match output {
+        Ok(output) => {
+            let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+            // The row of step 1 reads `Sleepy  1  30.123ms`
+            let slept = stdout.lines().find_map(|line| {
+                let columns: Vec<&str> = line.split_whitespace().collect();
+                match columns.as_slice() {
+                    ["Sleepy", "1", duration] => duration.trim_end_matches("ms").parse::<f64>().ok(),
+                    _ => None,
+                }
+            });
+            match slept {
+                Some(millis) if output.status.success() && millis >= 30.0 && stdout.contains("Step timings") && stdout.contains("Total") => {
+                    println!("✅ Example 92 timed the sleep step at {:.1}ms", millis)
+                }
+                _ => println!("❌ Example 92 unexpected output:\n{}", stdout),
+            }
+        }
+        Err(e) => println!("❌ Example 92 failed: {}", e),
+    }
+    
+    // Example 93: Every Value variant, evaluated from the DSL
+    let example93 = r#"
+let text = "hi"
+let count = 2 + 3 * 2
+let ready = 1 < 2
+let nothing = null
+let items = [1, "two"]
+workflow "Values" {
+    step 1: set(null, "price", 9.5)
+}
+"#;
+    
+    println!("\n📝 Example 93: Value Variants");
+    println!("{}", example93);
+    
+    match run_dsl_with_report(example93) {
+        Ok(report) => {
+            let object = report.step("Values", 1).map(|result| result.data.clone()).unwrap_or(Value::Null);
+            let values: Vec<(&str, Value)> = ["text", "count", "ready", "nothing", "items"]
+                .into_iter()
+                .map(|name| (name, report.variables.get(name).cloned().unwrap_or(Value::Null)))
+                .chain([("object", object)])
+                .collect();
+            let described: Vec<(&str, &str, String)> = values
+                .iter()
+                .map(|(name, value)| (*name, value.type_name(), value.to_display_string()))
+                .collect();
+            let expected = [
+                ("text", "string", "hi"),
+                ("count", "number", "8"),
+                ("ready", "bool", "true"),
+                ("nothing", "null", "null"),
+                ("items", "array", r#"[1,"two"]"#),
+                ("object", "object", r#"{"price":9.5}"#),
+            ];
+            let matches = described.len() == expected.len()
+                && described.iter().zip(expected).all(|(actual, expected)| (actual.0, actual.1, actual.2.as_str()) == expected);
+            // Each one also survives a trip through JSON
+            let round_trips = values.iter().all(|(_, value)| Value::from_json_text(&value.to_json()) == *value);
+            if matches && round_trips {
+                println!("✅ Example 93 evaluated every variant: {:?}", described)
+            } else {
+                println!("❌ Example 93 unexpected values: {:?}", described)
+            }
+        }
+        Err(e) => println!("❌ Example 93 failed: {}", e),
+    }
+    
+    // Example 94: Shadowing with a different type
+    let example94 = r#"
+workflow "ShadowTypes" {
+    let x = 5
+    step 1: if (x > 1) {
+        let x = "str"
+        let y = 1
+        step 2: print(x + y)
+    }
+}
+"#;
+    
+    println!("\n📝 Example 94: Shadowing Warnings");
+    println!("{}", example94);
+    
+    match parse_dsl_example(example94) {
+        Ok(ast) => {
+            let messages = |diagnostics: Vec<trademinutes_dsl::Diagnostic>| -> Vec<(trademinutes_dsl::Severity, String)> {
+                diagnostics.into_iter().map(|diagnostic| (diagnostic.severity, diagnostic.message)).collect()
+            };
+            let warned = messages(analyze_program(&ast));
+            let allowed = messages(trademinutes_dsl::Analyzer::new().allow_shadowing("x").analyze(&ast));
+            let expected = [(
+                trademinutes_dsl::Severity::Warning,
+                "Variable 'x' shadows an outer number binding with a string value".to_string(),
+            )];
+            // `y` shadows nothing, so it is never mentioned
+            if warned == expected && allowed.is_empty() {
+                println!("✅ Example 94 warned once and stayed quiet when allowed: {}", warned[0].1)
+            } else {
+                println!("❌ Example 94 unexpected diagnostics: {:?} / allowed {:?}", warned, allowed)
+            }
+        }
+        Err(e) => println!("❌ Example 94 failed: {}", e),
+    }
+    
+    // Example 95: Token columns place the caret at the missing colon
+    let example95 = "workflow \"Caret\" {\n  step 1 print(";
+    
+    println!("\n📝 Example 95: Error Columns");
+    println!("{}", example95);
+    
+    let columns = Lexer::new(example95)
+        .tokenize()
+        .map(|tokens| tokens.iter().filter(|token| token.line == 2).map(|token| (token.lexeme.clone(), token.column)).collect::<Vec<_>>());
+    match (columns, parse_dsl_example(example95)) {
+        (Ok(columns), Err(e))
+            if columns.starts_with(&[("step".to_string(), 3), ("1".to_string(), 8), ("print".to_string(), 10)])
+                && e.to_string() == "Expected ':' after step number, found 'print' at line 2, column 10" =>
+        {
+            println!("✅ Example 95 pointed at column 10: {}", e)
+        }
+        (columns, result) => println!("❌ Example 95 unexpected columns {:?} or result {:?}", columns, result.err()),
+    }
+    
+    // Example 96: Building an object with set and del
+    let example96 = r#"
+workflow "Profile" {
+    step 1: set(null, "name", "Ada")
+    step 2: set(step 1.data, "role", "admin")
+    step 3: set(step 2.data, "role", "owner")
+    step 4: del(step 3.data, "name")
+    step 5: del(step 4.data, "missing")
+}
+"#;
+    
+    println!("\n📝 Example 96: Object Mutation");
+    println!("{}", example96);
+    
+    match run_dsl_with_report(example96) {
+        Ok(report) => {
+            let objects: Vec<String> = (1..=5)
+                .filter_map(|id| report.step("Profile", id).map(|result| result.data.to_json()))
+                .collect();
+            // Every step returns a new object and leaves the one it was given alone
+            let expected = [
+                r#"{"name":"Ada"}"#,
+                r#"{"name":"Ada","role":"admin"}"#,
+                r#"{"name":"Ada","role":"owner"}"#,
+                r#"{"role":"owner"}"#,
+                r#"{"role":"owner"}"#,
+            ];
+            if objects == expected {
+                println!("✅ Example 96 built the object step by step: {:?}", objects)
+            } else {
+                println!("❌ Example 96 unexpected objects: {:?}", objects)
+            }
+        }
+        Err(e) => println!("❌ Example 96 failed: {}", e),
+    }
+    
+    // Example 97: Structural equality of arrays and objects
+    let example97 = r#"
+workflow "Equality" {
+    step 1: set(null, "a", 1)
+    step 2: set(step 1.data, "b", 2)
+    step 3: set(null, "b", 2)
+    step 4: set(step 3.data, "a", 1)
+    step 5: print([1, 2] == [1, 2])
+    step 6: print([1, 2] == [2, 1])
+    step 7: print(step 2.data == step 4.data)
+    step 8: print(step 2.data != step 4.data)
+}
+"#;
+    
+    println!("\n📝 Example 97: Structural Equality");
+    println!("{}", example97);
+    
+    match run_dsl_with_report(example97) {
+        Ok(report) => {
+            let results: Vec<String> = (5..=8)
+                .filter_map(|id| report.step("Equality", id).map(|result| result.data.to_display_string()))
+                .collect();
+            // Arrays compare in order, objects whatever order their keys were added in
+            if results == ["true", "false", "true", "false"] {
+                println!("✅ Example 97 compared structurally: {:?}", results)
+            } else {
+                println!("❌ Example 97 unexpected results: {:?}", results)
+            }
+        }
+        Err(e) => println!("❌ Example 97 failed: {}", e),
+    }
+    
+    // Example 98: Case-insensitive keywords
+    let example98 = r#"WORKFLOW "x" { STEP 1: print("hi") }"#;
+    
+    println!("\n📝 Example 98: Case-Insensitive Keywords");
+    println!("{}", example98);
+    
+    let parse = |case_insensitive: bool| {
+        Lexer::new(example98)
+            .with_case_insensitive_keywords(case_insensitive)
+            .tokenize()
+            .and_then(|tokens| Parser::new(tokens).parse())
+    };
+    match (parse(true), parse(false)) {
+        (Ok(ast), Err(e)) if ast.workflows.len() == 1 && ast.workflows[0].name == "x" && ast.workflows[0].steps.len() == 1 => {
+            println!("✅ Example 98 parsed uppercase keywords only when asked; by default: {}", e)
+        }
+        (with, without) => println!("❌ Example 98 unexpected results: {:?} / {:?}", with.err(), without.map(|ast| ast.workflows.len())),
+    }
+    
+    // Example 99: Logical operators
+    let example99 = r#"
+workflow "Logic" {
+    step 1: print(true && false)
+    step 2: print(false || true)
+    step 3: print(!(1 == 1))
+    step 4: print(false && missing)
+    step 5: print(true || missing)
+}
+"#;
+    
+    println!("\n📝 Example 99: Logical Operators");
+    println!("{}", example99);
+    
+    match run_dsl_with_report(example99) {
+        Ok(report) => {
+            let results: Vec<String> = (1..=5)
+                .filter_map(|id| report.step("Logic", id).map(|result| result.data.to_display_string()))
+                .collect();
+            // Steps 4 and 5 never evaluate the undefined `missing`
+            if results == ["false", "true", "false", "false", "true"] {
+                println!("✅ Example 99 evaluated and short-circuited: {:?}", results)
+            } else {
+                println!("❌ Example 99 unexpected results: {:?}", results)
+            }
+        }
+        Err(e) => println!("❌ Example 99 failed: {}", e),
+    }
+    
+    // Example 100: Explaining a comparison
+    let example100 = r#"
+let limit = 100
+workflow "Pricing" {
+    step 1: set(null, "price", 120)
+    step 2: if (step 1.data.price > limit) {
+        step 3: print("expensive")
+    }
+}
+"#;
+    
+    println!("\n📝 Example 100: Explain Step");
+    println!("{}", example100);
+    
+    let explained = parse_dsl_example(example100).and_then(|ast| {
+        let mut executor = Executor::new().with_verbose(false);
+        executor.execute(&ast)?;
+        executor.explain_step(&ast, 2)
+    });
+    match explained.as_deref() {
+        Ok([comparison]) => {
+            let value = |trace: &trademinutes_dsl::ExpressionTrace| (trace.expression.clone(), trace.value.clone().ok());
+            let operands: Vec<(String, Option<Value>)> = comparison.children.iter().map(value).collect();
+            let expected = [
+                ("step 1.data.price".to_string(), Some(Value::Number(120.0))),
+                ("limit".to_string(), Some(Value::Number(100.0))),
+            ];
+            if value(comparison) == ("step 1.data.price > limit".to_string(), Some(Value::Bool(true))) && operands == expected {
+                println!("✅ Example 100 explained {} = true from {:?}", comparison.expression, operands)
+            } else {
+                println!("❌ Example 100 unexpected trace: {:?}", comparison)
+            }
+        }
+        other => println!("❌ Example 100 unexpected traces: {:?}", other),
+    }
+    
+    // Example 102: A workflow-level on_error handler reading the error
+    let example102 = r#"
+let handled = ""
+workflow "Orders" {
+    step 1: print("placing order")
+    step 2: missing = 1
+    step 3: print("never reached")
+    on_error(problem) {
+        step 4: print("handled: " + problem)
+        step 5: handled = problem
+    }
+}
+"#;
+    
+    println!("\n📝 Example 102: on_error Handler");
+    println!("{}", example102);
+    
+    let continued = parse_dsl_example(example102)
+        .and_then(|ast| Executor::new().with_verbose(false).with_continue_workflows_on_error(true).execute(&ast));
+    let stopped = run_dsl_with_report(example102);
+    match (&continued, &stopped) {
+        (Ok(report), Err(stopped)) => {
+            let printed = report.step("Orders", 4).map(|result| result.data.to_string());
+            let handled = report.variables.get("handled").map(Value::to_string);
+            let expected = "Step 2 (line 5): Cannot assign to undeclared variable: missing";
+            if printed.as_deref() == Some(&format!("handled: {}", expected))
+                && handled.as_deref() == Some(expected)
+                && report.step("Orders", 3).is_none()
+                && report.failed_workflows.len() == 1
+                && stopped.to_string() == expected
+            {
+                println!("✅ Example 102 ran the handler with the error bound: {}", expected)
+            } else {
+                println!("❌ Example 102 unexpected handler results: {:?} / {:?} / {}", printed, handled, stopped)
+            }
+        }
+        other => println!("❌ Example 102 unexpected runs: {:?}", other),
+    }
+    
+    // Example 103: Incremental parsing matches a full parse over a series of random edits
+    let example103 = r#"
+let threshold = 100
+workflow "Quotes" {
+    step 1: fetch("https://api.com/quotes")
+    step 2: if (step 1.success) {
+        step 3: print(step 1.data)
+    }
+}
+const limit = 5
+workflow "Orders" {
+    let size = 2
+    step 1: print(size * limit)
+    step 2: for item in [1, 2, 3] {
+        step 3: log(item)
+    }
+}
+workflow "Alerts" {
+    step 1: notify("price above ${threshold}")
+}
+"#;
+    
+    println!("\n📝 Example 103: Incremental Parsing Property");
+    println!("{}", example103);
+    
+    // Mostly edits that keep the source valid, with some that break it
+    let fragments = ["", "7", "x", " ", "\n", "step 9: log(1)\n    ", "\"", "}", "{", "workflow \"New\" { step 1: log(2) }\n"];
+    let shape = |program: &trademinutes_dsl::ast::Program| serde_json::to_value(program).ok();
+    let mut random = SeededRandom::new(2024);
+    let mut current = trademinutes_dsl::ParsedSource::parse(example103);
+    let (mut edits, mut failures, mut reused) = (0, 0, 0);
+    let mut mismatch = None;
+    for _ in 0..300 {
+        let Ok(parsed) = &current else { break };
+        let length = parsed.source().chars().count();
+        let start = random.next_index(length + 1);
+        let end = (start + random.next_index(6)).min(length);
+        let replacement = fragments[random.next_index(fragments.len())];
+        
+        let incremental = parsed.apply_edit(start..end, replacement);
+        let mut edited: String = parsed.source().chars().take(start).collect();
+        edited.push_str(replacement);
+        edited.extend(parsed.source().chars().skip(end));
+        let full = parse_dsl_example(&edited);
+        edits += 1;
+        match (&incremental, &full) {
+            (Ok(incremental), Ok(full)) if shape(incremental.program()) == shape(full) => {
+                if incremental.reparsed_items() < full.workflows.len() {
+                    reused += 1;
+                }
+            }
+            // An edit that breaks the source fails either way and is undone
+            (Err(_), Err(_)) => {
+                failures += 1;
+                continue;
+            }
+            _ => {
+                mismatch = Some((start..end, replacement, edited));
+                break;
+            }
+        }
+        current = incremental;
+    }
+    match (mismatch, &current) {
+        (None, Ok(_)) if edits == 300 && failures > 0 && reused > 0 => println!(
+            "✅ Example 103 matched a full parse after {} edits ({} rejected both ways, {} reused untouched items)",
+            edits, failures, reused
+        ),
+        (mismatch, current) => println!(
+            "❌ Example 103 diverged after {} edits: {:?} / {:?}",
+            edits, mismatch, current.as_ref().err()
+        ),
+    }
+    
+    // Example 104: Object keys keep their insertion order
+    let example104 = r#"
+workflow "Ordering" {
+    step 1: set(null, "zeta", 1)
+    step 2: set(step 1.data, "alpha", 2)
+    step 3: set(step 2.data, "mid", 3)
+    step 4: set(step 3.data, "zeta", 4)
+    step 5: del(step 4.data, "alpha")
+    step 6: set(step 5.data, "alpha", 5)
+    step 7: keys(step 4.data)
+    step 8: values(step 4.data)
+    step 9: keys(step 6.data)
+}
+"#;
+    
+    println!("\n📝 Example 104: Object Key Order");
+    println!("{}", example104);
+    
+    // The same order on every run, not just on this one
+    let orders: Result<Vec<Vec<String>>> = (0..5)
+        .map(|_| {
+            let report = run_dsl_with_report(example104)?;
+            Ok((7..=9).filter_map(|id| report.step("Ordering", id).map(|result| result.data.to_json())).collect())
+        })
+        .collect();
+    match orders {
+        Ok(orders) => {
+            // Overwriting keeps a key in place, removing and adding it again moves it to the end
+            let expected = [r#"["zeta","alpha","mid"]"#, "[4,2,3]", r#"["zeta","mid","alpha"]"#];
+            if orders.iter().all(|order| order == &expected) {
+                println!("✅ Example 104 listed keys in insertion order on every run: {:?}", orders[0])
+            } else {
+                println!("❌ Example 104 unexpected key orders: {:?}", orders)
+            }
+        }
+        Err(e) => println!("❌ Example 104 failed: {}", e),
+    }
+    
+    // Example 107: Three-segment property access
+    let example107 = r#"
+workflow "Settings" {
+    step 1: set(null, "host", "db.internal")
+    step 2: set(null, "db", step 1.data)
+    step 3: for config in [step 2.data] {
+        step 4: print(config.db.host)
+    }
+    step 5: print(step 2.data.db.host)
+    step 6: print(step 2.data.db.port)
+}
+"#;
+    
+    println!("\n📝 Example 107: Nested Property Access");
+    println!("{}", example107);
+    
+    // The access chain nests left to right: ((config).db).host
+    fn chain(expression: &Expression) -> Option<(String, Vec<String>)> {
+        match &expression.kind {
+            ExpressionKind::PropertyAccess { object, property } => {
+                let (root, mut properties) = chain(object)?;
+                properties.push(property.clone());
+                Some((root, properties))
+            }
+            ExpressionKind::Identifier(name) => Some((name.clone(), Vec::new())),
+            ExpressionKind::StepReference { step_id, property } => {
+                Some((format!("step {}", step_id), property.iter().cloned().collect()))
+            }
+            _ => None,
+        }
+    }
+    fn argument(step: &trademinutes_dsl::ast::Step) -> Option<&Expression> {
+        match &step.content {
+            StepContent::Command(command) => command.arguments.first(),
+            _ => None,
+        }
+    }
+    match parse_dsl_example(example107) {
+        Ok(ast) => {
+            let steps = &ast.workflows[0].steps;
+            let nested = match &steps[2].content {
+                StepContent::ForEach(for_each) => for_each.body.first().and_then(argument).and_then(chain),
+                _ => None,
+            };
+            let referenced = steps.get(3).and_then(argument).and_then(chain);
+            let expected = (
+                Some(("config".to_string(), vec!["db".to_string(), "host".to_string()])),
+                Some(("step 2".to_string(), vec!["data".to_string(), "db".to_string(), "host".to_string()])),
+            );
+            if (nested.clone(), referenced.clone()) == expected {
+                println!("✅ Example 107 parsed both chains as nested property accesses: {:?} / {:?}", nested, referenced)
+            } else {
+                println!("❌ Example 107 unexpected AST shape: {:?} / {:?}", nested, referenced)
+            }
+            
+            let run = Executor::new().with_verbose(false).execute(&ast);
+            let printed: Vec<String> = [4, 5, 6]
+                .into_iter()
+                .filter_map(|id| run.as_ref().ok()?.step("Settings", id).map(|result| result.data.to_string()))
+                .collect();
+            if printed == ["db.internal", "db.internal", "null"] {
+                println!("✅ Example 107 walked both chains, missing keys give null: {:?}", printed)
+            } else {
+                println!("❌ Example 107 unexpected values: {:?} / {:?}", printed, run.err())
+            }
+        }
+        Err(e) => println!("❌ Example 107 failed to parse: {}", e),
+    }
+    
+    // Example 108: Empty and mixed array literals, and their JSON round trip
+    let example108 = r#"
+let empty = []
+let mixed = [1, "two", [3, 4], 2 + 3, null]
+workflow "Arrays" {
+    step 1: print(empty)
+    step 2: print(mixed)
+}
+"#;
+    
+    println!("\n📝 Example 108: Array Literals");
+    println!("{}", example108);
+    
+    let round_trip = parse_dsl_example(example108).and_then(|ast| {
+        let json = serde_json::to_string(&ast)?;
+        let reparsed: trademinutes_dsl::ast::Program = serde_json::from_str(&json)?;
+        let literals: Vec<serde_json::Value> = ast
+            .variables
+            .iter()
+            .map(|variable| serde_json::to_value(&variable.value.kind))
+            .collect::<serde_json::Result<_>>()?;
+        let same = serde_json::to_string(&reparsed)? == json;
+        Ok((literals, same, run_dsl_with_report(example108)?, trademinutes_dsl::run_program_json(&json)?))
+    });
+    match round_trip {
+        Ok((literals, same, report, from_json)) => {
+            let values = |report: &ExecutionReport| -> Vec<Option<Value>> {
+                ["empty", "mixed"].iter().map(|name| report.variables.get(*name).cloned()).collect()
+            };
+            let expected = [
+                Some(Value::Array(Vec::new())),
+                Some(Value::Array(vec![
+                    Value::Number(1.0),
+                    Value::string("two"),
+                    Value::Array(vec![Value::Number(3.0), Value::Number(4.0)]),
+                    Value::Number(5.0),
+                    Value::Null,
+                ])),
+            ];
+            let empty_literal = serde_json::json!({ "ArrayLiteral": [] });
+            let mixed_length = literals.get(1).and_then(|literal| literal["ArrayLiteral"].as_array()).map(Vec::len);
+            if literals.first() == Some(&empty_literal)
+                && mixed_length == Some(5)
+                && same
+                && values(&report) == expected
+                && values(&from_json) == expected
+            {
+                println!("✅ Example 108 evaluated the literals the same before and after JSON: {:?}", values(&from_json))
+            } else {
+                println!("❌ Example 108 unexpected arrays: {:?} / {} / {:?} / {:?}", literals, same, values(&report), values(&from_json))
+            }
+        }
+        Err(e) => println!("❌ Example 108 failed: {}", e),
+    }
+    
+    // Example 109: Partial application of functions and commands
+    let example109 = r#"
+fn notify_to(channel, message) = "[" + channel + "] " + message
+fn add(a, b) = a + b
+let send = notify_to("slack")
+let increment = add(1)
+let alert = notify("[ops]")
+workflow "Partial" {
+    step 1: send("deploy finished")
+    step 2: print(send("build broken"))
+    step 3: print(add(2)(3) + increment(41))
+    step 4: alert("disk full")
+}
+"#;
+    
+    println!("\n📝 Example 109: Partial Application");
+    println!("{}", example109);
+    
+    match run_dsl_with_report(example109) {
+        Ok(report) => {
+            let data: Vec<String> = (1..=4)
+                .filter_map(|id| report.step("Partial", id).map(|result| result.data.to_string()))
+                .collect();
+            let bound = |name: &str| report.variables.get(name).cloned();
+            let expected_data = ["[slack] deploy finished", "[slack] build broken", "47", "[ops] disk full"];
+            let expected_send = trademinutes_dsl::FunctionValue { name: "notify_to".to_string(), bound: vec![Value::string("slack")] };
+            if data == expected_data
+                && bound("send") == Some(Value::Function(expected_send))
+                && bound("increment").map(|value| value.to_string()).as_deref() == Some("fn add(1)")
+                && report.step("Partial", 4).is_some_and(|result| result.message == "Notification sent successfully")
+            {
+                println!("✅ Example 109 called partially applied functions with the rest: {:?}", data)
+            } else {
+                println!("❌ Example 109 unexpected results: {:?} / {:?}", data, report.variables)
+            }
+        }
+        Err(e) => println!("❌ Example 109 failed: {}", e),
+    }
+    // Functions print back as written, and their names and parameters count as declared
+    let checked = parse_dsl_example(example109).and_then(|ast| {
+        let reparsed = parse_dsl_example(&ast.to_dsl_string())?;
+        Ok((ast.to_dsl_string() == reparsed.to_dsl_string(), trademinutes_dsl::validate_program(&ast), dry_analyze(&ast)))
+    });
+    match checked {
+        Ok((true, undefined, diagnostics)) if undefined.is_empty() && diagnostics.is_empty() => {
+            println!("✅ Example 109 printed, validated and dry-analyzed the functions cleanly")
+        }
+        other => println!("❌ Example 109 unexpected checks: {:?}", other),
+    }
+    let misuses = [
+        ("fn add(a, b) = a + b\nlet three = add(1, 2, 3)", "Function add takes 2 argument(s), got 3"),
+        ("let n = 1\nlet m = n(2)", "Cannot call n, a number"),
+        ("fn a(x) = b(x)\nfn b(x) = a(x)\nlet loop = a(1)", "Recursive function call: a -> b -> a"),
+    ];
+    for (source, expected) in misuses {
+        match run_dsl_with_report(source) {
+            Err(e) if e.to_string() == expected => println!("✅ Example 109 rejected a bad call: {}", e),
+            other => println!("❌ Example 109 expected '{}', got {:?}", expected, other.map(|report| report.variables)),
+        }
+    }
+    
+    // Example 110: Property access on values without properties
+    let example110 = r#"
+workflow "Scalars" {
+    let tags = ["a", "b"]
+    step 1: print("ready")
+    step 2: print(step 1.status.foo)
+    step 3: print(step 1.data.length)
+    step 4: print(tags.first)
+    step 5: if (step 1.status.foo == null) {
+        step 6: print("no such property")
+    }
+}
+"#;
+    
+    println!("\n📝 Example 110: Property Access on Scalars");
+    println!("{}", example110);
+    
+    match run_dsl_with_report(example110) {
+        Ok(report) => {
+            let printed: Vec<String> = [2, 3, 4, 6]
+                .into_iter()
+                .filter_map(|id| report.step("Scalars", id).map(|result| result.data.to_string()))
+                .collect();
+            if printed == ["null", "null", "null", "no such property"] {
+                println!("✅ Example 110 gave null for properties of a number, a string and an array: {:?}", printed)
+            } else {
+                println!("❌ Example 110 unexpected values (not \"200.foo\"): {:?}", printed)
+            }
+        }
+        Err(e) => println!("❌ Example 110 failed: {}", e),
+    }
+    
+    #[cfg(feature = "bincode")]
+    run_binary_program_example();
+    
+    #[cfg(feature = "tokio")]
+    run_async_executor_example();
+    
+    #[cfg(feature = "tokio")]
+    run_async_executor_panic_example();
+    
+    #[cfg(feature = "http")]
+    run_fetch_retry_example();
+    
+    #[cfg(feature = "http")]
+    run_fetch_timeout_example();
+    
+    #[cfg(feature = "http")]
+    run_fetch_mock_server_example();
+    
+    #[cfg(feature = "http")]
+    run_fetch_json_example();
+    
+    #[cfg(feature = "ai")]
+    run_ai_example();
+    
+    #[cfg(feature = "otel")]
+    run_tracing_spans_example();
+}
+
+/// Example 62: answers every `input` step from a fixed table, with an out-of-range age
+struct CannedAnswers;
+
+impl InputProvider for CannedAnswers {
+    fn provide(&mut self, request: &InputRequest) -> Option<Value> {
+        match request.variable.as_str() {
+            "name" => Some(Value::string("Ann")),
+            "age" if request.rules.iter().any(|rule| rule == "min:0") => Some(Value::Number(-5.0)),
+            _ => None,
+        }
+    }
+}
+
+/// Example 43: counts `Command` nodes by name, everything else uses the default walk
+#[derive(Default)]
+struct CommandCounter {
+    counts: BTreeMap<String, usize>,
+}
+
+impl Visitor for CommandCounter {
+    fn visit_command(&mut self, command: &Command) {
+        *self.counts.entry(command.name.clone()).or_insert(0) += 1;
+        walk_command(self, command);
+    }
+}
+
+/// Example 43: renames one command everywhere
+struct CommandRenamer {
+    from: &'static str,
+    to: &'static str,
+}
+
+impl VisitorMut for CommandRenamer {
+    fn visit_command_mut(&mut self, command: &mut Command) {
+        if command.name == self.from {
+            command.name = self.to.to_string();
+        }
+    }
+}
+
+/// Example 64: a compiled program saved as bytes and loaded back
+#[cfg(feature = "bincode")]
+fn run_binary_program_example() {
+    use trademinutes_dsl::CompiledProgram;
+    
+    let example64 = r#"
+const FEE = 0x10
+workflow "Orders" {
+    let total = 0
+    step 1: for price in [100, 250.5, 1e3] {
+        total = total + price + FEE
+    }
+    step 2: print("total ${total}")
+    step 3: if (total > 1000) {
+        step 4: store("big_order", total)
+    }
+}
+"#;
+    
+    println!("\n📝 Example 64: Binary Compiled Programs");
+    println!("{}", example64);
+    
+    let outcome = compile_dsl(example64).and_then(|compiled| {
+        let bytes = compiled.to_bytes()?;
+        let reloaded = CompiledProgram::from_bytes(&bytes)?;
+        let run = |program: &CompiledProgram| -> anyhow::Result<Vec<(u32, bool, String)>> {
+            let report = program.execute(&mut Executor::new().with_verbose(false))?;
+            Ok(report.steps["Orders"].iter().map(|(id, result)| (*id, result.success, result.data.to_json())).collect())
+        };
+        let mut tampered = bytes.clone();
+        tampered[4] = 99;
+        Ok((bytes.len(), run(&compiled)?, run(&reloaded)?, CompiledProgram::from_bytes(&tampered).err()))
+    });
+    match outcome {
+        Ok((size, original, reloaded, Some(rejected))) if original == reloaded && original.len() == 2 => {
+            println!("✅ Example 64 reloaded {} bytes with identical results and rejected a bad version: {}", size, rejected)
+        }
+        Ok((_, original, reloaded, rejected)) => {
+            println!("❌ Example 64 mismatch: {:?} vs {:?}, tampered error {:?}", original, reloaded, rejected)
+        }
+        Err(e) => println!("❌ Example 64 failed: {}", e),
+    }
+    
+    // Example 91: the stored position of every expression variant, pinned to the format
+    // version; reordering variants without bumping the version makes bytes misread
+    println!("\n📝 Example 91: Binary Variant Layout");
+    
+    const PINNED_VERSION: u32 = 4;
+    let text = |value: &str| value.to_string();
+    let number = || Box::new(trademinutes_dsl::Expression::number(1.0));
+    let variants = [
+        ("StringLiteral", ExpressionKind::StringLiteral(text("a"))),
+        ("NumberLiteral", ExpressionKind::NumberLiteral(1.0)),
+        ("Identifier", ExpressionKind::Identifier(text("a"))),
+        ("ArrayLiteral", ExpressionKind::ArrayLiteral(Vec::new())),
+        ("BinaryExpression", ExpressionKind::BinaryExpression { left: number(), operator: text("+"), right: number() }),
+        ("UnaryExpression", ExpressionKind::UnaryExpression { operator: text("-"), operand: number() }),
+        ("PropertyAccess", ExpressionKind::PropertyAccess { object: number(), property: text("a") }),
+        ("StepReference", ExpressionKind::StepReference { step_id: 1, property: None }),
+        ("PreviousStep", ExpressionKind::PreviousStep { property: None }),
+        ("WorkflowReference", ExpressionKind::WorkflowReference { name: text("a") }),
+        ("Interpolation", ExpressionKind::Interpolation(Vec::new())),
+        ("EnvironmentVariable", ExpressionKind::EnvironmentVariable { name: text("a"), default: None }),
+        ("Call", ExpressionKind::Call { callee: number(), arguments: Vec::new() }),
+    ];
+    let tags: Vec<(&str, Option<u32>)> = variants
+        .into_iter()
+        .map(|(name, kind)| {
+            let bytes = bincode::serialize(&kind).unwrap_or_default();
+            (name, bytes.get(..4).and_then(|tag| tag.try_into().ok()).map(u32::from_le_bytes))
+        })
+        .collect();
+    let version = compile_dsl(r#"workflow "W" { step 1: print("a") }"#)
+        .and_then(|compiled| compiled.to_bytes())
+        .ok()
+        .and_then(|bytes| bytes.get(4..8).and_then(|version| version.try_into().ok()).map(u32::from_le_bytes));
+    let in_order = tags.iter().enumerate().all(|(position, (_, tag))| *tag == Some(position as u32));
+    match version {
+        Some(PINNED_VERSION) if in_order => println!("✅ Example 91 variant tags match format version {}", PINNED_VERSION),
+        Some(PINNED_VERSION) => println!("❌ Example 91 variants moved without a format version bump: {:?}", tags),
+        // A bumped version needs the table above and the pin brought up to date
+        other => println!("❌ Example 91 pinned version {} but programs are written as {:?}", PINNED_VERSION, other),
+    }
+}
+
+/// Example 61: `fetch` with retries against an endpoint that fails twice, then answers
+#[cfg(feature = "http")]
+fn run_fetch_retry_example() {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    
+    let listener = match TcpListener::bind("127.0.0.1:0") {
+        Ok(listener) => listener,
+        Err(e) => return println!("❌ Example 61 could not start the mock endpoint: {}", e),
+    };
+    let address = listener.local_addr().map(|addr| addr.to_string()).unwrap_or_default();
+    std::thread::spawn(move || {
+        for (attempt, stream) in listener.incoming().take(3).enumerate() {
+            let Ok(mut stream) = stream else { continue };
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request);
+            let (status, body) = if attempt < 2 { ("500 Internal Server Error", "down") } else { ("200 OK", "{\"price\":42}") };
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status, body.len(), body
+            );
+        }
+    });
+    
+    let dsl = format!("workflow \"Flaky\" {{\n    step 1: fetch(\"http://{}/price\", retries: 3)\n}}", address);
+    println!("{}", dsl);
+    
+    let report = parse_dsl_example(&dsl).and_then(|ast| {
+        Executor::new().with_verbose(false).with_retry_backoff(std::time::Duration::from_millis(5)).execute(&ast)
+    });
+    match report.map(|report| report.step("Flaky", 1).cloned()) {
+        Ok(Some(result)) if result.success && result.message.ends_with("(after 3 attempts)") => {
+            println!("✅ Example 61 fetch succeeded on the third attempt: {} {}", result.data, result.message)
+        }
+        Ok(other) => println!("❌ Example 61 unexpected fetch result: {:?}", other),
+        Err(e) => println!("❌ Example 61 fetch failed: {}", e),
+    }
+}
+
+/// Example 58: `fetch` with a timeout against an endpoint that never answers
+#[cfg(feature = "http")]
+fn run_fetch_timeout_example() {
+    use std::net::TcpListener;
+    use std::time::{Duration, Instant};
+    
+    println!("\n📝 Example 58: Fetch Timeouts");
+    
+    let listener = match TcpListener::bind("127.0.0.1:0") {
+        Ok(listener) => listener,
+        Err(e) => return println!("❌ Example 58 could not start the mock endpoint: {}", e),
+    };
+    let address = listener.local_addr().map(|addr| addr.to_string()).unwrap_or_default();
+    // Hold the connection open without answering, long after the fetch gives up
+    std::thread::spawn(move || {
+        if let Ok((_stream, _)) = listener.accept() {
+            std::thread::sleep(Duration::from_secs(3));
+        }
+    });
+    
+    let dsl = format!("workflow \"Slow\" {{\n    step 1: fetch(\"http://{}/slow\", 200)\n}}", address);
+    println!("{}", dsl);
+    
+    let started = Instant::now();
+    match run_dsl_with_report(&dsl) {
+        Ok(report) => {
+            let elapsed = started.elapsed();
+            match report.step("Slow", 1) {
+                Some(result) if !result.success && result.status == 0 && result.message.contains("timeout")
+                    && elapsed < Duration::from_secs(2) =>
+                {
+                    println!("✅ Example 58 gave up after {:?}: {}", elapsed, result.message)
+                }
+                other => println!("❌ Example 58 unexpected result after {:?}: {:?}", elapsed, other),
+            }
+        }
+        Err(e) => println!("❌ Example 58 failed: {}", e),
+    }
+}
+
+/// Example 105: indexing into the nested JSON body a mock endpoint returns
+#[cfg(feature = "http")]
+fn run_fetch_json_example() {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    
+    println!("\n📝 Example 105: Nested Fetched JSON");
+    
+    let listener = match TcpListener::bind("127.0.0.1:0") {
+        Ok(listener) => listener,
+        Err(e) => return println!("❌ Example 105 could not start the mock endpoint: {}", e),
+    };
+    let address = listener.local_addr().map(|addr| addr.to_string()).unwrap_or_default();
+    std::thread::spawn(move || {
+        if let Ok((mut stream, _)) = listener.accept() {
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request);
+            let body = r#"{"quote":{"symbol":"AAPL","price":187.5,"venue":{"name":"NASDAQ"}},"tags":["tech"]}"#;
+            let _ = write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body);
+        }
+    });
+    
+    let dsl = format!(
+        r#"workflow "Quote" {{
+    step 1: fetch("http://{}/quote")
+    step 2: print(step 1.data.quote.venue.name)
+    step 3: if (step 1.data.quote.price > 100) {{
+        step 4: print("above 100")
+    }}
+    step 5: if (step 1.data.quote.missing == null && step 1.data.quote.missing.deeper == null) {{
+        step 6: print("missing keys are null")
+    }}
+}}"#,
+        address
+    );
+    println!("{}", dsl);
+    
+    match run_dsl_with_report(&dsl) {
+        Ok(report) => {
+            let printed: Vec<String> = [2, 4, 6]
+                .into_iter()
+                .filter_map(|id| report.step("Quote", id).map(|result| result.data.to_string()))
+                .collect();
+            let price = report.step("Quote", 1).and_then(|result| match &result.data {
+                Value::Object(fields) => match fields.get("quote") {
+                    Some(Value::Object(quote)) => quote.get("price").cloned(),
+                    _ => None,
+                },
+                _ => None,
+            });
+            if printed == ["NASDAQ", "above 100", "missing keys are null"] && price == Some(Value::Number(187.5)) {
+                println!("✅ Example 105 read nested fields from the fetched body: {:?}", printed)
+            } else {
+                println!("❌ Example 105 unexpected results: {:?} / {:?}", printed, price)
+            }
+        }
+        Err(e) => println!("❌ Example 105 failed: {}", e),
+    }
+}
+
+/// Example 101: real `fetch` requests against mock endpoints: an error status with its
+/// body, a host outside the allow-list that is never contacted, a `timeout_ms` that
+/// runs out and a refused connection
+#[cfg(feature = "http")]
+fn run_fetch_mock_server_example() {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    
+    println!("\n📝 Example 101: Fetch Against Mock Servers");
+    
+    let bind = || TcpListener::bind("127.0.0.1:0").and_then(|listener| {
+        let address = listener.local_addr()?.to_string();
+        Ok((listener, address))
+    });
+    let (Ok((answering, answering_address)), Ok((silent, silent_address)), Ok((closed, closed_address))) = (bind(), bind(), bind()) else {
+        return println!("❌ Example 101 could not start the mock endpoints");
+    };
+    // Nothing listens here any more, so connecting is refused
+    drop(closed);
+    
+    let connections = Arc::new(AtomicUsize::new(0));
+    let counted = connections.clone();
+    std::thread::spawn(move || {
+        for stream in answering.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            counted.fetch_add(1, Ordering::SeqCst);
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request);
+            let body = "{\"error\":\"no such symbol\"}";
+            let _ = write!(stream, "HTTP/1.1 404 Not Found\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body);
+        }
+    });
+    std::thread::spawn(move || {
+        if let Ok((_stream, _)) = silent.accept() {
+            std::thread::sleep(Duration::from_secs(3));
+        }
+    });
+    
+    let dsl = format!(
+        "workflow \"Quotes\" {{\n    step 1: fetch(\"http://{}/quote\")\n    step 2: fetch(\"http://{}/quote\", timeout_ms: 200)\n    step 3: fetch(\"http://{}/quote\")\n}}",
+        answering_address, silent_address, closed_address
+    );
+    println!("{}", dsl);
+    
+    let run = |allowlist: &[&str]| {
+        parse_dsl_example(&dsl).and_then(|ast| Executor::new().with_verbose(false).with_fetch_allowlist(allowlist).execute(&ast))
+    };
+    let allowed = run(&["127.0.0.1"]);
+    let contacted = connections.load(Ordering::SeqCst);
+    let blocked = run(&["api.example.com"]);
+    
+    let summary = |id| {
+        allowed.as_ref().ok().and_then(|report| report.step("Quotes", id)).map(|result| (result.success, result.status, result.data.to_json(), result.message.clone()))
+    };
+    match (summary(1), summary(2), summary(3), &blocked) {
+        (Some((false, 404, body, _)), Some((false, 0, _, timeout)), Some((false, 0, _, refused)), Err(blocked))
+            if body == r#"{"error":"no such symbol"}"#
+                && timeout.contains("timeout")
+                && !refused.is_empty()
+                && blocked.to_string().contains("is not allowed")
+                && contacted == 1
+                && connections.load(Ordering::SeqCst) == 1 =>
+        {
+            println!("✅ Example 101 got a 404 with its body, timed out, was refused and never reached a blocked host: {}", blocked)
+        }
+        (first, second, third, blocked) => println!(
+            "❌ Example 101 unexpected results: {:?} / {:?} / {:?} / {:?}, {} connections",
+            first, second, third, blocked.as_ref().err(), connections.load(Ordering::SeqCst)
+        ),
+    }
+}
+
+/// Example 68: the `AsyncExecutor` awaiting two fetches against a mock endpoint in order,
+/// twice on the same executor
+#[cfg(feature = "tokio")]
+fn run_async_executor_example() {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use trademinutes_dsl::AsyncExecutor;
+    
+    println!("\n📝 Example 68: Async Executor");
+    
+    let listener = match TcpListener::bind("127.0.0.1:0") {
+        Ok(listener) => listener,
+        Err(e) => return println!("❌ Example 68 could not start the mock endpoint: {}", e),
+    };
+    let address = listener.local_addr().map(|addr| addr.to_string()).unwrap_or_default();
+    // Answer each request with the path it asked for and hand back the paths in arrival order
+    let server = std::thread::spawn(move || {
+        let mut paths = Vec::new();
+        for stream in listener.incoming().take(4) {
+            let Ok(mut stream) = stream else { continue };
+            let mut request = [0u8; 1024];
+            let read = stream.read(&mut request).unwrap_or(0);
+            let request = String::from_utf8_lossy(&request[..read]).to_string();
+            let path = request.split_whitespace().nth(1).unwrap_or("").to_string();
+            let body = format!("{{\"path\":\"{}\"}}", path);
+            let _ = write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body);
+            paths.push(path);
+        }
+        paths
+    });
+    
+    let dsl = format!(
+        "workflow \"Quotes\" {{\n    step 1: fetch(\"http://{0}/first\")\n    step 2: fetch(\"http://{0}\" + step 1.data.path + \"/second\")\n}}",
+        address
+    );
+    println!("{}", dsl);
+    
+    let report = parse_dsl_example(&dsl).and_then(|ast| {
+        let runtime = tokio::runtime::Runtime::new()?;
+        let mut executor = AsyncExecutor::from(Executor::new().with_verbose(false));
+        runtime.block_on(executor.execute(&ast))?;
+        runtime.block_on(executor.execute(&ast))
+    });
+    let paths = server.join().unwrap_or_default();
+    match report {
+        Ok(report)
+            if report.steps["Quotes"].values().all(|result| result.success)
+                && report.timings.len() == 2
+                && paths == ["/first", "/first/second", "/first", "/first/second"] =>
+        {
+            println!("✅ Example 68 awaited both fetches in step order: {}", report.steps["Quotes"][&2].data)
+        }
+        Ok(report) => println!("❌ Example 68 unexpected steps {:?} for requests {:?}", report.steps, paths),
+        Err(e) => println!("❌ Example 68 failed: {}", e),
+    }
+}
+
+/// Example 90: a run that panics leaves the `AsyncExecutor` unusable instead of reset
+#[cfg(feature = "tokio")]
+fn run_async_executor_panic_example() {
+    use trademinutes_dsl::AsyncExecutor;
+    
+    println!("\n📝 Example 90: Async Executor After A Panic");
+    
+    let mut executor = Executor::new().with_verbose(false);
+    executor.register_command("boom", |_: &[Value]| -> Result<StepResult> { panic!("boom") });
+    let mut executor = AsyncExecutor::from(executor);
+    let runs = parse_dsl_example(r#"workflow "Crash" { step 1: boom() }"#).and_then(|ast| {
+        let runtime = tokio::runtime::Runtime::new()?;
+        // Keep the expected panic out of the output
+        let hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(|_| {}));
+        let first = runtime.block_on(executor.execute(&ast));
+        std::panic::set_hook(hook);
+        let second = runtime.block_on(executor.execute(&ast));
+        Ok((first, second))
+    });
+    match runs {
+        Ok((Err(first), Err(second))) if executor.executor().is_none() && second.to_string().contains("unusable") => {
+            println!("✅ Example 90 refused to run again: {}; {}", first, second)
+        }
+        Ok((first, second)) => println!("❌ Example 90 unexpected results: {:?} / {:?}", first.err(), second.err()),
+        Err(e) => println!("❌ Example 90 failed: {}", e),
+    }
+}
+
+/// Example 25: `generate` against a local mock of the chat completions API
+/// Example 106: the spans a run emits, collected by a test subscriber: one per executed
+/// step under its workflow's span, with command steps carrying their attributes
+#[cfg(feature = "otel")]
+fn run_tracing_spans_example() {
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    
+    #[derive(Debug, Clone)]
+    struct RecordedSpan {
+        name: &'static str,
+        parent: Option<u64>,
+        fields: BTreeMap<String, String>,
+    }
+    
+    struct Fields<'a>(&'a mut BTreeMap<String, String>);
+    
+    impl Visit for Fields<'_> {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name().to_string(), value.to_string());
+        }
+        
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0.insert(field.name().to_string(), format!("{:?}", value));
+        }
+    }
+    
+    /// Keeps every span with its fields; ids are positions in `spans` plus one
+    #[derive(Default)]
+    struct Recorder {
+        spans: Arc<Mutex<Vec<RecordedSpan>>>,
+        entered: Mutex<Vec<u64>>,
+    }
+    
+    impl tracing::Subscriber for Recorder {
+        fn enabled(&self, _metadata: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+        
+        fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+            let parent = match attributes.parent() {
+                Some(parent) => Some(parent.into_u64()),
+                None if attributes.is_contextual() => self.entered.lock().ok().and_then(|entered| entered.last().copied()),
+                None => None,
+            };
+            let mut fields = BTreeMap::new();
+            attributes.record(&mut Fields(&mut fields));
+            let mut spans = self.spans.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            spans.push(RecordedSpan { name: attributes.metadata().name(), parent, fields });
+            Id::from_u64(spans.len() as u64)
+        }
+        
+        fn record(&self, span: &Id, values: &Record<'_>) {
+            let mut spans = self.spans.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            if let Some(recorded) = spans.get_mut(span.into_u64() as usize - 1) {
+                values.record(&mut Fields(&mut recorded.fields));
+            }
+        }
+        
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+        
+        fn event(&self, _event: &tracing::Event<'_>) {}
+        
+        fn enter(&self, span: &Id) {
+            if let Ok(mut entered) = self.entered.lock() {
+                entered.push(span.into_u64());
+            }
+        }
+        
+        fn exit(&self, span: &Id) {
+            if let Ok(mut entered) = self.entered.lock() {
+                if let Some(index) = entered.iter().rposition(|id| *id == span.into_u64()) {
+                    entered.remove(index);
+                }
+            }
+        }
+    }
+    
+    let example106 = r#"
+workflow "Traced" {
+    step 1: print("quote")
+    step 2: if (step 1.success) {
+        step 3: log("fetched")
+    }
+    step 4: print("skipped") when (false)
+}
+"#;
+    
+    println!("\n📝 Example 106: Tracing Spans");
+    println!("{}", example106);
+    
+    let recorder = Recorder::default();
+    let spans = recorder.spans.clone();
+    let run = tracing::subscriber::with_default(recorder, || run_dsl_with_report(example106));
+    let spans = spans.lock().map(|spans| spans.clone()).unwrap_or_default();
+    
+    let field = |span: &RecordedSpan, name: &str| span.fields.get(name).cloned().unwrap_or_default();
+    let workflows: Vec<(usize, &RecordedSpan)> =
+        spans.iter().enumerate().filter(|(_, span)| span.name == "workflow").collect();
+    let steps: Vec<&RecordedSpan> = spans.iter().filter(|span| span.name == "step").collect();
+    let step_ids: Vec<String> = steps.iter().map(|span| field(span, "step_id")).collect();
+    let command_steps: Vec<(String, String, String)> = steps
+        .iter()
+        .filter(|span| span.fields.contains_key("command"))
+        .map(|span| (field(span, "step_id"), field(span, "command"), field(span, "status")))
+        .collect();
+    let nested = |span: &RecordedSpan, parent: u64| span.parent == Some(parent);
+    let timed = steps
+        .iter()
+        .filter(|span| span.fields.contains_key("command"))
+        .all(|span| field(span, "success") == "true" && field(span, "duration_ms").parse::<f64>().is_ok_and(|ms| ms >= 0.0));
+    
+    match (run, workflows.as_slice()) {
+        (Ok(_), [(index, workflow)]) => {
+            let workflow_id = *index as u64 + 1;
+            // Step 3 sits inside the conditional's span, the others directly under the workflow
+            let step_id = |id: &str| spans.iter().position(|span| span.name == "step" && field(span, "step_id") == id).map(|index| index as u64 + 1);
+            let parents_ok = steps.iter().all(|span| match field(span, "step_id").as_str() {
+                "3" => step_id("2").is_some_and(|parent| nested(span, parent)),
+                _ => nested(span, workflow_id),
+            });
+            let expected_commands = [
+                ("1".to_string(), "print".to_string(), "200".to_string()),
+                ("3".to_string(), "log".to_string(), "200".to_string()),
+            ];
+            if field(workflow, "name") == "Traced" && step_ids == ["1", "2", "3"] && command_steps == expected_commands && parents_ok && timed {
+                println!("✅ Example 106 recorded a span per executed step: {:?}", command_steps)
+            } else {
+                println!("❌ Example 106 unexpected spans: {:?}", spans)
+            }
+        }
+        (run, _) => println!("❌ Example 106 unexpected run: {:?} / {:?}", run.err(), spans),
+    }
+}
+
+#[cfg(feature = "ai")]
+fn run_ai_example() {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    
+    println!("\n📝 Example 25: Generate With The ai Feature");
+    
+    let listener = match TcpListener::bind("127.0.0.1:0") {
+        Ok(listener) => listener,
+        Err(e) => return println!("❌ Example 25 could not start the mock endpoint: {}", e),
+    };
+    let endpoint = format!("http://{}/v1/chat/completions", listener.local_addr().map(|addr| addr.to_string()).unwrap_or_default());
+    
+    // Answer a single request and hand back the body it was sent
+    let server = std::thread::spawn(move || -> std::io::Result<String> {
+        let (stream, _) = listener.accept()?;
+        let mut reader = BufReader::new(stream);
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line)?;
+            if line == "\r\n" || line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap_or(0);
+                }
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body)?;
+        
+        let reply = r#"{"choices":[{"message":{"role":"assistant","content":"Markets were calm."}}]}"#;
+        write!(
+            reader.get_mut(),
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            reply.len(),
+            reply
+        )?;
+        Ok(String::from_utf8_lossy(&body).to_string())
+    });
+    
+    std::env::set_var("AI_API_KEY", "test-key");
+    let dsl_code = r#"workflow "AiExample" { step 1: generate("Summarize the market", "mistral-small-latest", "0.2") }"#;
+    let report = parse_dsl_example(dsl_code)
+        .and_then(|ast| Executor::new().with_verbose(false).with_ai_endpoint(&endpoint).execute(&ast));
+    let request = server.join().ok().and_then(|body| body.ok()).unwrap_or_default();
+    
+    match report {
+        Ok(report) if report.steps["AiExample"][&1].data == Value::string("Markets were calm.")
+            && request.contains("Summarize the market")
+            && request.contains("\"temperature\":0.2") => {
+            println!("✅ Example 25 sent the prompt and temperature and stored the completion");
+        }
+        Ok(report) => println!("❌ Example 25 unexpected result {:?} for request {}", report.step("AiExample", 1), request),
+        Err(e) => println!("❌ Example 25 failed: {}", e),
+    }
 }
 
 pub fn test_tokenization() {
@@ -186,12 +4193,12 @@ fn run_dsl_example(dsl_code: &str) -> Result<()> {
     Ok(())
 }
 
-fn parse_dsl_example(dsl_code: &str) -> Result<crate::ast::Program> {
+fn parse_dsl_example(dsl_code: &str) -> Result<trademinutes_dsl::ast::Program> {
     let tokens = Lexer::new(dsl_code).tokenize()?;
     let ast = Parser::new(tokens).parse()?;
     Ok(ast)
 }
 
-fn tokenize_dsl_example(dsl_code: &str) -> Result<Vec<crate::lexer::Token>> {
+fn tokenize_dsl_example(dsl_code: &str) -> Result<Vec<trademinutes_dsl::lexer::Token>> {
     Lexer::new(dsl_code).tokenize()
 } 
\ No newline at end of file
@@ -0,0 +1,267 @@
+use anyhow::Result;
+use std::collections::HashSet;
+use crate::ast::*;
+use crate::error::DslError;
+
+impl Program {
+    /// Check step ids without executing anything: ids must be unique within a
+    /// workflow, and `step N` may only refer to a step defined earlier in the same
+    /// workflow; other workflows' results are read with `workflow("Name").result`.
+    /// `on_error` handlers run last, so they may refer to any step of their workflow.
+    pub fn validate(&self) -> Result<()> {
+        for workflow in &self.workflows {
+            let mut checker = StepIdChecker { workflow: &workflow.name, defined: HashSet::new() };
+            for variable in &workflow.variables {
+                checker.expression(&variable.value)?;
+            }
+            checker.steps(&workflow.steps)?;
+            if let Some(handler) = &workflow.on_error {
+                checker.steps(&handler.steps)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Every reference to a variable that isn't declared where it is used, found without
+/// executing anything. Declarations only count from their own position onwards and
+/// block, loop and handler bindings only inside their block.
+pub fn validate_program(program: &Program) -> Vec<DslError> {
+    let mut checker = VariableChecker { scopes: vec![HashSet::new()], errors: Vec::new() };
+    for variable in &program.variables {
+        checker.declaration(variable);
+    }
+    // Functions can be named anywhere, and their bodies see every program-level variable
+    checker.scopes[0].extend(program.functions.iter().map(|function| function.name.clone()));
+    for function in &program.functions {
+        checker.scopes.push(function.parameters.iter().cloned().collect());
+        checker.expression(&function.body);
+        checker.scopes.pop();
+    }
+    for workflow in &program.workflows {
+        checker.scopes.push(HashSet::new());
+        for variable in &workflow.variables {
+            checker.declaration(variable);
+        }
+        checker.steps(&workflow.steps);
+        if let Some(handler) = &workflow.on_error {
+            checker.block_with(&handler.variable, &handler.steps);
+        }
+        checker.scopes.pop();
+    }
+    checker.errors
+}
+
+struct VariableChecker {
+    scopes: Vec<HashSet<String>>,
+    errors: Vec<DslError>,
+}
+
+impl VariableChecker {
+    fn steps(&mut self, steps: &[Step]) {
+        for step in steps {
+            if let Some(guard) = &step.guard {
+                self.expression(guard);
+            }
+            match &step.content {
+                StepContent::Command(command) => {
+                    for argument in command.arguments.iter().chain(command.named_arguments.values()) {
+                        self.expression(argument);
+                    }
+                }
+                StepContent::Conditional(conditional) => {
+                    self.expression(&conditional.condition);
+                    self.block(&conditional.if_steps);
+                    if let Some(else_steps) = &conditional.else_steps {
+                        self.block(else_steps);
+                    }
+                }
+                StepContent::WhileLoop(while_loop) => {
+                    self.expression(&while_loop.condition);
+                    self.block(&while_loop.body);
+                }
+                StepContent::ForEach(for_each) => {
+                    self.expression(&for_each.iterable);
+                    self.block_with(&for_each.var_name, &for_each.body);
+                }
+                StepContent::TryCatch(try_catch) => {
+                    self.block(&try_catch.try_steps);
+                    self.block_with(&try_catch.catch_variable, &try_catch.catch_steps);
+                }
+                StepContent::CallWorkflow(_) => {}
+                StepContent::Return(return_statement) => {
+                    if let Some(value) = &return_statement.value {
+                        self.expression(value);
+                    }
+                }
+                StepContent::Assignment(assignment) => {
+                    self.expression(&assignment.value);
+                    self.name(&assignment.name);
+                }
+                StepContent::Declaration(variable) => self.declaration(variable),
+            }
+        }
+    }
+
+    fn block(&mut self, steps: &[Step]) {
+        self.scopes.push(HashSet::new());
+        self.steps(steps);
+        self.scopes.pop();
+    }
+
+    /// A block with `name` bound inside it, like a loop variable
+    fn block_with(&mut self, name: &str, steps: &[Step]) {
+        self.scopes.push(HashSet::from([name.to_string()]));
+        self.steps(steps);
+        self.scopes.pop();
+    }
+
+    fn declaration(&mut self, variable: &VariableDeclaration) {
+        // The initializer can't see the name it is declaring
+        self.expression(&variable.value);
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(variable.name.clone());
+        }
+    }
+
+    fn name(&mut self, name: &str) {
+        let declared = ["true", "false", "null"].contains(&name)
+            || self.scopes.iter().any(|scope| scope.contains(name));
+        if !declared {
+            self.errors.push(DslError::UndefinedVariable { name: name.to_string() });
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        match &expression.kind {
+            ExpressionKind::Identifier(name) => self.name(name),
+            ExpressionKind::ArrayLiteral(elements) => elements.iter().for_each(|element| self.expression(element)),
+            ExpressionKind::BinaryExpression { left, right, .. } => {
+                self.expression(left);
+                self.expression(right);
+            }
+            ExpressionKind::UnaryExpression { operand, .. } => self.expression(operand),
+            ExpressionKind::PropertyAccess { object, .. } => self.expression(object),
+            ExpressionKind::Interpolation(parts) => {
+                parts.iter().filter_map(InterpolationPart::expression).for_each(|expression| self.expression(expression))
+            }
+            ExpressionKind::Call { callee, arguments } => {
+                // Commands are called by name too, and which exist is only known when running
+                if !matches!(callee.kind, ExpressionKind::Identifier(_)) {
+                    self.expression(callee);
+                }
+                arguments.iter().for_each(|argument| self.expression(argument));
+            }
+            ExpressionKind::StringLiteral(_)
+            | ExpressionKind::NumberLiteral(_)
+            | ExpressionKind::StepReference { .. }
+            | ExpressionKind::PreviousStep { .. }
+            | ExpressionKind::WorkflowReference { .. }
+            | ExpressionKind::EnvironmentVariable { .. } => {}
+        }
+    }
+}
+
+struct StepIdChecker<'a> {
+    workflow: &'a str,
+    defined: HashSet<u32>,
+}
+
+impl StepIdChecker<'_> {
+    fn steps(&mut self, steps: &[Step]) -> Result<()> {
+        for step in steps {
+            if let Some(guard) = &step.guard {
+                self.expression(guard)?;
+            }
+            match &step.content {
+                StepContent::Command(command) => {
+                    for argument in command.arguments.iter().chain(command.named_arguments.values()) {
+                        self.expression(argument)?;
+                    }
+                    self.define(step.id)?;
+                }
+                StepContent::Conditional(conditional) => self.conditional(step.id, conditional, false)?,
+                StepContent::WhileLoop(while_loop) => {
+                    self.expression(&while_loop.condition)?;
+                    self.define(step.id)?;
+                    self.steps(&while_loop.body)?;
+                }
+                StepContent::ForEach(for_each) => {
+                    self.expression(&for_each.iterable)?;
+                    self.define(step.id)?;
+                    self.steps(&for_each.body)?;
+                }
+                StepContent::TryCatch(try_catch) => {
+                    self.define(step.id)?;
+                    self.steps(&try_catch.try_steps)?;
+                    self.steps(&try_catch.catch_steps)?;
+                }
+                StepContent::CallWorkflow(_) => self.define(step.id)?,
+                StepContent::Return(return_statement) => {
+                    if let Some(value) = &return_statement.value {
+                        self.expression(value)?;
+                    }
+                }
+                StepContent::Assignment(assignment) => self.expression(&assignment.value)?,
+                StepContent::Declaration(variable) => self.expression(&variable.value)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn conditional(&mut self, id: u32, conditional: &ConditionalStatement, is_else_if: bool) -> Result<()> {
+        self.expression(&conditional.condition)?;
+        if !is_else_if {
+            self.define(id)?;
+        }
+        self.steps(&conditional.if_steps)?;
+        match conditional.else_steps.as_deref() {
+            // `else if` is a nested conditional that shares the id of the `if`
+            Some([Step { id: nested_id, content: StepContent::Conditional(nested), .. }]) if *nested_id == id => {
+                self.conditional(id, nested, true)
+            }
+            Some(else_steps) => self.steps(else_steps),
+            None => Ok(()),
+        }
+    }
+
+    fn define(&mut self, id: u32) -> Result<()> {
+        // Bare statements such as assignments all use id 0
+        if id == 0 {
+            return Ok(());
+        }
+        if !self.defined.insert(id) {
+            return Err(DslError::DuplicateStepId { step_id: id, workflow: self.workflow.to_string() }.into());
+        }
+        Ok(())
+    }
+
+    fn expression(&self, expression: &Expression) -> Result<()> {
+        match &expression.kind {
+            ExpressionKind::StepReference { step_id, .. } if !self.defined.contains(step_id) => {
+                Err(DslError::UndefinedStepReference { step_id: *step_id, workflow: self.workflow.to_string() }.into())
+            }
+            ExpressionKind::ArrayLiteral(elements) => elements.iter().try_for_each(|element| self.expression(element)),
+            ExpressionKind::BinaryExpression { left, right, .. } => {
+                self.expression(left)?;
+                self.expression(right)
+            }
+            ExpressionKind::UnaryExpression { operand, .. } => self.expression(operand),
+            ExpressionKind::PropertyAccess { object, .. } => self.expression(object),
+            ExpressionKind::Interpolation(parts) => {
+                parts.iter().filter_map(InterpolationPart::expression).try_for_each(|expression| self.expression(expression))
+            }
+            ExpressionKind::Call { callee, arguments } => {
+                self.expression(callee)?;
+                arguments.iter().try_for_each(|argument| self.expression(argument))
+            }
+            ExpressionKind::StringLiteral(_)
+            | ExpressionKind::NumberLiteral(_)
+            | ExpressionKind::Identifier(_)
+            | ExpressionKind::StepReference { .. }
+            | ExpressionKind::PreviousStep { .. }
+            | ExpressionKind::WorkflowReference { .. }
+            | ExpressionKind::EnvironmentVariable { .. } => Ok(()),
+        }
+    }
+}
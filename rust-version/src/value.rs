@@ -0,0 +1,290 @@
+use anyhow::{anyhow, Result};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum Value {
+    String(String),
+    Number(f64),
+    Bool(bool),
+    Null,
+    Array(Vec<Value>),
+    /// Keys keep insertion order so iteration and output are reproducible
+    Object(IndexMap<String, Value>),
+    /// A function or command waiting for the rest of its arguments; it only exists
+    /// while a program runs, so it is written out as text and never read back
+    #[serde(skip_deserializing)]
+    Function(FunctionValue),
+}
+
+/// A user `fn` or a command, with the arguments it was given so far
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionValue {
+    pub name: String,
+    pub bound: Vec<Value>,
+}
+
+impl FunctionValue {
+    pub fn new(name: &str) -> Self {
+        FunctionValue { name: name.to_string(), bound: Vec::new() }
+    }
+}
+
+impl Value {
+    pub fn string(value: &str) -> Self {
+        Value::String(value.to_string())
+    }
+
+    pub fn object(fields: Vec<(&str, Value)>) -> Self {
+        Value::Object(fields.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+    }
+
+    /// Parse text as JSON, keeping it as a plain string when it isn't valid JSON
+    pub fn from_json_text(text: &str) -> Self {
+        serde_json::from_str(text).unwrap_or_else(|_| Value::string(text))
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Number(_) => "number",
+            Value::Bool(_) => "bool",
+            Value::Null => "null",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+            Value::Function(_) => "function",
+        }
+    }
+
+    /// How many values this one is made of, itself and every element and field
+    /// included, and roughly how many bytes they take
+    pub fn footprint(&self) -> (usize, usize) {
+        let own = std::mem::size_of::<Value>();
+        match self {
+            Value::String(text) => (1, own + text.len()),
+            Value::Array(items) | Value::Function(FunctionValue { bound: items, .. }) => items.iter().fold((1, own), |(values, bytes), item| {
+                let (item_values, item_bytes) = item.footprint();
+                (values + item_values, bytes + item_bytes)
+            }),
+            Value::Object(fields) => fields.iter().fold((1, own), |(values, bytes), (key, field)| {
+                let (field_values, field_bytes) = field.footprint();
+                (values + field_values, bytes + key.len() + field_bytes)
+            }),
+            Value::Number(_) | Value::Bool(_) | Value::Null => (1, own),
+        }
+    }
+
+    /// Numeric view of the value; numeric strings count so step data can take part in math
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Value::Number(number) => Some(*number),
+            Value::String(text) => text.parse::<f64>().ok().filter(|number| number.is_finite()),
+            _ => None,
+        }
+    }
+
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::String(text) => !text.is_empty() && text != "0" && text != "false",
+            Value::Number(number) => *number != 0.0,
+            Value::Bool(flag) => *flag,
+            Value::Null => false,
+            Value::Array(items) => !items.is_empty(),
+            Value::Object(fields) => !fields.is_empty(),
+            Value::Function(_) => true,
+        }
+    }
+
+    /// Text used by print/log and string concatenation
+    pub fn to_display_string(&self) -> String {
+        match self {
+            Value::String(text) => text.clone(),
+            Value::Number(number) => format_number(*number),
+            Value::Bool(flag) => flag.to_string(),
+            Value::Null => "null".to_string(),
+            Value::Array(_) | Value::Object(_) => self.to_json(),
+            Value::Function(function) => {
+                let bound: Vec<String> = function.bound.iter().map(Value::to_json).collect();
+                format!("fn {}({})", function.name, bound.join(", "))
+            }
+        }
+    }
+
+    /// Compact JSON text
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Block-style YAML text without a trailing newline
+    pub fn to_yaml(&self) -> String {
+        let mut out = String::new();
+        write_yaml(self, 0, &mut out);
+        out.trim_end().to_string()
+    }
+
+    /// CSV text for an array. Arrays of objects get a header row built from every
+    /// key in first-seen order, arrays of arrays are written row by row and
+    /// arrays of scalars become a single column.
+    pub fn to_csv(&self) -> Result<String> {
+        let items = match self {
+            Value::Array(items) => items,
+            other => return Err(anyhow!("to_csv expects an array, got {}", other.type_name())),
+        };
+
+        let rows: Vec<Vec<String>> = if !items.is_empty() && items.iter().all(|item| matches!(item, Value::Object(_))) {
+            let mut headers: Vec<&String> = Vec::new();
+            for item in items {
+                if let Value::Object(fields) = item {
+                    for key in fields.keys() {
+                        if !headers.contains(&key) {
+                            headers.push(key);
+                        }
+                    }
+                }
+            }
+
+            let mut rows = vec![headers.iter().map(|header| header.to_string()).collect()];
+            for item in items {
+                if let Value::Object(fields) = item {
+                    rows.push(headers
+                        .iter()
+                        .map(|header| fields.get(*header).map(Value::to_display_string).unwrap_or_default())
+                        .collect());
+                }
+            }
+            rows
+        } else {
+            items.iter()
+                .map(|item| match item {
+                    Value::Array(cells) => Ok(cells.iter().map(Value::to_display_string).collect()),
+                    Value::Object(_) => Err(anyhow!("to_csv cannot mix objects with other rows")),
+                    scalar => Ok(vec![scalar.to_display_string()]),
+                })
+                .collect::<Result<_>>()?
+        };
+
+        Ok(rows
+            .iter()
+            .map(|row| row.iter().map(|cell| csv_cell(cell)).collect::<Vec<_>>().join(","))
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::String(text) => serializer.serialize_str(text),
+            // Integral numbers serialize without a trailing `.0`, matching the display form
+            Value::Number(number) if number.fract() == 0.0 && number.abs() < 1e15 => {
+                serializer.serialize_i64(*number as i64)
+            }
+            Value::Number(number) => serializer.serialize_f64(*number),
+            Value::Bool(flag) => serializer.serialize_bool(*flag),
+            Value::Null => serializer.serialize_unit(),
+            Value::Array(items) => items.serialize(serializer),
+            Value::Object(fields) => fields.serialize(serializer),
+            Value::Function(_) => serializer.serialize_str(&self.to_display_string()),
+        }
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::string(value)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_display_string())
+    }
+}
+
+fn csv_cell(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+fn write_yaml(value: &Value, indent: usize, out: &mut String) {
+    let pad = " ".repeat(indent);
+    match value {
+        Value::Object(fields) if !fields.is_empty() => {
+            for (key, field) in fields {
+                if is_yaml_block(field) {
+                    out.push_str(&format!("{}{}:\n", pad, yaml_string(key)));
+                    write_yaml(field, indent + 2, out);
+                } else {
+                    out.push_str(&format!("{}{}: {}\n", pad, yaml_string(key), yaml_scalar(field)));
+                }
+            }
+        }
+        Value::Array(items) if !items.is_empty() => {
+            for item in items {
+                if is_yaml_block(item) {
+                    // Render one level deeper, then let the dash take the place of the first indent
+                    let mut nested = String::new();
+                    write_yaml(item, indent + 2, &mut nested);
+                    out.push_str(&format!("{}- {}", pad, &nested[indent + 2..]));
+                } else {
+                    out.push_str(&format!("{}- {}\n", pad, yaml_scalar(item)));
+                }
+            }
+        }
+        scalar => out.push_str(&format!("{}{}\n", pad, yaml_scalar(scalar))),
+    }
+}
+
+fn is_yaml_block(value: &Value) -> bool {
+    match value {
+        Value::Object(fields) => !fields.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        _ => false,
+    }
+}
+
+fn yaml_scalar(value: &Value) -> String {
+    match value {
+        Value::String(text) => yaml_string(text),
+        Value::Array(_) => "[]".to_string(),
+        Value::Object(_) => "{}".to_string(),
+        other => other.to_display_string(),
+    }
+}
+
+/// Plain text when YAML would read it back as the same string, JSON-quoted otherwise
+fn yaml_string(text: &str) -> String {
+    let reserved = ["true", "false", "null", "yes", "no", "on", "off", "~"];
+    let needs_quotes = text.is_empty()
+        || text.trim() != text
+        || text.parse::<f64>().is_ok()
+        || reserved.contains(&text.to_lowercase().as_str())
+        || text.starts_with(['-', '?', '!', '&', '*', '|', '>', '%', '@', '`', '\'', '"'])
+        || text.contains([':', '#', '{', '}', '[', ']', ',', '\n', '\r', '\t']);
+
+    if needs_quotes {
+        serde_json::to_string(text).unwrap_or_default()
+    } else {
+        text.to_string()
+    }
+}
+
+/// Format a number, dropping the trailing `.0` for integral values
+pub fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,395 @@
+use crate::ast::*;
+
+/// Read-only traversal of a program. Every `visit_*` method walks into the node's
+/// children by default, so a visitor overrides only the nodes it cares about and
+/// calls the matching `walk_*` function when it still wants to descend.
+pub trait Visitor {
+    fn visit_program(&mut self, program: &Program) {
+        walk_program(self, program);
+    }
+
+    fn visit_workflow(&mut self, workflow: &Workflow) {
+        walk_workflow(self, workflow);
+    }
+
+    fn visit_variable(&mut self, variable: &VariableDeclaration) {
+        walk_variable(self, variable);
+    }
+
+    fn visit_function(&mut self, function: &FunctionDeclaration) {
+        walk_function(self, function);
+    }
+
+    fn visit_error_handler(&mut self, handler: &ErrorHandler) {
+        walk_error_handler(self, handler);
+    }
+
+    fn visit_step(&mut self, step: &Step) {
+        walk_step(self, step);
+    }
+
+    fn visit_command(&mut self, command: &Command) {
+        walk_command(self, command);
+    }
+
+    fn visit_conditional(&mut self, conditional: &ConditionalStatement) {
+        walk_conditional(self, conditional);
+    }
+
+    fn visit_while_loop(&mut self, while_loop: &WhileLoop) {
+        walk_while_loop(self, while_loop);
+    }
+
+    fn visit_for_each(&mut self, for_each: &ForEach) {
+        walk_for_each(self, for_each);
+    }
+
+    fn visit_try_catch(&mut self, try_catch: &TryCatch) {
+        walk_try_catch(self, try_catch);
+    }
+
+    fn visit_call_workflow(&mut self, _call: &CallWorkflow) {}
+
+    fn visit_return(&mut self, return_statement: &ReturnStatement) {
+        walk_return(self, return_statement);
+    }
+
+    fn visit_assignment(&mut self, assignment: &Assignment) {
+        walk_assignment(self, assignment);
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        walk_expression(self, expression);
+    }
+}
+
+pub fn walk_program<V: Visitor + ?Sized>(visitor: &mut V, program: &Program) {
+    for variable in &program.variables {
+        visitor.visit_variable(variable);
+    }
+    for function in &program.functions {
+        visitor.visit_function(function);
+    }
+    for workflow in &program.workflows {
+        visitor.visit_workflow(workflow);
+    }
+}
+
+pub fn walk_workflow<V: Visitor + ?Sized>(visitor: &mut V, workflow: &Workflow) {
+    for variable in &workflow.variables {
+        visitor.visit_variable(variable);
+    }
+    for step in &workflow.steps {
+        visitor.visit_step(step);
+    }
+    if let Some(handler) = &workflow.on_error {
+        visitor.visit_error_handler(handler);
+    }
+}
+
+pub fn walk_variable<V: Visitor + ?Sized>(visitor: &mut V, variable: &VariableDeclaration) {
+    visitor.visit_expression(&variable.value);
+}
+
+pub fn walk_function<V: Visitor + ?Sized>(visitor: &mut V, function: &FunctionDeclaration) {
+    visitor.visit_expression(&function.body);
+}
+
+pub fn walk_error_handler<V: Visitor + ?Sized>(visitor: &mut V, handler: &ErrorHandler) {
+    for step in &handler.steps {
+        visitor.visit_step(step);
+    }
+}
+
+pub fn walk_step<V: Visitor + ?Sized>(visitor: &mut V, step: &Step) {
+    if let Some(guard) = &step.guard {
+        visitor.visit_expression(guard);
+    }
+    match &step.content {
+        StepContent::Command(command) => visitor.visit_command(command),
+        StepContent::Conditional(conditional) => visitor.visit_conditional(conditional),
+        StepContent::WhileLoop(while_loop) => visitor.visit_while_loop(while_loop),
+        StepContent::ForEach(for_each) => visitor.visit_for_each(for_each),
+        StepContent::TryCatch(try_catch) => visitor.visit_try_catch(try_catch),
+        StepContent::CallWorkflow(call) => visitor.visit_call_workflow(call),
+        StepContent::Return(return_statement) => visitor.visit_return(return_statement),
+        StepContent::Assignment(assignment) => visitor.visit_assignment(assignment),
+        StepContent::Declaration(variable) => visitor.visit_variable(variable),
+    }
+}
+
+pub fn walk_command<V: Visitor + ?Sized>(visitor: &mut V, command: &Command) {
+    for argument in command.arguments.iter().chain(command.named_arguments.values()) {
+        visitor.visit_expression(argument);
+    }
+}
+
+pub fn walk_conditional<V: Visitor + ?Sized>(visitor: &mut V, conditional: &ConditionalStatement) {
+    visitor.visit_expression(&conditional.condition);
+    for step in &conditional.if_steps {
+        visitor.visit_step(step);
+    }
+    for step in conditional.else_steps.iter().flatten() {
+        visitor.visit_step(step);
+    }
+}
+
+pub fn walk_while_loop<V: Visitor + ?Sized>(visitor: &mut V, while_loop: &WhileLoop) {
+    visitor.visit_expression(&while_loop.condition);
+    for step in &while_loop.body {
+        visitor.visit_step(step);
+    }
+}
+
+pub fn walk_for_each<V: Visitor + ?Sized>(visitor: &mut V, for_each: &ForEach) {
+    visitor.visit_expression(&for_each.iterable);
+    for step in &for_each.body {
+        visitor.visit_step(step);
+    }
+}
+
+pub fn walk_try_catch<V: Visitor + ?Sized>(visitor: &mut V, try_catch: &TryCatch) {
+    for step in try_catch.try_steps.iter().chain(&try_catch.catch_steps) {
+        visitor.visit_step(step);
+    }
+}
+
+pub fn walk_return<V: Visitor + ?Sized>(visitor: &mut V, return_statement: &ReturnStatement) {
+    if let Some(value) = &return_statement.value {
+        visitor.visit_expression(value);
+    }
+}
+
+pub fn walk_assignment<V: Visitor + ?Sized>(visitor: &mut V, assignment: &Assignment) {
+    visitor.visit_expression(&assignment.value);
+}
+
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, expression: &Expression) {
+    match &expression.kind {
+        ExpressionKind::ArrayLiteral(elements) => {
+            for element in elements {
+                visitor.visit_expression(element);
+            }
+        }
+        ExpressionKind::BinaryExpression { left, right, .. } => {
+            visitor.visit_expression(left);
+            visitor.visit_expression(right);
+        }
+        ExpressionKind::UnaryExpression { operand, .. } => visitor.visit_expression(operand),
+        ExpressionKind::PropertyAccess { object, .. } => visitor.visit_expression(object),
+        ExpressionKind::Interpolation(parts) => {
+            for expression in parts.iter().filter_map(InterpolationPart::expression) {
+                visitor.visit_expression(expression);
+            }
+        }
+        ExpressionKind::Call { callee, arguments } => {
+            visitor.visit_expression(callee);
+            for argument in arguments {
+                visitor.visit_expression(argument);
+            }
+        }
+        ExpressionKind::StringLiteral(_)
+        | ExpressionKind::NumberLiteral(_)
+        | ExpressionKind::Identifier(_)
+        | ExpressionKind::StepReference { .. }
+        | ExpressionKind::PreviousStep { .. }
+        | ExpressionKind::WorkflowReference { .. }
+        | ExpressionKind::EnvironmentVariable { .. } => {}
+    }
+}
+
+/// Traversal that can rewrite nodes in place, mirroring `Visitor`
+pub trait VisitorMut {
+    fn visit_program_mut(&mut self, program: &mut Program) {
+        walk_program_mut(self, program);
+    }
+
+    fn visit_workflow_mut(&mut self, workflow: &mut Workflow) {
+        walk_workflow_mut(self, workflow);
+    }
+
+    fn visit_variable_mut(&mut self, variable: &mut VariableDeclaration) {
+        walk_variable_mut(self, variable);
+    }
+
+    fn visit_function_mut(&mut self, function: &mut FunctionDeclaration) {
+        walk_function_mut(self, function);
+    }
+
+    fn visit_error_handler_mut(&mut self, handler: &mut ErrorHandler) {
+        walk_error_handler_mut(self, handler);
+    }
+
+    fn visit_step_mut(&mut self, step: &mut Step) {
+        walk_step_mut(self, step);
+    }
+
+    fn visit_command_mut(&mut self, command: &mut Command) {
+        walk_command_mut(self, command);
+    }
+
+    fn visit_conditional_mut(&mut self, conditional: &mut ConditionalStatement) {
+        walk_conditional_mut(self, conditional);
+    }
+
+    fn visit_while_loop_mut(&mut self, while_loop: &mut WhileLoop) {
+        walk_while_loop_mut(self, while_loop);
+    }
+
+    fn visit_for_each_mut(&mut self, for_each: &mut ForEach) {
+        walk_for_each_mut(self, for_each);
+    }
+
+    fn visit_try_catch_mut(&mut self, try_catch: &mut TryCatch) {
+        walk_try_catch_mut(self, try_catch);
+    }
+
+    fn visit_call_workflow_mut(&mut self, _call: &mut CallWorkflow) {}
+
+    fn visit_return_mut(&mut self, return_statement: &mut ReturnStatement) {
+        walk_return_mut(self, return_statement);
+    }
+
+    fn visit_assignment_mut(&mut self, assignment: &mut Assignment) {
+        walk_assignment_mut(self, assignment);
+    }
+
+    fn visit_expression_mut(&mut self, expression: &mut Expression) {
+        walk_expression_mut(self, expression);
+    }
+}
+
+pub fn walk_program_mut<V: VisitorMut + ?Sized>(visitor: &mut V, program: &mut Program) {
+    for variable in &mut program.variables {
+        visitor.visit_variable_mut(variable);
+    }
+    for function in &mut program.functions {
+        visitor.visit_function_mut(function);
+    }
+    for workflow in &mut program.workflows {
+        visitor.visit_workflow_mut(workflow);
+    }
+}
+
+pub fn walk_workflow_mut<V: VisitorMut + ?Sized>(visitor: &mut V, workflow: &mut Workflow) {
+    for variable in &mut workflow.variables {
+        visitor.visit_variable_mut(variable);
+    }
+    for step in &mut workflow.steps {
+        visitor.visit_step_mut(step);
+    }
+    if let Some(handler) = &mut workflow.on_error {
+        visitor.visit_error_handler_mut(handler);
+    }
+}
+
+pub fn walk_variable_mut<V: VisitorMut + ?Sized>(visitor: &mut V, variable: &mut VariableDeclaration) {
+    visitor.visit_expression_mut(&mut variable.value);
+}
+
+pub fn walk_function_mut<V: VisitorMut + ?Sized>(visitor: &mut V, function: &mut FunctionDeclaration) {
+    visitor.visit_expression_mut(&mut function.body);
+}
+
+pub fn walk_error_handler_mut<V: VisitorMut + ?Sized>(visitor: &mut V, handler: &mut ErrorHandler) {
+    for step in &mut handler.steps {
+        visitor.visit_step_mut(step);
+    }
+}
+
+pub fn walk_step_mut<V: VisitorMut + ?Sized>(visitor: &mut V, step: &mut Step) {
+    if let Some(guard) = &mut step.guard {
+        visitor.visit_expression_mut(guard);
+    }
+    match &mut step.content {
+        StepContent::Command(command) => visitor.visit_command_mut(command),
+        StepContent::Conditional(conditional) => visitor.visit_conditional_mut(conditional),
+        StepContent::WhileLoop(while_loop) => visitor.visit_while_loop_mut(while_loop),
+        StepContent::ForEach(for_each) => visitor.visit_for_each_mut(for_each),
+        StepContent::TryCatch(try_catch) => visitor.visit_try_catch_mut(try_catch),
+        StepContent::CallWorkflow(call) => visitor.visit_call_workflow_mut(call),
+        StepContent::Return(return_statement) => visitor.visit_return_mut(return_statement),
+        StepContent::Assignment(assignment) => visitor.visit_assignment_mut(assignment),
+        StepContent::Declaration(variable) => visitor.visit_variable_mut(variable),
+    }
+}
+
+pub fn walk_command_mut<V: VisitorMut + ?Sized>(visitor: &mut V, command: &mut Command) {
+    for argument in command.arguments.iter_mut().chain(command.named_arguments.values_mut()) {
+        visitor.visit_expression_mut(argument);
+    }
+}
+
+pub fn walk_conditional_mut<V: VisitorMut + ?Sized>(visitor: &mut V, conditional: &mut ConditionalStatement) {
+    visitor.visit_expression_mut(&mut conditional.condition);
+    for step in &mut conditional.if_steps {
+        visitor.visit_step_mut(step);
+    }
+    for step in conditional.else_steps.iter_mut().flatten() {
+        visitor.visit_step_mut(step);
+    }
+}
+
+pub fn walk_while_loop_mut<V: VisitorMut + ?Sized>(visitor: &mut V, while_loop: &mut WhileLoop) {
+    visitor.visit_expression_mut(&mut while_loop.condition);
+    for step in &mut while_loop.body {
+        visitor.visit_step_mut(step);
+    }
+}
+
+pub fn walk_for_each_mut<V: VisitorMut + ?Sized>(visitor: &mut V, for_each: &mut ForEach) {
+    visitor.visit_expression_mut(&mut for_each.iterable);
+    for step in &mut for_each.body {
+        visitor.visit_step_mut(step);
+    }
+}
+
+pub fn walk_try_catch_mut<V: VisitorMut + ?Sized>(visitor: &mut V, try_catch: &mut TryCatch) {
+    for step in try_catch.try_steps.iter_mut().chain(&mut try_catch.catch_steps) {
+        visitor.visit_step_mut(step);
+    }
+}
+
+pub fn walk_return_mut<V: VisitorMut + ?Sized>(visitor: &mut V, return_statement: &mut ReturnStatement) {
+    if let Some(value) = &mut return_statement.value {
+        visitor.visit_expression_mut(value);
+    }
+}
+
+pub fn walk_assignment_mut<V: VisitorMut + ?Sized>(visitor: &mut V, assignment: &mut Assignment) {
+    visitor.visit_expression_mut(&mut assignment.value);
+}
+
+pub fn walk_expression_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expression: &mut Expression) {
+    match &mut expression.kind {
+        ExpressionKind::ArrayLiteral(elements) => {
+            for element in elements {
+                visitor.visit_expression_mut(element);
+            }
+        }
+        ExpressionKind::BinaryExpression { left, right, .. } => {
+            visitor.visit_expression_mut(left);
+            visitor.visit_expression_mut(right);
+        }
+        ExpressionKind::UnaryExpression { operand, .. } => visitor.visit_expression_mut(operand),
+        ExpressionKind::PropertyAccess { object, .. } => visitor.visit_expression_mut(object),
+        ExpressionKind::Interpolation(parts) => {
+            for expression in parts.iter_mut().filter_map(InterpolationPart::expression_mut) {
+                visitor.visit_expression_mut(expression);
+            }
+        }
+        ExpressionKind::Call { callee, arguments } => {
+            visitor.visit_expression_mut(callee);
+            for argument in arguments {
+                visitor.visit_expression_mut(argument);
+            }
+        }
+        ExpressionKind::StringLiteral(_)
+        | ExpressionKind::NumberLiteral(_)
+        | ExpressionKind::Identifier(_)
+        | ExpressionKind::StepReference { .. }
+        | ExpressionKind::PreviousStep { .. }
+        | ExpressionKind::WorkflowReference { .. }
+        | ExpressionKind::EnvironmentVariable { .. } => {}
+    }
+}